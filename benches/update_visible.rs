@@ -0,0 +1,72 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+
+use celestial::comparison;
+use celestial::pathdata::Path;
+use celestial::pathlog::PathLog;
+
+// synthetic comparisons to see how inserting paths and update_visible scale with the number of paths
+
+const SIZES: [usize; 4] = [100, 1_000, 10_000, 50_000];
+const PATHS_PER_COLLECTION: usize = 100;
+const NODES_PER_PATH: usize = 16;
+
+// xorshift, good enough for fake run times
+fn next_random(state: &mut u64) -> u64 {
+    *state ^= *state << 13;
+    *state ^= *state >> 7;
+    *state ^= *state << 17;
+    *state
+}
+
+fn synthetic_pathlog(size: usize) -> PathLog {
+    let mut pathlog = PathLog::default();
+    let mut rng = 0x2545F4914F6CDD1D_u64 ^ size as u64;
+
+    for i in 0..size {
+        if i % PATHS_PER_COLLECTION == 0 {
+            pathlog.create_collection();
+        }
+        let collection_id = pathlog.collections().last().unwrap().id();
+
+        let mut path = Path::new();
+        for n in 0..NODES_PER_PATH {
+            path.add_node([n as f32, (next_random(&mut rng) % 100) as f32 * 0.01, 0.]);
+        }
+        path.end_path(20_000 + next_random(&mut rng) % 40_000);
+
+        pathlog.insert_path(path, collection_id).unwrap();
+    }
+
+    pathlog
+}
+
+fn insert(c: &mut Criterion) {
+    let mut group = c.benchmark_group("insert_path");
+    group.sample_size(10);
+
+    for size in SIZES {
+        group.bench_with_input(BenchmarkId::from_parameter(size), &size, |b, size| b.iter(|| synthetic_pathlog(*size)));
+    }
+
+    group.finish();
+}
+
+fn update_visible(c: &mut Criterion) {
+    let mut group = c.benchmark_group("update_visible");
+
+    for size in SIZES {
+        let mut pathlog = synthetic_pathlog(size);
+
+        for strategy in comparison::strategies() {
+            let name = strategy.name();
+            pathlog.set_comparison_strategy(strategy);
+
+            group.bench_with_input(BenchmarkId::new(name, size), &size, |b, _| b.iter(|| pathlog.update_visible()));
+        }
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, insert, update_visible);
+criterion_main!(benches);
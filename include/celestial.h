@@ -0,0 +1,41 @@
+// C API of celestial.dll for other NieR:Automata mods.
+// celestial is installed as d3d11.dll, dxgi.dll or dinput8.dll, so find the module that exports
+// celestial_api_version (GetModuleHandleA + GetProcAddress) and get the functions from there.
+// Check celestial_api_version() before using functions added in later versions.
+
+#ifndef CELESTIAL_H
+#define CELESTIAL_H
+
+#include <stdbool.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef void (*celestial_finished_callback)(uint64_t time_ms, void* user_data);
+
+// version 1
+uint32_t celestial_api_version(void);
+
+// milliseconds, the last finished time while not recording
+uint64_t celestial_get_time(void);
+bool celestial_is_recording(void);
+// 0 if nothing was recorded yet
+uint64_t celestial_last_path_time(void);
+
+// run on the next frame, same as the keybinds
+void celestial_start_recording(void);
+void celestial_stop_recording(void);
+void celestial_reset_recording(void);
+
+// called on the render thread whenever a recording stops, at the end trigger or by hand (celestial_stop_recording,
+// the stop keybind). time_ms is the recorded time. returns a handle, 0 on failure
+uint32_t celestial_register_finished_callback(celestial_finished_callback callback, void* user_data);
+bool celestial_unregister_finished_callback(uint32_t handle);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
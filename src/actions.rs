@@ -0,0 +1,206 @@
+use std::collections::BTreeMap;
+use egui::{Key, KeyboardShortcut, Modifiers};
+use egui_keybind::Shortcut;
+
+use crate::config::CompareKeybindToEvent;
+use crate::events::CelEvent;
+use crate::gamedata;
+use crate::ui::TeleportIndex;
+
+// everything that can be bound to a key. adding an action here makes it show up in the config tab,
+// get saved in the profile's keybinds section and fire from check_input
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Action {
+    Start,
+    Stop,
+    Reset,
+    ClearTriggers,
+    TeleportStart,
+    TeleportEnd,
+    TeleportLocation1,
+    TeleportLocation2,
+    SetLocation1,
+    SetLocation2,
+    DeleteLocation1,
+    DeleteLocation2,
+    TogglePause,
+    NextComparisonMode,
+    CreateCollection,
+    SaveComparison,
+    NextCollection,
+    PreviousCollection,
+}
+
+impl Action {
+    pub const ALL : [Action; 18] = [
+        Action::Start,
+        Action::Stop,
+        Action::Reset,
+        Action::ClearTriggers,
+        Action::TeleportStart,
+        Action::TeleportEnd,
+        Action::TeleportLocation1,
+        Action::TeleportLocation2,
+        Action::SetLocation1,
+        Action::SetLocation2,
+        Action::DeleteLocation1,
+        Action::DeleteLocation2,
+        Action::TogglePause,
+        Action::NextComparisonMode,
+        Action::CreateCollection,
+        Action::SaveComparison,
+        Action::NextCollection,
+        Action::PreviousCollection,
+    ];
+
+    // key in the config file, don't change these
+    pub fn name(self) -> &'static str {
+        match self {
+            Action::Start => "start",
+            Action::Stop => "stop",
+            Action::Reset => "reset",
+            Action::ClearTriggers => "clear_triggers",
+            Action::TeleportStart => "teleport_start",
+            Action::TeleportEnd => "teleport_end",
+            Action::TeleportLocation1 => "teleport_location_1",
+            Action::TeleportLocation2 => "teleport_location_2",
+            Action::SetLocation1 => "set_location_1",
+            Action::SetLocation2 => "set_location_2",
+            Action::DeleteLocation1 => "delete_location_1",
+            Action::DeleteLocation2 => "delete_location_2",
+            Action::TogglePause => "toggle_pause",
+            Action::NextComparisonMode => "next_comparison_mode",
+            Action::CreateCollection => "create_collection",
+            Action::SaveComparison => "save_comparison",
+            Action::NextCollection => "next_collection",
+            Action::PreviousCollection => "previous_collection",
+        }
+    }
+
+    pub fn label(self, direct_mode: bool) -> &'static str {
+        match self {
+            Action::Start if direct_mode => "Start Recording",
+            Action::Start => "Spawn Start Trigger",
+            Action::Stop if direct_mode => "Stop Recording",
+            Action::Stop => "Spawn End Trigger",
+            Action::Reset => "Reset Recording",
+            Action::ClearTriggers => "Delete Triggers",
+            Action::TeleportStart => "Teleport to Start Trigger",
+            Action::TeleportEnd => "Teleport to End Trigger",
+            Action::TeleportLocation1 => "Teleport to Location 1",
+            Action::TeleportLocation2 => "Teleport to Location 2",
+            Action::SetLocation1 => "Set Location 1",
+            Action::SetLocation2 => "Set Location 2",
+            Action::DeleteLocation1 => "Delete Location 1",
+            Action::DeleteLocation2 => "Delete Location 2",
+            Action::TogglePause => "Pause Recording",
+            Action::NextComparisonMode => "Next Comparison Mode",
+            Action::CreateCollection => "Create Collection",
+            Action::SaveComparison => "Save Comparison",
+            Action::NextCollection => "Activate Next Collection",
+            Action::PreviousCollection => "Activate Previous Collection",
+        }
+    }
+
+    fn default_shortcut(self) -> Shortcut {
+        let (modifiers, key) = match self {
+            Action::Start => (Modifiers::NONE, Key::Comma),
+            Action::Stop => (Modifiers::NONE, Key::Period),
+            Action::Reset => (Modifiers::NONE, Key::Minus),
+            Action::ClearTriggers => (Modifiers::NONE, Key::Delete),
+            Action::TeleportStart => (Modifiers::NONE, Key::K),
+            Action::TeleportEnd => (Modifiers::NONE, Key::L),
+            Action::TeleportLocation1 => (Modifiers::NONE, Key::Num1),
+            Action::TeleportLocation2 => (Modifiers::NONE, Key::Num2),
+            Action::SetLocation1 => (Modifiers::SHIFT, Key::Num1),
+            Action::SetLocation2 => (Modifiers::SHIFT, Key::Num2),
+            Action::DeleteLocation1 => (Modifiers::ALT, Key::Num1),
+            Action::DeleteLocation2 => (Modifiers::ALT, Key::Num2),
+            // unbound until the player picks one
+            _ => return Shortcut::new(None, None),
+        };
+
+        Shortcut::new(Some(KeyboardShortcut { modifiers, logical_key: key }), None)
+    }
+
+    pub fn event(self, direct_mode: bool) -> CelEvent {
+        match self {
+            Action::Start if direct_mode => CelEvent::StartRecording,
+            Action::Start => CelEvent::SpawnTrigger {
+                index: 0,
+                position: gamedata::get_player_position(),
+                rotation: gamedata::get_player_rotation(),
+            },
+            Action::Stop if direct_mode => CelEvent::StopRecording,
+            Action::Stop => CelEvent::SpawnTrigger {
+                index: 1,
+                position: gamedata::get_player_position(),
+                rotation: gamedata::get_player_rotation(),
+            },
+            Action::Reset => CelEvent::ResetRecording,
+            Action::ClearTriggers => CelEvent::ClearTriggers,
+            Action::TeleportStart => CelEvent::Teleport { index: TeleportIndex::Main { i: 0 } },
+            Action::TeleportEnd => CelEvent::Teleport { index: TeleportIndex::Main { i: 1 } },
+            Action::TeleportLocation1 => CelEvent::Teleport { index: TeleportIndex::Extra { i: 0 } },
+            Action::TeleportLocation2 => CelEvent::Teleport { index: TeleportIndex::Extra { i: 1 } },
+            Action::SetLocation1 => CelEvent::SpawnTeleport { index: TeleportIndex::Extra { i: 0 } },
+            Action::SetLocation2 => CelEvent::SpawnTeleport { index: TeleportIndex::Extra { i: 1 } },
+            Action::DeleteLocation1 => CelEvent::DeleteTeleport { index: TeleportIndex::Extra { i: 0 } },
+            Action::DeleteLocation2 => CelEvent::DeleteTeleport { index: TeleportIndex::Extra { i: 1 } },
+            Action::TogglePause => CelEvent::TogglePause,
+            Action::NextComparisonMode => CelEvent::NextComparisonMode,
+            Action::CreateCollection => CelEvent::CreateCollection,
+            Action::SaveComparison => CelEvent::SaveComparison,
+            Action::NextCollection => CelEvent::CycleActiveCollection { forward: true },
+            Action::PreviousCollection => CelEvent::CycleActiveCollection { forward: false },
+        }
+    }
+}
+
+#[derive(Clone, PartialEq)]
+pub struct Keybinds {
+    binds: BTreeMap<Action, Shortcut>,
+}
+
+impl Keybinds {
+    pub fn new() -> Keybinds {
+        Keybinds { binds: Action::ALL.into_iter().map(|action| (action, action.default_shortcut())).collect() }
+    }
+
+    pub fn get(&self, action: Action) -> Shortcut {
+        self.binds.get(&action).copied().unwrap_or(Shortcut::new(None, None))
+    }
+
+    pub fn set(&mut self, action: Action, shortcut: Shortcut) {
+        self.binds.insert(action, shortcut);
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (Action, Shortcut)> + '_ {
+        self.binds.iter().map(|(action, shortcut)| (*action, *shortcut))
+    }
+
+    // the other actions on the same shortcut
+    pub fn conflicts(&self, action: Action) -> Vec<Action> {
+        let shortcut = self.get(action);
+        if shortcut.keyboard().is_none() { return Vec::new(); }
+
+        self.iter()
+            .filter(|(other, other_shortcut)| *other != action && *other_shortcut == shortcut)
+            .map(|(other, _)| other)
+            .collect()
+    }
+
+    // every action bound to the key of an input event
+    pub fn triggered<'a>(&'a self, event: &'a egui::Event) -> impl Iterator<Item = Action> + 'a {
+        self.iter()
+            .filter(|(_, shortcut)| shortcut.compare_to_event(event))
+            .map(|(action, _)| action)
+    }
+}
+
+impl Default for Keybinds {
+    fn default() -> Self {
+        Keybinds::new()
+    }
+}
@@ -0,0 +1,270 @@
+use std::io::{BufRead, BufReader, ErrorKind, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use serde_json::{json, Value};
+use tracing::{error, info};
+use uuid::Uuid;
+
+use crate::events::CelEvent;
+use crate::recording::RecordingEvent;
+use crate::ui::TeleportIndex;
+use crate::{EVENTS, PATHLOG};
+
+// line delimited json-rpc 2.0 on localhost, for overlays and other tools.
+//   get_state                          timer, recording state and the active collection
+//   get_recent_times {count}           latest recorded paths, newest first
+//   subscribe                          sends a "recording_event" notification for every event in recording.rs
+//   start_recording, stop_recording, reset_recording, create_collection
+//   toggle_active_collection {id}
+//   teleport {main: 0..2} or {extra: 0..10}
+
+pub const DEFAULT_PORT : u16 = 16835;
+const POLL_INTERVAL : Duration = Duration::from_millis(100);
+const MAX_RECENT_TIMES : usize = 100;
+
+// json-rpc error codes
+const PARSE_ERROR : i64 = -32700;
+const INVALID_REQUEST : i64 = -32600;
+const METHOD_NOT_FOUND : i64 = -32601;
+const INVALID_PARAMS : i64 = -32602;
+
+#[derive(Clone, PartialEq)]
+pub struct ApiSettings {
+    pub enabled: bool,
+    pub port: u16,
+}
+
+pub struct Api {
+    settings: Option<ApiSettings>,
+    // tells the listener and all clients to stop
+    stop: Arc<AtomicBool>,
+    subscribers: Arc<Mutex<Vec<Sender<String>>>>,
+}
+
+impl Api {
+    pub fn new() -> Api {
+        Api {
+            settings: None,
+            stop: Arc::new(AtomicBool::new(false)),
+            subscribers: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+
+    pub fn configure(&mut self, settings: &ApiSettings) {
+        if self.settings.as_ref() == Some(settings) { return; }
+
+        self.settings = Some(settings.clone());
+        self.stop.store(true, Ordering::Relaxed);
+        self.stop = Arc::new(AtomicBool::new(false));
+        self.subscribers = Arc::new(Mutex::new(Vec::new()));
+
+        if !settings.enabled { return; }
+
+        let listener = match TcpListener::bind(("127.0.0.1", settings.port)) {
+            Ok(listener) => listener,
+            Err(e) => {
+                error!("Failed to start the API on port {}: {e}", settings.port);
+                return;
+            }
+        };
+
+        if let Err(e) = listener.set_nonblocking(true) {
+            error!("{e}");
+            return;
+        }
+
+        info!("API listening on port {}", settings.port);
+
+        let stop = self.stop.clone();
+        let subscribers = self.subscribers.clone();
+
+        std::thread::spawn(move || listen(listener, stop, subscribers));
+    }
+
+    pub fn broadcast(&self, event: &RecordingEvent) {
+        let mut subscribers = self.subscribers.lock().unwrap();
+        if subscribers.is_empty() { return; }
+
+        let notification = json!({
+            "jsonrpc": "2.0",
+            "method": "recording_event",
+            "params": event,
+        }).to_string();
+
+        // clients that disconnected are dropped here
+        subscribers.retain(|tx| tx.send(notification.clone()).is_ok());
+    }
+}
+
+impl Default for Api {
+    fn default() -> Self {
+        Api::new()
+    }
+}
+
+fn listen(listener: TcpListener, stop: Arc<AtomicBool>, subscribers: Arc<Mutex<Vec<Sender<String>>>>) {
+    while !stop.load(Ordering::Relaxed) {
+        match listener.accept() {
+            Ok((stream, address)) => {
+                info!("API client connected: {address}");
+                let stop = stop.clone();
+                let subscribers = subscribers.clone();
+                std::thread::spawn(move || serve_client(stream, stop, subscribers));
+            }
+            Err(e) if e.kind() == ErrorKind::WouldBlock => std::thread::sleep(POLL_INTERVAL),
+            Err(e) => {
+                error!("API: {e}");
+                std::thread::sleep(POLL_INTERVAL);
+            }
+        }
+    }
+}
+
+fn serve_client(stream: TcpStream, stop: Arc<AtomicBool>, subscribers: Arc<Mutex<Vec<Sender<String>>>>) {
+    // accepted sockets can inherit non blocking mode from the listener
+    let _ = stream.set_nonblocking(false);
+    let _ = stream.set_read_timeout(Some(POLL_INTERVAL));
+
+    let Ok(mut writer) = stream.try_clone() else { return; };
+
+    // responses and notifications both go through here so their lines don't get mixed up
+    let (tx, rx) = mpsc::channel::<String>();
+
+    std::thread::spawn(move || {
+        while let Ok(line) = rx.recv() {
+            if writer.write_all(format!("{line}\n").as_bytes()).is_err() { break; }
+        }
+    });
+
+    let mut reader = BufReader::new(stream);
+    let mut line = String::new();
+
+    while !stop.load(Ordering::Relaxed) {
+        match reader.read_line(&mut line) {
+            Ok(0) => break,
+            Ok(_) => {
+                match handle_line(line.trim(), &tx, &subscribers) {
+                    Ok(Some(response)) => if tx.send(response.to_string()).is_err() { break; },
+                    Ok(None) => {}
+                    Err(response) => {
+                        let _ = tx.send(response.to_string());
+                        break;
+                    }
+                }
+                line.clear();
+            }
+            // read timeout, check if we should stop. a partial line stays in the buffer
+            Err(e) if e.kind() == ErrorKind::WouldBlock || e.kind() == ErrorKind::TimedOut => {}
+            Err(_) => break,
+        }
+    }
+}
+
+// Err closes the connection after sending the response. anything that isn't json-rpc ends it right away,
+// otherwise a web page could send an http request here and have a line of its body run as a command
+fn handle_line(line: &str, tx: &Sender<String>, subscribers: &Arc<Mutex<Vec<Sender<String>>>>) -> Result<Option<Value>, Value> {
+    if line.is_empty() { return Ok(None); }
+
+    let request : Value = match serde_json::from_str(line) {
+        Ok(request) => request,
+        Err(e) => return Err(error_response(Value::Null, PARSE_ERROR, &e.to_string())),
+    };
+
+    if request.get("jsonrpc").and_then(Value::as_str) != Some("2.0") {
+        return Err(error_response(Value::Null, INVALID_REQUEST, "expected a json-rpc 2.0 request"));
+    }
+
+    let Some(method) = request.get("method").and_then(Value::as_str) else {
+        return Err(error_response(Value::Null, INVALID_REQUEST, "expected a method"));
+    };
+
+    let id = request.get("id").cloned();
+    let params = request.get("params").cloned().unwrap_or(Value::Null);
+
+    let result = match method {
+        "subscribe" => {
+            subscribers.lock().unwrap().push(tx.clone());
+            Ok(json!(true))
+        }
+        _ => call(method, &params),
+    };
+
+    // requests without an id are notifications and get no answer
+    let Some(id) = id else { return Ok(None); };
+
+    Ok(Some(match result {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err((code, message)) => error_response(id, code, &message),
+    }))
+}
+
+fn error_response(id: Value, code: i64, message: &str) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "error": { "code": code, "message": message } })
+}
+
+fn push_event(event: CelEvent) -> Result<Value, (i64, String)> {
+    EVENTS.lock().unwrap().push_back(event);
+    Ok(json!(true))
+}
+
+fn call(method: &str, params: &Value) -> Result<Value, (i64, String)> {
+    match method {
+        "get_state" => {
+            let pathlog = PATHLOG.lock().unwrap();
+
+            let active_collection = pathlog.active_collection
+                .and_then(|id| pathlog.get_collection(id))
+                .map(|c| json!({ "id": c.id(), "name": c.name, "paths": c.paths().len() }));
+
+            Ok(json!({
+                "recording": pathlog.is_recording(),
+                "paused": pathlog.is_paused(),
+                "time": pathlog.time(),
+                "active_collection": active_collection,
+                "collections": pathlog.collections().len(),
+            }))
+        }
+        "get_recent_times" => {
+            let count = params.get("count").and_then(Value::as_u64).unwrap_or(10).min(MAX_RECENT_TIMES as u64) as usize;
+            let pathlog = PATHLOG.lock().unwrap();
+
+            let times : Vec<Value> = pathlog.recent_paths(count).iter().map(|path| {
+                let collection = pathlog.collections().iter().find(|c| c.paths().contains(&path.id())).map(|c| c.id());
+                json!({
+                    "id": path.id(),
+                    "time": path.time(),
+                    "recorded_at": path.meta().recorded_at,
+                    "collection": collection,
+                })
+            }).collect();
+
+            Ok(json!(times))
+        }
+        "start_recording" => push_event(CelEvent::StartRecording),
+        "stop_recording" => push_event(CelEvent::StopRecording),
+        "reset_recording" => push_event(CelEvent::ResetRecording),
+        "create_collection" => push_event(CelEvent::CreateCollection),
+        "toggle_active_collection" => {
+            let Some(id) = params.get("id").and_then(Value::as_str).and_then(|id| Uuid::parse_str(id).ok()) else {
+                return Err((INVALID_PARAMS, "expected a collection id".to_string()));
+            };
+            if PATHLOG.lock().unwrap().get_collection(id).is_none() {
+                return Err((INVALID_PARAMS, "no collection with that id".to_string()));
+            }
+            push_event(CelEvent::ToggleActive { id })
+        }
+        "teleport" => {
+            let index = match (params.get("main").and_then(Value::as_u64), params.get("extra").and_then(Value::as_u64)) {
+                (Some(i), None) if i < 2 => TeleportIndex::Main { i: i as usize },
+                (None, Some(i)) if i < 10 => TeleportIndex::Extra { i: i as usize },
+                _ => return Err((INVALID_PARAMS, "expected main (0-1) or extra (0-9)".to_string())),
+            };
+            push_event(CelEvent::Teleport { index })
+        }
+        _ => Err((METHOD_NOT_FOUND, format!("unknown method '{method}'"))),
+    }
+}
@@ -0,0 +1,116 @@
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::date;
+use crate::error::Error;
+use crate::pathdata::FILE_EXTENTION;
+
+pub const BACKUP_DIR : &str = "backups";
+
+// writes next to the target first so a crash never leaves a half written file behind
+pub fn write_atomic(file_path: &Path, contents: &[u8]) -> Result<(), Error> {
+    let mut temp_path = file_path.as_os_str().to_owned();
+    temp_path.push(".tmp");
+    let temp_path = PathBuf::from(temp_path);
+
+    let mut file = fs::File::create(&temp_path)?;
+    file.write_all(contents)?;
+    file.sync_all()?;
+    drop(file);
+
+    fs::rename(&temp_path, file_path)?;
+    Ok(())
+}
+
+fn backup_dir(file_path: &Path) -> PathBuf {
+    file_path.parent().unwrap_or(Path::new(".")).join(BACKUP_DIR)
+}
+
+fn file_stem(file_path: &Path) -> String {
+    file_path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default()
+}
+
+// backups are named {stem}_YYYY-MM-DD_HH-MM-SS-mmm.ccmp with _2, _3.. added if that already exists.
+// older backups have no milliseconds. anything else in the folder (e.g. backups of run_2.ccmp for run.ccmp)
+// isn't a backup of this file, so the name has to match exactly.
+// returns something that sorts by age
+fn backup_key(file_name: &str, stem: &str) -> Option<(String, u32)> {
+    let rest = file_name.strip_prefix(stem)?.strip_prefix('_')?.strip_suffix(FILE_EXTENTION)?.strip_suffix('.')?;
+
+    for pattern in [TIMESTAMP_PATTERN, &TIMESTAMP_PATTERN[..SECONDS_PATTERN_LEN]] {
+        let Some(stamp) = rest.get(..pattern.len()) else { continue; };
+        if !matches_pattern(stamp, pattern) { continue; }
+
+        let counter = match &rest[pattern.len()..] {
+            "" => 1,
+            tail => tail.strip_prefix('_')?.parse::<u32>().ok()?,
+        };
+
+        // old backups sort as if they were made at .000
+        return Some((format!("{stamp}{}", &TIMESTAMP_PATTERN[pattern.len()..]), counter));
+    }
+
+    None
+}
+
+// 0 is any digit
+fn matches_pattern(text: &str, pattern: &str) -> bool {
+    text.len() == pattern.len() && text.bytes().zip(pattern.bytes()).all(|(c, p)| if p == b'0' { c.is_ascii_digit() } else { c == p })
+}
+
+// copies the current version of the file into the backup folder and deletes the oldest backups
+pub fn create_backup(file_path: &Path, keep: usize) -> Result<(), Error> {
+    if keep == 0 || !file_path.exists() { return Ok(()); }
+
+    let dir = backup_dir(file_path);
+    fs::create_dir_all(&dir)?;
+
+    // saving twice in the same millisecond shouldn't overwrite the first backup
+    let name = format!("{}_{}", file_stem(file_path), timestamp());
+    let mut backup_path = dir.join(format!("{name}.{FILE_EXTENTION}"));
+    let mut counter = 1;
+
+    while backup_path.exists() {
+        counter += 1;
+        backup_path = dir.join(format!("{name}_{counter}.{FILE_EXTENTION}"));
+    }
+
+    fs::copy(file_path, backup_path)?;
+
+    for old_backup in list_backups(file_path).iter().skip(keep) {
+        fs::remove_file(old_backup)?;
+    }
+
+    Ok(())
+}
+
+// newest first
+pub fn list_backups(file_path: &Path) -> Vec<PathBuf> {
+    let stem = file_stem(file_path);
+
+    let Ok(entries) = fs::read_dir(backup_dir(file_path)) else { return Vec::new(); };
+
+    let mut backups : Vec<((String, u32), PathBuf)> = entries
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter_map(|path| {
+            let key = backup_key(&path.file_name()?.to_string_lossy(), &stem)?;
+            Some((key, path))
+        })
+        .collect();
+
+    // timestamps sort like strings
+    backups.sort();
+    backups.into_iter().rev().map(|(_, path)| path).collect()
+}
+
+const TIMESTAMP_PATTERN : &str = "0000-00-00_00-00-00-000";
+const SECONDS_PATTERN_LEN : usize = 19;
+
+// utc, YYYY-MM-DD_HH-MM-SS-mmm
+fn timestamp() -> String {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+    let [year, month, day, hour, minute, second] = date::utc_date_time(now.as_secs());
+    format!("{year:04}-{month:02}-{day:02}_{hour:02}-{minute:02}-{second:02}-{:03}", now.subsec_millis())
+}
@@ -0,0 +1,87 @@
+use std::io::{BufRead, BufReader};
+use std::net::TcpListener;
+use std::sync::Mutex;
+use once_cell::sync::Lazy;
+use tracing::info;
+
+use crate::capture;
+
+// a fake livesplit server and replays of session captures, the benchmarks are in benches/.
+// only compiled into debug builds
+
+pub static BENCH_RESULTS: Lazy<Mutex<Vec<String>>> = Lazy::new(|| Mutex::new(Vec::new()));
+static BENCH_RUNNING: Lazy<Mutex<bool>> = Lazy::new(|| Mutex::new(false));
+
+pub fn is_running() -> bool {
+    *BENCH_RUNNING.lock().unwrap()
+}
+
+// clears the running flag when the job is done, even if it panicked
+struct RunningGuard;
+
+impl Drop for RunningGuard {
+    fn drop(&mut self) {
+        let mut running = BENCH_RUNNING.lock().unwrap_or_else(|e| e.into_inner());
+        *running = false;
+    }
+}
+
+// none if something is already running
+fn claim_running() -> Option<RunningGuard> {
+    let mut running = BENCH_RUNNING.lock().unwrap();
+    if *running { return None; }
+    *running = true;
+    drop(running);
+
+    BENCH_RESULTS.lock().unwrap().clear();
+    Some(RunningGuard)
+}
+
+fn push_result(line: String) {
+    info!("{line}");
+    BENCH_RESULTS.lock().unwrap_or_else(|e| e.into_inner()).push(line);
+}
+
+fn run_in_background(job: impl FnOnce() -> Vec<String> + Send + 'static) {
+    let Some(guard) = claim_running() else { return; };
+
+    std::thread::spawn(move || {
+        let _guard = guard;
+
+        match std::panic::catch_unwind(std::panic::AssertUnwindSafe(job)) {
+            Ok(lines) => lines.into_iter().for_each(push_result),
+            Err(_) => push_result("FAILED: panicked, see the log".to_string()),
+        }
+    });
+}
+
+// stands in for livesplit to see which commands we send. stops when the client disconnects
+pub fn start_livesplit_mock(port: u16) {
+    let Some(guard) = claim_running() else { return; };
+
+    std::thread::spawn(move || {
+        let _guard = guard;
+
+        match TcpListener::bind(("127.0.0.1", port)) {
+            Ok(listener) => {
+                push_result(format!("mock livesplit: listening on {port}"));
+
+                if let Ok((stream, address)) = listener.accept() {
+                    push_result(format!("mock livesplit: {address} connected"));
+
+                    for line in BufReader::new(stream).lines() {
+                        let Ok(line) = line else { break; };
+                        push_result(format!("mock livesplit: {line}"));
+                    }
+
+                    push_result("mock livesplit: disconnected".to_string());
+                }
+            }
+            Err(e) => push_result(format!("mock livesplit: {e}")),
+        }
+    });
+}
+
+pub fn start_replay(file_path: String) {
+    run_in_background(move || capture::replay(&file_path).unwrap_or_else(|e| vec![format!("replay: {e}")]));
+}
@@ -0,0 +1,459 @@
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver};
+
+use egui::Key;
+use serde::{Serialize, Deserialize};
+use tracing::{error, info};
+use uuid::Uuid;
+
+use crate::clock::Clock;
+use crate::config::{ConfigState, SamplingPolicy};
+use crate::date;
+use crate::error::Error;
+use crate::events::{self, AppState, CelEvent};
+use crate::gamedata::GameVersion;
+use crate::pathdata::{BoxCollider, HighPassFilter, Path, PathCollection};
+use crate::pathlog::PathLog;
+use crate::rendering::RenderUpdates;
+use crate::services::{FileDialogKind, Services};
+use crate::ui::UIState;
+
+// session captures for bug reports, one json object per line. the first line is the path log and config when the
+// capture started, every line after that is a frame: clock time, player position and rotation, loading flag, the config
+// if it changed and the events processed that frame. replay() runs them through the path log and the dispatcher headless.
+// the path log's ids are seeded while capturing so the replay creates the same ones the captured events refer to.
+// nodes of paths that existed before aren't captured, they come back with only their id and time
+
+pub const CAPTURE_DIRECTORY : &str = "Paths/captures";
+pub const CAPTURE_EXTENSION : &str = "capture";
+const CAPTURE_VERSION : u32 = 3;
+
+// egui's keys only (de)serialize with its serde feature, the name is stable either way
+pub mod key_name {
+    use egui::Key;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(key: &Key, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(key.name())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Key, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        Key::from_name(&name).ok_or_else(|| serde::de::Error::custom(format!("unknown key '{name}'")))
+    }
+}
+
+// vectors of keys, for the input suppression exceptions
+mod key_names {
+    use egui::Key;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S: Serializer>(keys: &[Key], serializer: S) -> Result<S::Ok, S::Error> {
+        keys.iter().map(|key| key.name()).collect::<Vec<_>>().serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<Key>, D::Error> {
+        Vec::<String>::deserialize(deserializer)?.iter()
+            .map(|name| Key::from_name(name).ok_or_else(|| serde::de::Error::custom(format!("unknown key '{name}'"))))
+            .collect()
+    }
+}
+
+// the config the path log and the dispatcher read. captured in the header and again in every frame it changed in
+#[derive(Clone, PartialEq)]
+#[derive(Serialize, Deserialize)]
+pub struct CapturedConfig {
+    sampling: SamplingPolicy,
+    trigger_sizes: [[f32; 3]; 2],
+    simplify_tolerance: f32,
+    simplify_on_save: bool,
+    compress_files: bool,
+    backup_count: usize,
+    runner_name: String,
+    #[serde(with = "key_names")]
+    input_suppression_exceptions: Vec<Key>,
+}
+
+impl CapturedConfig {
+    pub fn from_config(config: &ConfigState) -> CapturedConfig {
+        CapturedConfig {
+            sampling: config.sampling,
+            trigger_sizes: config.trigger_sizes,
+            simplify_tolerance: config.simplify_tolerance,
+            simplify_on_save: config.simplify_on_save,
+            compress_files: config.compress_files,
+            backup_count: config.backup_count,
+            runner_name: config.runner_name.clone(),
+            input_suppression_exceptions: config.input_suppression_exceptions.clone(),
+        }
+    }
+
+    fn apply(&self, config: &mut ConfigState) {
+        config.sampling = self.sampling;
+        config.trigger_sizes = self.trigger_sizes;
+        config.simplify_tolerance = self.simplify_tolerance;
+        config.simplify_on_save = self.simplify_on_save;
+        config.compress_files = self.compress_files;
+        config.backup_count = self.backup_count;
+        config.runner_name.clone_from(&self.runner_name);
+        config.input_suppression_exceptions.clone_from(&self.input_suppression_exceptions);
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct CapturedPath {
+    id: Uuid,
+    time: u64,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CaptureHeader {
+    version: u32,
+    game_version: GameVersion,
+    recorded_at: u64,
+    config: CapturedConfig,
+    direct_mode: bool,
+    autoreset: bool,
+    main_triggers: [Option<BoxCollider>; 2],
+    checkpoint_triggers: Vec<BoxCollider>,
+    collections: Vec<PathCollection>,
+    paths: Vec<CapturedPath>,
+    filters: Vec<(Uuid, HighPassFilter)>,
+    active_collection: Option<Uuid>,
+    // older captures don't have these, they fail the version check instead of parsing
+    #[serde(default)]
+    recording_id: Uuid,
+    #[serde(default)]
+    id_seed: u64,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CapturedFrame {
+    // clock micros
+    time: u64,
+    position: [f32; 3],
+    rotation: [f32; 3],
+    loading: bool,
+    // only when it changed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    config: Option<CapturedConfig>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    events: Vec<CelEvent>,
+}
+
+pub struct Capture {
+    writer: Option<BufWriter<File>>,
+    file_path: Option<String>,
+    // written when the next frame begins, events get added until then
+    frame: Option<CapturedFrame>,
+    config: Option<CapturedConfig>,
+}
+
+impl Capture {
+    pub fn new() -> Capture {
+        Capture { writer: None, file_path: None, frame: None, config: None }
+    }
+
+    pub fn is_capturing(&self) -> bool {
+        self.writer.is_some()
+    }
+
+    // the current capture, or the last one after stopping
+    pub fn file_path(&self) -> Option<&String> {
+        self.file_path.as_ref()
+    }
+
+    pub fn start(&mut self, pathlog: &mut PathLog, config: &ConfigState, game_version: GameVersion) -> Result<(), Error> {
+        if self.is_capturing() { return Ok(()); }
+
+        std::fs::create_dir_all(CAPTURE_DIRECTORY)?;
+
+        let recorded_at = date::unix_time();
+        let [year, month, day, hour, minute, second] = date::utc_date_time(recorded_at);
+        let file_path = format!("{CAPTURE_DIRECTORY}/{year:04}-{month:02}-{day:02}_{hour:02}-{minute:02}-{second:02}.{CAPTURE_EXTENSION}");
+
+        let paths = pathlog.collections().iter()
+            .flat_map(|c| c.paths())
+            .filter_map(|id| pathlog.path(id))
+            .map(|path| CapturedPath { id: path.id(), time: path.time() })
+            .collect();
+
+        let id_seed = Uuid::new_v4().as_u128() as u64;
+        let config = CapturedConfig::from_config(config);
+
+        let header = CaptureHeader {
+            version: CAPTURE_VERSION,
+            game_version,
+            recorded_at,
+            config: config.clone(),
+            direct_mode: pathlog.direct_mode(),
+            autoreset: pathlog.autoreset(),
+            main_triggers: pathlog.main_triggers,
+            checkpoint_triggers: pathlog.checkpoint_triggers.clone(),
+            collections: pathlog.collections().clone(),
+            paths,
+            filters: pathlog.filters.iter().map(|(id, filter)| (*id, *filter)).collect(),
+            active_collection: pathlog.active_collection,
+            recording_id: pathlog.recording_path.id(),
+            id_seed,
+        };
+
+        let mut writer = BufWriter::new(File::create(&file_path)?);
+        write_line(&mut writer, &header)?;
+
+        info!("Capturing to {file_path}");
+
+        pathlog.seed_ids(Some(id_seed));
+
+        self.writer = Some(writer);
+        self.file_path = Some(file_path);
+        self.frame = None;
+        self.config = Some(config);
+
+        Ok(())
+    }
+
+    pub fn stop(&mut self, pathlog: &mut PathLog) {
+        if !self.is_capturing() { return; }
+
+        pathlog.seed_ids(None);
+
+        self.write_frame();
+
+        if let Some(mut writer) = self.writer.take() {
+            if let Err(e) = writer.flush() {
+                error!("{e}");
+            }
+        }
+
+        info!("Capture stopped");
+    }
+
+    // called once per frame after the clock ticked
+    pub fn begin_frame(&mut self, time: u64, position: [f32; 3], rotation: [f32; 3], loading: bool, config: CapturedConfig) {
+        if !self.is_capturing() { return; }
+
+        self.write_frame();
+
+        let changed = self.config.as_ref() != Some(&config);
+        if changed { self.config = Some(config.clone()); }
+
+        self.frame = Some(CapturedFrame {
+            time,
+            position,
+            rotation,
+            loading,
+            config: Some(config).filter(|_| changed),
+            events: Vec::new(),
+        });
+    }
+
+    pub fn record_events(&mut self, events: &VecDeque<CelEvent>) {
+        if let Some(frame) = &mut self.frame {
+            frame.events.extend(events.iter().cloned());
+        }
+    }
+
+    fn write_frame(&mut self) {
+        let (Some(writer), Some(frame)) = (&mut self.writer, self.frame.take()) else { return; };
+
+        if let Err(e) = write_line(writer, &frame) {
+            error!("Capture stopped: {e}");
+            self.writer = None;
+        }
+    }
+}
+
+impl Default for Capture {
+    fn default() -> Self {
+        Capture::new()
+    }
+}
+
+fn write_line<T: Serialize>(writer: &mut BufWriter<File>, value: &T) -> Result<(), Error> {
+    let line = serde_json::to_string(value).map_err(|e| Error::Parse { msg: e.to_string() })?;
+    writeln!(writer, "{line}")?;
+    Ok(())
+}
+
+// replays never touch files, these events would
+fn replayable(event: &CelEvent) -> bool {
+    !matches!(event,
+        CelEvent::SaveComparison | CelEvent::LoadComparison | CelEvent::RestoreBackup { .. }
+        | CelEvent::RecoverRuns { .. } | CelEvent::DiscardRecoveredRuns
+        | CelEvent::SaveConfig | CelEvent::LoadConfig
+        | CelEvent::SwitchProfile { .. } | CelEvent::CreateProfile { .. }
+        | CelEvent::DuplicateProfile { .. } | CelEvent::RenameProfile { .. }
+        | CelEvent::StartCapture | CelEvent::StopCapture)
+}
+
+// the captured player instead of the game, dialogs never get an answer
+struct ReplayServices {
+    position: [f32; 3],
+    rotation: [f32; 3],
+    game_version: GameVersion,
+}
+
+impl Services for ReplayServices {
+    fn file_dialog(&mut self, _kind: FileDialogKind) -> Receiver<Option<PathBuf>> {
+        mpsc::channel().1
+    }
+
+    fn player_position(&self) -> [f32; 3] { self.position }
+    fn player_rotation(&self) -> [f32; 3] { self.rotation }
+    fn camera_rotation(&self) -> [f32; 2] { [0., 0.] }
+
+    fn teleport_player(&mut self, _location: [f32; 3], _rotation: [f32; 3]) {}
+    fn set_camera_rotation(&mut self, _rotation: [f32; 2]) {}
+
+    fn game_version(&self) -> GameVersion { self.game_version }
+
+    // replays never touch the files of the session they came from
+    fn read_file(&mut self, path: &std::path::Path) -> Result<Vec<u8>, Error> {
+        Err(Error::IO { msg: format!("{} isn't available in replays", path.display()) })
+    }
+
+    fn write_file(&mut self, _path: &std::path::Path, _contents: &[u8]) -> Result<(), Error> { Ok(()) }
+    fn back_up(&mut self, _path: &std::path::Path, _keep: usize) -> Result<(), Error> { Ok(()) }
+}
+
+fn parse_line<'a, T: Deserialize<'a>>(line: &'a str, line_number: usize) -> Result<T, Error> {
+    serde_json::from_str(line).map_err(|e| Error::Parse { msg: format!("line {line_number}: {e}") })
+}
+
+fn restore(header: &CaptureHeader) -> PathLog {
+    let mut pathlog = PathLog::new();
+    pathlog.set_clock(Clock::manual());
+
+    let paths = header.paths.iter().map(|captured| {
+        let mut path = Path::with_id(captured.id);
+        path.end_path(captured.time);
+        (captured.id, path)
+    }).collect();
+
+    pathlog.set_comparison(header.main_triggers, paths, header.collections.clone(), None);
+    pathlog.checkpoint_triggers = header.checkpoint_triggers.clone();
+    pathlog.filters = header.filters.iter().cloned().collect();
+    pathlog.active_collection = header.active_collection;
+    pathlog.set_direct_mode(header.direct_mode);
+    pathlog.set_autoreset(header.autoreset);
+    pathlog.set_game_version(Some(header.game_version));
+    pathlog.recording_path = Path::with_id(header.recording_id);
+    pathlog.seed_ids(Some(header.id_seed));
+
+    pathlog
+}
+
+// feeds a capture through PathLog::update and the event dispatcher, returns what the path log did
+pub fn replay(file_path: &str) -> Result<Vec<String>, Error> {
+    let mut lines = BufReader::new(File::open(file_path)?).lines();
+
+    let header_line = lines.next().ok_or(Error::Truncated { msg: "no header".to_string() })??;
+    let header : CaptureHeader = parse_line(&header_line, 1)?;
+
+    if header.version != CAPTURE_VERSION {
+        return Err(Error::UnknownVersion { version: header.version.to_string() });
+    }
+
+    let mut pathlog = restore(&header);
+    let mut config = ConfigState::new();
+    header.config.apply(&mut config);
+    let mut ui_state = UIState::init();
+    let mut render_updates = RenderUpdates::new();
+    let mut capture = Capture::new();
+
+    let mut report = vec![format!("replaying {file_path} ({:?})", header.game_version)];
+    let mut frame_count = 0;
+
+    for (i, line) in lines.enumerate() {
+        let line = line?;
+        if line.is_empty() { continue; }
+
+        let frame : CapturedFrame = parse_line(&line, i + 2)?;
+        frame_count += 1;
+
+        if let Some(frame_config) = &frame.config {
+            frame_config.apply(&mut config);
+        }
+
+        // the same steps as hk_present and process_queued_events
+        pathlog.set_save_options(config.save_options());
+        pathlog.set_runner(&config.runner_name);
+        pathlog.clock_mut().set_micros(frame.time);
+
+        pathlog.set_loading(frame.loading);
+
+        render_updates.or(pathlog.update(&frame.position, &frame.rotation, config.sampling));
+
+        let events : VecDeque<CelEvent> = frame.events.into_iter().filter(replayable).collect();
+
+        let mut state = AppState {
+            pathlog: &mut pathlog,
+            config: &mut config,
+            ui: &mut ui_state,
+            render_updates: &mut render_updates,
+            capture: &mut capture,
+        };
+
+        let mut services = ReplayServices { position: frame.position, rotation: frame.rotation, game_version: header.game_version };
+
+        // events queued for the next frame are already in the capture
+        events::process_events(&mut state, &mut services, events);
+
+        for event in pathlog.take_events() {
+            let event = serde_json::to_string(&event).unwrap_or_default();
+            report.push(format!("{:.3}s: {event}", frame.time as f64 / 1_000_000.));
+        }
+    }
+
+    report.push(format!("replayed {frame_count} frames"));
+
+    Ok(report)
+}
+
+// newest first
+pub fn list_captures() -> Vec<String> {
+    let Ok(entries) = std::fs::read_dir(CAPTURE_DIRECTORY) else { return Vec::new(); };
+
+    let mut captures : Vec<String> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|extension| extension == CAPTURE_EXTENSION))
+        .filter_map(|path| path.to_str().map(|p| p.to_string()))
+        .collect();
+
+    // the names are timestamps
+    captures.sort();
+    captures.reverse();
+    captures
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn captured_config_restores_what_the_dispatcher_reads() {
+        let mut config = ConfigState::new();
+        config.trigger_sizes = [[2., 3., 4.], [5., 6., 7.]];
+        config.simplify_tolerance = 0.25;
+        config.simplify_on_save = true;
+        config.backup_count = 3;
+        config.runner_name = "runner".to_string();
+        config.input_suppression_exceptions = vec![Key::F1, Key::Space];
+        config.sampling.distance = 2.;
+
+        let line = serde_json::to_string(&CapturedConfig::from_config(&config)).unwrap();
+        let captured : CapturedConfig = parse_line(&line, 1).unwrap();
+
+        let mut replayed = ConfigState::new();
+        captured.apply(&mut replayed);
+
+        assert!(CapturedConfig::from_config(&replayed) == CapturedConfig::from_config(&config));
+        assert_eq!(replayed.trigger_sizes, config.trigger_sizes);
+        assert_eq!(replayed.save_options().simplification, Some(0.25));
+    }
+}
@@ -0,0 +1,44 @@
+use std::time::Instant;
+
+// time for the path log. it only moves once per frame so everything within a frame sees the same time,
+// which is what lets captures replay exactly. replays set it by hand instead
+
+#[derive(Clone)]
+pub struct Clock {
+    // None for a manual clock
+    origin: Option<Instant>,
+    // microseconds
+    now: u64,
+}
+
+impl Clock {
+    pub fn system() -> Clock {
+        Clock { origin: Some(Instant::now()), now: 0 }
+    }
+
+    pub fn manual() -> Clock {
+        Clock { origin: None, now: 0 }
+    }
+
+    pub fn tick(&mut self) {
+        if let Some(origin) = self.origin {
+            self.now = origin.elapsed().as_micros() as u64;
+        }
+    }
+
+    // only does something on a manual clock
+    pub fn set_micros(&mut self, now: u64) {
+        if self.origin.is_none() {
+            self.now = now;
+        }
+    }
+
+    pub fn micros(&self) -> u64 {
+        self.now
+    }
+
+    // milliseconds since `start`, a value from micros()
+    pub fn millis_since(&self, start: u64) -> u64 {
+        self.now.saturating_sub(start) / 1000
+    }
+}
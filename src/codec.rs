@@ -0,0 +1,171 @@
+use std::io::{Read, Write};
+use flate2::Compression;
+use flate2::read::DeflateDecoder;
+use flate2::write::DeflateEncoder;
+
+use crate::error::Error;
+
+// geometry of 0.8 files (unchanged since 0.7). every coordinate is rounded to a multiple of the quantisation step
+// and stored as the zigzag varint of its difference to the previous node of the same path.
+// per path: segment count, then per segment: node count and nodes
+
+// 1mm
+pub const DEFAULT_QUANTISATION : f32 = 0.001;
+
+pub const FLAG_DEFLATE : u8 = 1;
+// deflated geometry can't grow past this many times its size or MIN_DECOMPRESS_LIMIT, whichever is larger.
+// real paths stay far below it, a deflate bomb doesn't
+const MAX_EXPANSION : usize = 256;
+const MIN_DECOMPRESS_LIMIT : usize = 64 << 20;
+// path headers are in the order the paths were recorded. older readers ignore it
+pub const FLAG_RECORDING_ORDER : u8 = 2;
+
+fn zigzag(value: i64) -> u64 {
+    ((value << 1) ^ (value >> 63)) as u64
+}
+
+fn unzigzag(value: u64) -> i64 {
+    ((value >> 1) as i64) ^ -((value & 1) as i64)
+}
+
+fn write_varint(out: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        out.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+pub struct GeometryReader<'a> {
+    data: &'a [u8],
+    head: usize,
+}
+
+impl<'a> GeometryReader<'a> {
+    pub fn new(data: &'a [u8]) -> GeometryReader<'a> {
+        GeometryReader { data, head: 0 }
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.head >= self.data.len()
+    }
+
+    fn read_varint(&mut self) -> Result<u64, Error> {
+        let mut value = 0u64;
+        let mut shift = 0;
+
+        loop {
+            let Some(byte) = self.data.get(self.head) else {
+                return Err(Error::Truncated { msg: "geometry ended unexpectedly".to_string() });
+            };
+            self.head += 1;
+
+            if shift >= 64 {
+                return Err(Error::CorruptField { field: "geometry".to_string(), msg: "number too long".to_string() });
+            }
+
+            value |= ((byte & 0x7f) as u64) << shift;
+            if byte & 0x80 == 0 { return Ok(value); }
+            shift += 7;
+        }
+    }
+
+    // a count that has to fit into what's left of the stream, so broken files can't make us allocate forever
+    fn read_count(&mut self, min_bytes_each: usize) -> Result<usize, Error> {
+        let count = self.read_varint()? as usize;
+        if count.saturating_mul(min_bytes_each) > self.data.len() - self.head {
+            return Err(Error::Truncated { msg: "geometry count is larger than the file".to_string() });
+        }
+        Ok(count)
+    }
+
+    pub fn read_path(&mut self, quantisation: f32) -> Result<Vec<Vec<[f32; 3]>>, Error> {
+        let mut previous = [0i64; 3];
+        let segment_count = self.read_count(1)?;
+        let mut segments = Vec::with_capacity(segment_count);
+
+        for _ in 0..segment_count {
+            let node_count = self.read_count(3)?;
+            let mut nodes = Vec::with_capacity(node_count);
+
+            for _ in 0..node_count {
+                let mut node = [0f32; 3];
+                for i in 0..3 {
+                    previous[i] = previous[i].wrapping_add(unzigzag(self.read_varint()?));
+                    node[i] = previous[i] as f32 * quantisation;
+                }
+                nodes.push(node);
+            }
+
+            segments.push(nodes);
+        }
+
+        Ok(segments)
+    }
+}
+
+pub fn write_path(out: &mut Vec<u8>, segments: &[Vec<[f32; 3]>], quantisation: f32) {
+    let mut previous = [0i64; 3];
+    write_varint(out, segments.len() as u64);
+
+    for segment in segments {
+        write_varint(out, segment.len() as u64);
+
+        for node in segment {
+            for i in 0..3 {
+                let quantised = (node[i] / quantisation).round() as i64;
+                write_varint(out, zigzag(quantised.wrapping_sub(previous[i])));
+                previous[i] = quantised;
+            }
+        }
+    }
+}
+
+pub fn compress(data: &[u8]) -> Result<Vec<u8>, Error> {
+    let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(data)?;
+    Ok(encoder.finish()?)
+}
+
+pub fn decompress(data: &[u8]) -> Result<Vec<u8>, Error> {
+    let limit = data.len().saturating_mul(MAX_EXPANSION).max(MIN_DECOMPRESS_LIMIT);
+    let mut decoded = Vec::new();
+
+    // one byte more than allowed to tell a stream that ends at the limit from one that keeps going
+    DeflateDecoder::new(data).take(limit as u64 + 1).read_to_end(&mut decoded)
+        .map_err(|e| Error::CorruptField { field: "geometry".to_string(), msg: e.to_string() })?;
+
+    if decoded.len() > limit {
+        return Err(Error::CorruptField { field: "geometry".to_string(), msg: format!("expands to more than {limit} bytes") });
+    }
+
+    Ok(decoded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_compressed_geometry() {
+        let segments = vec![vec![[1.5, -2.25, 3.], [1.501, -2.25, 3.2]], Vec::new(), vec![[0., 0., 0.]]];
+        let mut geometry = Vec::new();
+        write_path(&mut geometry, &segments, DEFAULT_QUANTISATION);
+
+        let decoded = decompress(&compress(&geometry).unwrap()).unwrap();
+        let read = GeometryReader::new(&decoded).read_path(DEFAULT_QUANTISATION).unwrap();
+
+        assert_eq!(decoded, geometry);
+        assert_eq!(read.len(), segments.len());
+        assert!(read.iter().flatten().zip(segments.iter().flatten())
+            .all(|(a, b)| (0..3).all(|i| (a[i] - b[i]).abs() <= DEFAULT_QUANTISATION)));
+    }
+
+    #[test]
+    fn rejects_deflate_bombs() {
+        let bomb = compress(&vec![0; MIN_DECOMPRESS_LIMIT + 1]).unwrap();
+        assert!(bomb.len() * MAX_EXPANSION < MIN_DECOMPRESS_LIMIT);
+
+        assert!(matches!(decompress(&bomb), Err(Error::CorruptField { .. })));
+    }
+}
@@ -0,0 +1,332 @@
+use std::collections::{HashMap, HashSet};
+use std::ops::RangeInclusive;
+
+use uuid::Uuid;
+
+use crate::pathdata::{Path, PathCollection};
+
+#[derive(Clone, Debug)]
+pub struct ComparedPath {
+    pub id: Uuid,
+    // rank among all paths, used for the gradient in rank mode
+    pub position: usize,
+    // time used for the gradient in time mode
+    pub time: u64,
+    // paths that are drawn faded in the same color (e.g. the rest of a collection in median mode)
+    pub grouped: Vec<Uuid>,
+}
+
+pub struct VisibleCollection<'a> {
+    pub collection: &'a PathCollection,
+    // visible paths of the collection, sorted by time
+    pub paths: Vec<Uuid>,
+}
+
+// all paths sorted by time. kept up to date when paths are added or removed
+// so comparisons don't have to sort everything again
+#[derive(Clone, Default)]
+pub struct PathRanking {
+    sorted: Vec<(u64, Uuid)>,
+}
+
+impl PathRanking {
+    pub fn new() -> PathRanking {
+        PathRanking { sorted: Vec::new() }
+    }
+
+    pub fn from_paths(paths: &HashMap<Uuid, Path>) -> PathRanking {
+        let mut sorted: Vec<(u64, Uuid)> = paths.values().map(|p| (p.time(), p.id())).collect();
+        sorted.sort_unstable();
+        PathRanking { sorted }
+    }
+
+    pub fn insert(&mut self, time: u64, path_id: Uuid) {
+        let index = self.sorted.partition_point(|entry| *entry < (time, path_id));
+        self.sorted.insert(index, (time, path_id));
+    }
+
+    pub fn remove(&mut self, time: u64, path_id: Uuid) {
+        if let Ok(index) = self.sorted.binary_search(&(time, path_id)) {
+            self.sorted.remove(index);
+        }
+    }
+
+    pub fn position(&self, time: u64, path_id: Uuid) -> Option<usize> {
+        self.sorted.binary_search(&(time, path_id)).ok()
+    }
+
+    pub fn len(&self) -> usize {
+        self.sorted.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sorted.is_empty()
+    }
+
+    // fastest first
+    pub fn iter(&self) -> impl Iterator<Item = &(u64, Uuid)> {
+        self.sorted.iter()
+    }
+
+    pub fn fastest_time(&self) -> Option<u64> {
+        self.sorted.first().map(|entry| entry.0)
+    }
+
+    pub fn slowest_time(&self) -> Option<u64> {
+        self.sorted.last().map(|entry| entry.0)
+    }
+
+    pub fn clear(&mut self) {
+        self.sorted.clear();
+    }
+}
+
+pub struct ComparisonInput<'a> {
+    pub paths: &'a HashMap<Uuid, Path>,
+    pub ranking: &'a PathRanking,
+    // all paths in the order they were recorded
+    pub history: &'a [Uuid],
+    pub collections: Vec<VisibleCollection<'a>>,
+    pub visible: HashSet<Uuid>,
+}
+
+impl<'a> ComparisonInput<'a> {
+    pub fn time(&self, path_id: &Uuid) -> u64 {
+        self.paths.get(path_id).map_or(0, |p| p.time())
+    }
+
+    pub fn position(&self, path_id: &Uuid) -> usize {
+        self.ranking.position(self.time(path_id), *path_id).unwrap_or(self.ranking.len())
+    }
+
+    pub fn is_visible(&self, path_id: &Uuid) -> bool {
+        self.visible.contains(path_id)
+    }
+
+    fn compared(&self, path_id: Uuid) -> ComparedPath {
+        ComparedPath { id: path_id, position: self.position(&path_id), time: self.time(&path_id), grouped: Vec::new() }
+    }
+}
+
+pub struct StrategyParameter {
+    pub label: &'static str,
+    pub value: u32,
+    pub range: RangeInclusive<u32>,
+}
+
+pub trait ComparisonStrategy: Send {
+    fn name(&self) -> &'static str;
+
+    // paths to draw. the result is ordered however the strategy likes
+    fn compare(&self, input: &ComparisonInput) -> Vec<ComparedPath>;
+
+    fn parameter(&self) -> Option<StrategyParameter> { None }
+
+    fn set_parameter(&mut self, _value: u32) {}
+
+    // whether the result depends on the recording order
+    fn uses_history(&self) -> bool { false }
+}
+
+// every selectable strategy with its default settings, in the order they show up in the combo box
+pub fn strategies() -> Vec<Box<dyn ComparisonStrategy>> {
+    vec![
+        Box::new(All),
+        Box::new(Gold),
+        Box::new(Median),
+        Box::new(Average),
+        Box::new(LatestN { n: 5 }),
+        Box::new(BestN { n: 3 }),
+        Box::new(Percentile { percentile: 25 }),
+        Box::new(PbProgression),
+    ]
+}
+
+pub fn strategy_by_name(name: &str) -> Option<Box<dyn ComparisonStrategy>> {
+    strategies().into_iter().find(|s| s.name() == name)
+}
+
+pub struct All;
+
+impl ComparisonStrategy for All {
+    fn name(&self) -> &'static str { "All" }
+
+    fn compare(&self, input: &ComparisonInput) -> Vec<ComparedPath> {
+        input.collections.iter()
+            .flat_map(|c| c.paths.iter())
+            .map(|id| input.compared(*id))
+            .collect()
+    }
+}
+
+pub struct Gold;
+
+impl ComparisonStrategy for Gold {
+    fn name(&self) -> &'static str { "Gold" }
+
+    fn compare(&self, input: &ComparisonInput) -> Vec<ComparedPath> {
+        input.collections.iter()
+            .filter_map(|c| c.paths.first())
+            .map(|id| input.compared(*id))
+            .collect()
+    }
+}
+
+pub struct Median;
+
+impl ComparisonStrategy for Median {
+    fn name(&self) -> &'static str { "Median" }
+
+    fn compare(&self, input: &ComparisonInput) -> Vec<ComparedPath> {
+        let mut compared = Vec::new();
+
+        for collection in &input.collections {
+            if collection.paths.is_empty() { continue; }
+
+            let median = collection.paths[collection.paths.len() / 2];
+            let mut path = input.compared(median);
+            path.grouped = collection.paths.iter().filter(|id| **id != median).cloned().collect();
+            compared.push(path);
+        }
+
+        compared
+    }
+}
+
+pub struct Average;
+
+impl ComparisonStrategy for Average {
+    fn name(&self) -> &'static str { "Average" }
+
+    fn compare(&self, input: &ComparisonInput) -> Vec<ComparedPath> {
+        let mut compared = Vec::new();
+
+        for collection in &input.collections {
+            if collection.paths.is_empty() { continue; }
+
+            let sum: u64 = collection.paths.iter().map(|id| input.time(id)).sum();
+            let average = sum / collection.paths.len() as u64;
+
+            // the path closest to the average stands in for the collection
+            let closest = *collection.paths.iter().min_by_key(|id| input.time(id).abs_diff(average)).unwrap();
+
+            let mut path = input.compared(closest);
+            path.time = average;
+            path.grouped = collection.paths.iter().filter(|id| **id != closest).cloned().collect();
+            compared.push(path);
+        }
+
+        compared
+    }
+}
+
+pub struct LatestN {
+    pub n: u32,
+}
+
+impl ComparisonStrategy for LatestN {
+    fn name(&self) -> &'static str { "Latest N" }
+
+    fn uses_history(&self) -> bool { true }
+
+    fn compare(&self, input: &ComparisonInput) -> Vec<ComparedPath> {
+        input.history.iter().rev()
+            .filter(|id| input.is_visible(id))
+            .take(self.n as usize)
+            .map(|id| input.compared(*id))
+            .collect()
+    }
+
+    fn parameter(&self) -> Option<StrategyParameter> {
+        Some(StrategyParameter { label: "Attempts", value: self.n, range: 1..=100 })
+    }
+
+    fn set_parameter(&mut self, value: u32) {
+        self.n = value;
+    }
+}
+
+pub struct BestN {
+    pub n: u32,
+}
+
+impl ComparisonStrategy for BestN {
+    fn name(&self) -> &'static str { "Best N" }
+
+    fn compare(&self, input: &ComparisonInput) -> Vec<ComparedPath> {
+        input.collections.iter()
+            .flat_map(|c| c.paths.iter().take(self.n as usize))
+            .map(|id| input.compared(*id))
+            .collect()
+    }
+
+    fn parameter(&self) -> Option<StrategyParameter> {
+        Some(StrategyParameter { label: "Per Collection", value: self.n, range: 1..=100 })
+    }
+
+    fn set_parameter(&mut self, value: u32) {
+        self.n = value;
+    }
+}
+
+pub struct Percentile {
+    pub percentile: u32,
+}
+
+impl ComparisonStrategy for Percentile {
+    fn name(&self) -> &'static str { "Percentile" }
+
+    // only the fastest paths up to the given percentile of all visible times
+    fn compare(&self, input: &ComparisonInput) -> Vec<ComparedPath> {
+        let mut times: Vec<u64> = input.collections.iter()
+            .flat_map(|c| c.paths.iter())
+            .map(|id| input.time(id))
+            .collect();
+
+        if times.is_empty() { return Vec::new(); }
+
+        times.sort_unstable();
+        let index = ((times.len() - 1) as f32 * self.percentile as f32 / 100.).round() as usize;
+        let threshold = times[index.min(times.len() - 1)];
+
+        input.collections.iter()
+            .flat_map(|c| c.paths.iter())
+            .filter(|id| input.time(id) <= threshold)
+            .map(|id| input.compared(*id))
+            .collect()
+    }
+
+    fn parameter(&self) -> Option<StrategyParameter> {
+        Some(StrategyParameter { label: "Percent", value: self.percentile, range: 1..=100 })
+    }
+
+    fn set_parameter(&mut self, value: u32) {
+        self.percentile = value;
+    }
+}
+
+pub struct PbProgression;
+
+impl ComparisonStrategy for PbProgression {
+    fn name(&self) -> &'static str { "PB Progression" }
+
+    fn uses_history(&self) -> bool { true }
+
+    // paths that were faster than everything recorded before them
+    fn compare(&self, input: &ComparisonInput) -> Vec<ComparedPath> {
+        let mut best = u64::MAX;
+        let mut compared = Vec::new();
+
+        for id in input.history {
+            let time = input.time(id);
+            if time >= best { continue; }
+            best = time;
+
+            if input.is_visible(id) {
+                compared.push(input.compared(*id));
+            }
+        }
+
+        compared
+    }
+}
@@ -1,20 +1,118 @@
-use ini::Ini;
-use egui::{Key, KeyboardShortcut, Modifiers, ecolor};
+use std::ops::RangeInclusive;
+use egui::{Key, ecolor};
 use egui_keybind::Shortcut;
-use serde_json;
-use tracing::{info, error};
+use serde::{Serialize, Deserialize};
+use serde_json::{json, Map, Value};
+use tracing::{info, warn, error};
 
 use crate::error::Error;
+use crate::actions::{Action, Keybinds};
+use crate::configfile::{self, ConfigIssue, SectionReader, CONFIG_SCHEMA_VERSION, DEFAULT_PROFILE, color_value, shortcut_to_name};
+use crate::api::{self, ApiSettings};
+use crate::livesplit::{self, LiveSplitSettings};
+use crate::overlay::{self, OverlaySettings};
+use crate::pathlog::SaveOptions;
+
+pub const CONFIG_FILE_NAME : &str = "celestial.json";
+// only read once to migrate it
+pub const LEGACY_CONFIG_FILE_NAME : &str = "celestial.ini";
+
+// shared with the config tab, anything outside gets clamped and reported on load
+pub const ZOOM_RANGE : RangeInclusive<f32> = 0.5..=8.0;
+pub const TRIGGER_SIZE_RANGE : RangeInclusive<f32> = 0.1..=10.0;
+pub const TIMER_SIZE_RANGE : RangeInclusive<f32> = 6.9..=69.0;
+pub const DISTANCE_RANGE : RangeInclusive<f32> = 0.0..=10000.0;
+pub const GRADIENT_CUTOFF_RANGE : RangeInclusive<f32> = 0.0..=3600.0;
+pub const SAMPLING_INTERVAL_RANGE : RangeInclusive<f32> = 1.0..=1000.0;
+pub const SAMPLING_DISTANCE_RANGE : RangeInclusive<f32> = 0.05..=50.0;
+pub const SAMPLING_ANGLE_RANGE : RangeInclusive<f32> = 1.0..=90.0;
+pub const SIMPLIFY_TOLERANCE_RANGE : RangeInclusive<f32> = 0.001..=5.0;
+pub const DISPLAY_TOLERANCE_RANGE : RangeInclusive<f32> = 0.0..=5.0;
+pub const BACKUP_COUNT_RANGE : RangeInclusive<usize> = 0..=100;
+pub const OVERLAY_TOP_N_RANGE : RangeInclusive<usize> = 0..=20;
+
+const VIRIDIS_STOPS : [[f32; 4]; 5] = [
+    [0.993, 0.906, 0.144, 0.8],
+    [0.369, 0.788, 0.384, 0.8],
+    [0.128, 0.567, 0.551, 0.8],
+    [0.231, 0.322, 0.546, 0.8],
+    [0.267, 0.005, 0.329, 0.8],
+];
+
+const CIVIDIS_STOPS : [[f32; 4]; 5] = [
+    [1.000, 0.918, 0.275, 0.8],
+    [0.737, 0.686, 0.435, 0.8],
+    [0.486, 0.482, 0.471, 0.8],
+    [0.255, 0.302, 0.420, 0.8],
+    [0.000, 0.125, 0.302, 0.8],
+];
+
+// Okabe-Ito palette
+const BLUE_ORANGE_STOPS : [[f32; 4]; 4] = [
+    [0.337, 0.706, 0.914, 0.8],
+    [0.000, 0.447, 0.698, 0.8],
+    [0.902, 0.624, 0.000, 0.8],
+    [0.835, 0.369, 0.000, 0.8],
+];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GradientMode {
+    Rank,
+    Time,
+}
 
-pub const CONFIG_FILE_NAME : &str = "celestial.ini";
+impl GradientMode {
+    pub const ALL : [GradientMode; 2] = [GradientMode::Rank, GradientMode::Time];
 
-macro_rules! set_if_ok {
-    ($dest:expr, $source:expr) => {
-        if let Ok(value) = $source {
-            $dest = value;
-        }
-        else { error!("Value not found. Default: {:?}", $dest); }
-    };
+    pub fn from_name(name: &str) -> Option<GradientMode> {
+        Self::ALL.into_iter().find(|m| format!("{:?}", m) == name)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GradientPreset {
+    Custom,
+    Viridis,
+    Cividis,
+    BlueOrange,
+}
+
+impl GradientPreset {
+    pub const ALL : [GradientPreset; 4] = [GradientPreset::Custom, GradientPreset::Viridis, GradientPreset::Cividis, GradientPreset::BlueOrange];
+
+    pub fn from_name(name: &str) -> Option<GradientPreset> {
+        Self::ALL.into_iter().find(|p| format!("{:?}", p) == name)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[derive(Serialize, Deserialize)]
+pub enum SamplingMode {
+    EveryFrame,
+    Interval,
+    Distance,
+    Adaptive,
+}
+
+impl SamplingMode {
+    pub const ALL : [SamplingMode; 4] = [SamplingMode::EveryFrame, SamplingMode::Interval, SamplingMode::Distance, SamplingMode::Adaptive];
+
+    pub fn from_name(name: &str) -> Option<SamplingMode> {
+        Self::ALL.into_iter().find(|m| format!("{:?}", m) == name)
+    }
+}
+
+// how often the recording path gets a new node
+#[derive(Clone, Copy, PartialEq)]
+#[derive(Serialize, Deserialize)]
+pub struct SamplingPolicy {
+    pub mode: SamplingMode,
+    // milliseconds between nodes in interval mode
+    pub interval: f32,
+    // meters between nodes in distance mode, max spacing in adaptive mode
+    pub distance: f32,
+    // degrees of direction change that add a node in adaptive mode
+    pub angle: f32,
 }
 
 pub struct ConfigState {
@@ -26,16 +124,7 @@ pub struct ConfigState {
     pub zoom: f32,
 
 	// pub toggle_window_keybind: Shortcut,
-	pub start_keybind: Shortcut,
-	pub stop_keybind: Shortcut,
-	pub reset_keybind: Shortcut,
-	pub clear_keybind: Shortcut,
-	pub teleport_keybinds: [Shortcut; 2],
-	pub spawn_checkpoint_keybind: Shortcut,
-
-	pub extra_teleport_keybinds: [Shortcut; 2],
-	pub spawn_teleport_keybinds: [Shortcut; 2],
-	pub delete_teleport_keybinds: [Shortcut; 2],
+    pub keybinds: Keybinds,
 
 	pub trigger_sizes: [[f32; 3]; 2],
     pub timer_size: f32,
@@ -52,7 +141,47 @@ pub struct ConfigState {
     pub select_color: [f32; 4],
     pub accent_colors: [egui::Color32; 2],
 
+    pub gradient_mode: GradientMode,
+    pub gradient_preset: GradientPreset,
+    // stops between fast_color and slow_color for the custom preset
+    pub gradient_stops: Vec<[f32; 4]>,
+    // seconds slower than gold at which the gradient ends. 0 means the slowest path
+    pub gradient_cutoff: f32,
+
+    pub frustum_culling: bool,
+    // meters from the player. 0 disables
+    pub cull_distance: f32,
+    pub lod_distance: f32,
+
+    pub sampling: SamplingPolicy,
+
+    // meters a node may be off the simplified line
+    pub simplify_tolerance: f32,
+    pub simplify_on_save: bool,
+    // only simplifies what gets drawn. 0 disables
+    pub display_tolerance: f32,
+    pub compress_files: bool,
+    pub backup_count: usize,
+    // stored with every recorded path
+    pub runner_name: String,
+    pub livesplit_enabled: bool,
+    pub livesplit_host: String,
+    pub livesplit_port: u16,
+    pub api_enabled: bool,
+    pub api_port: u16,
+    pub overlay_enabled: bool,
+    pub overlay_port: u16,
+    pub overlay_top_n: usize,
+
     pub shapes_enabled: bool,
+
+    // what was wrong with the config file on the last load
+    pub issues: Vec<ConfigIssue>,
+
+    // name of the active profile, its settings are the fields above
+    pub profile: String,
+    // every profile as it was last saved or switched away from
+    profiles: Map<String, Value>,
 }
 
 impl ConfigState {
@@ -64,28 +193,7 @@ impl ConfigState {
             autoreset: true,
             zoom: 1.0,
             // toggle_window_keybind: Shortcut::new(Some(KeyboardShortcut{modifiers: Modifiers::NONE, logical_key: Key::Home}), None),
-            start_keybind: Shortcut::new(Some(KeyboardShortcut{modifiers: Modifiers::NONE, logical_key: Key::Comma}), None),
-            stop_keybind: Shortcut::new(Some(KeyboardShortcut{modifiers: Modifiers::NONE, logical_key: Key::Period}), None),
-            reset_keybind: Shortcut::new(Some(KeyboardShortcut{modifiers: Modifiers::NONE, logical_key: Key::Minus}), None),
-            clear_keybind: Shortcut::new(Some(KeyboardShortcut{modifiers: Modifiers::NONE, logical_key: Key::Delete}), None),
-            teleport_keybinds: [
-                Shortcut::new(Some(KeyboardShortcut{modifiers: Modifiers::NONE, logical_key: Key::K}), None),
-                Shortcut::new(Some(KeyboardShortcut{modifiers: Modifiers::NONE, logical_key: Key::L}), None),
-            ],
-            spawn_checkpoint_keybind: Shortcut::new(Some(KeyboardShortcut{modifiers: Modifiers::NONE, logical_key: Key::C}), None),
-
-            extra_teleport_keybinds: [
-                Shortcut::new(Some(KeyboardShortcut{modifiers: Modifiers::NONE, logical_key: Key::Num1}), None),
-                Shortcut::new(Some(KeyboardShortcut{modifiers: Modifiers::NONE, logical_key: Key::Num2}), None),
-            ],
-            spawn_teleport_keybinds: [
-                Shortcut::new(Some(KeyboardShortcut{modifiers: Modifiers::SHIFT, logical_key: Key::Num1}), None),
-                Shortcut::new(Some(KeyboardShortcut{modifiers: Modifiers::SHIFT, logical_key: Key::Num2}), None),
-            ],
-            delete_teleport_keybinds: [
-                Shortcut::new(Some(KeyboardShortcut{modifiers: Modifiers::ALT, logical_key: Key::Num1}), None),
-                Shortcut::new(Some(KeyboardShortcut{modifiers: Modifiers::ALT, logical_key: Key::Num2}), None),
-            ],
+            keybinds: Keybinds::new(),
 
             trigger_sizes: [[1.0, 1.0, 1.0], [1.0, 1.0, 1.0]],
             timer_size: 24.,
@@ -102,198 +210,417 @@ impl ConfigState {
             select_color: [0.7, 0.8, 1.0, 0.8],
             accent_colors: [egui::Color32::from_rgb(85, 149, 255), egui::Color32::from_rgb(156, 85, 255)],
 
+            gradient_mode: GradientMode::Rank,
+            gradient_preset: GradientPreset::Custom,
+            gradient_stops: Vec::new(),
+            gradient_cutoff: 0.,
+
+            frustum_culling: true,
+            cull_distance: 0.,
+            lod_distance: 60.,
+
+            sampling: SamplingPolicy { mode: SamplingMode::Interval, interval: 1000. / 60., distance: 0.5, angle: 10. },
+
+            simplify_tolerance: 0.05,
+            simplify_on_save: false,
+            display_tolerance: 0.,
+            compress_files: true,
+            backup_count: 5,
+            runner_name: String::new(),
+            livesplit_enabled: false,
+            livesplit_host: "localhost".to_string(),
+            livesplit_port: livesplit::DEFAULT_PORT,
+            api_enabled: false,
+            api_port: api::DEFAULT_PORT,
+            overlay_enabled: false,
+            overlay_port: overlay::DEFAULT_PORT,
+            overlay_top_n: 5,
+
             shapes_enabled: false,
+
+            issues: Vec::new(),
+
+            profile: DEFAULT_PROFILE.to_string(),
+            profiles: Map::new(),
         };
 
         config
     }
 
+    pub fn livesplit_settings(&self) -> LiveSplitSettings {
+        LiveSplitSettings {
+            enabled: self.livesplit_enabled,
+            host: self.livesplit_host.clone(),
+            port: self.livesplit_port,
+        }
+    }
+
+    pub fn api_settings(&self) -> ApiSettings {
+        ApiSettings { enabled: self.api_enabled, port: self.api_port }
+    }
+
+    pub fn overlay_settings(&self) -> OverlaySettings {
+        OverlaySettings { enabled: self.overlay_enabled, port: self.overlay_port, top_n: self.overlay_top_n }
+    }
+
+    pub fn save_options(&self) -> SaveOptions {
+        SaveOptions {
+            simplification: Some(self.simplify_tolerance).filter(|_| self.simplify_on_save),
+            compress: self.compress_files,
+            backups: self.backup_count,
+        }
+    }
+
     pub fn init() -> ConfigState {
         let mut state = Self::new();
 
-        if let Err(_) = state.read(CONFIG_FILE_NAME.to_string()) {
-            if let Err(e) = state.read("data/".to_string() + CONFIG_FILE_NAME) {
+        for directory in ["", "data/"] {
+            let file_path = directory.to_string() + CONFIG_FILE_NAME;
+            if !std::path::Path::new(&file_path).exists() { continue; }
+
+            if let Err(e) = state.read(file_path) {
                 error!("{e}");
             }
+            return state;
+        }
+
+        // first start after updating, the json file goes next to the ini
+        for directory in ["", "data/"] {
+            let ini_path = directory.to_string() + LEGACY_CONFIG_FILE_NAME;
+            if !std::path::Path::new(&ini_path).exists() { continue; }
+
+            match configfile::migrate_ini(&ini_path) {
+                Ok(document) => {
+                    state.load(document);
+                    info!("Migrated {ini_path} to {CONFIG_FILE_NAME}");
+
+                    if let Err(e) = state.write(directory.to_string() + CONFIG_FILE_NAME) {
+                        error!("{e}");
+                    }
+                }
+                Err(e) => {
+                    error!("{e}");
+                    state.issues = vec![ConfigIssue { section: String::new(), key: String::new(), msg: e.to_string() }];
+                }
+            }
+            return state;
         }
 
         state
     }
 
     pub fn read(&mut self, file_path: String) -> Result<(), Error> {
-        let conf = Ini::load_from_file(file_path.clone())?;
-
-        let mut general_section = conf.section(Some("General"));
-        if general_section.is_none() { general_section = conf.section(Some("Celestial")) } // backwards compatibility
-
-        if let Some(section) = general_section {
-            set_if_ok!(self.autoreset, section.get("autoreset").unwrap_or("true").parse::<bool>());
-            set_if_ok!(self.zoom, section.get("zoom").unwrap_or("1.0").parse::<f32>());
-
-            set_if_ok!(self.start_keybind, Shortcut::from_string(section.get("start_keybind").unwrap_or("")));
-            set_if_ok!(self.stop_keybind, Shortcut::from_string(section.get("stop_keybind").unwrap_or("")));
-            set_if_ok!(self.reset_keybind, Shortcut::from_string(section.get("reset_keybind").unwrap_or("")));
-            set_if_ok!(self.clear_keybind, Shortcut::from_string(section.get("clear_keybind").unwrap_or("")));
-            set_if_ok!(self.teleport_keybinds[0], Shortcut::from_string(section.get("teleport_1_keybind").unwrap_or("")));
-            set_if_ok!(self.teleport_keybinds[1], Shortcut::from_string(section.get("teleport_2_keybind").unwrap_or("")));
-            set_if_ok!(self.spawn_checkpoint_keybind, Shortcut::from_string(section.get("spawn_checkpoint_keybind").unwrap_or("")));
-
-            set_if_ok!(self.extra_teleport_keybinds[0], Shortcut::from_string(section.get("extra_teleport_keybind_0").unwrap_or("")));
-            set_if_ok!(self.extra_teleport_keybinds[1], Shortcut::from_string(section.get("extra_teleport_keybind_1").unwrap_or("")));
-            set_if_ok!(self.spawn_teleport_keybinds[0], Shortcut::from_string(section.get("spawn_teleport_keybind_0").unwrap_or("")));
-            set_if_ok!(self.spawn_teleport_keybinds[1], Shortcut::from_string(section.get("spawn_teleport_keybind_1").unwrap_or("")));
-            set_if_ok!(self.delete_teleport_keybinds[0], Shortcut::from_string(section.get("delete_teleport_keybind_0").unwrap_or("")));
-            set_if_ok!(self.delete_teleport_keybinds[1], Shortcut::from_string(section.get("delete_teleport_keybind_1").unwrap_or("")));
-
-            set_if_ok!(self.trigger_sizes[0], serde_json::from_str(section.get("start_trigger_size").unwrap_or("")));
-            set_if_ok!(self.trigger_sizes[1], serde_json::from_str(section.get("end_trigger_size").unwrap_or("")));
-            set_if_ok!(self.timer_size, serde_json::from_str(section.get("timer_size").unwrap_or("")));
-            set_if_ok!(self.timer_position, serde_json::from_str(section.get("timer_position").unwrap_or("")));
-
-            set_if_ok!(self.input_suppression, section.get("input_suppression").unwrap_or("true").parse::<bool>());
-            // set_if_ok!(self.input_suppression_exceptions, serde_json::from_str(section.get("input_suppression_exceptions").unwrap_or("")));
-            if section.contains_key("input_suppression_exceptions") { self.input_suppression_exceptions.clear(); }
-            for keystring in section.get("input_suppression_exceptions").unwrap_or("").split(&['[', ']', ','][..]) {
-                if let Some(key) = Key::from_name(keystring.trim()) { self.input_suppression_exceptions.push(key); }
+        self.open(configfile::read_document(&file_path))
+    }
+
+    // a config file that was read somewhere else, errors end up in the issues too
+    pub fn open(&mut self, document: Result<Value, Error>) -> Result<(), Error> {
+        match document {
+            Ok(document) => self.load(document),
+            Err(e) => {
+                self.issues = vec![ConfigIssue { section: String::new(), key: String::new(), msg: e.to_string() }];
+                return Err(e);
+            }
+        }
+
+        Ok(())
+    }
+
+    // replaces every profile with the ones in the document and switches to the one used last
+    pub fn load(&mut self, document: Value) {
+        let mut issues = Vec::new();
+
+        let (active_profile, profiles) = match configfile::open_document(document, &mut issues) {
+            Ok(document) => document,
+            Err(e) => {
+                error!("{e}");
+                self.issues = vec![ConfigIssue { section: String::new(), key: String::new(), msg: e.to_string() }];
+                return;
             }
+        };
+
+        self.profiles = profiles;
 
-            set_if_ok!(self.trigger_colors[0], serde_json::from_str(section.get("start_trigger_color").unwrap_or("")));
-            set_if_ok!(self.trigger_colors[1], serde_json::from_str(section.get("end_trigger_color").unwrap_or("")));
-            set_if_ok!(self.checkpoint_color, serde_json::from_str(section.get("checkpoint_color").unwrap_or("")));
-            set_if_ok!(self.fast_color, serde_json::from_str(section.get("fast_color").unwrap_or("")));
-            set_if_ok!(self.slow_color, serde_json::from_str(section.get("slow_color").unwrap_or("")));
-            set_if_ok!(self.gold_color, serde_json::from_str(section.get("gold_color").unwrap_or("")));
-            set_if_ok!(self.select_color, serde_json::from_str(section.get("select_color").unwrap_or("")));
-            set_if_ok!(self.accent_colors[0], serde_json::from_str(section.get("accent_color_0").unwrap_or("")));
-            set_if_ok!(self.accent_colors[1], serde_json::from_str(section.get("accent_color_1").unwrap_or("")));
+        if !self.profiles.contains_key(&active_profile) {
+            if !self.profiles.is_empty() {
+                issues.push(ConfigIssue { section: String::new(), key: "active_profile".to_string(), msg: format!("profile '{active_profile}' not found, using defaults") });
+            }
+            self.profiles.insert(active_profile.clone(), json!({}));
         }
-        else { error!("'General' section not found in config file.") }
 
-        let extra_section = conf.section(Some("Extra"));
+        let profile = self.profiles[&active_profile].clone();
+        self.profile = active_profile;
+        self.apply_profile(profile, issues);
+    }
+
+    // every setting starts from its default, then gets what the profile has
+    fn apply_profile(&mut self, profile: Value, mut issues: Vec<ConfigIssue>) {
+        let defaults = ConfigState::new();
+        let profiles = std::mem::take(&mut self.profiles);
+        let name = std::mem::take(&mut self.profile);
+        let (direct_mode, autosave) = (self.direct_mode, self.autosave);
+
+        *self = ConfigState { profiles, profile: name, direct_mode, autosave, ..defaults };
+
+        let sections = configfile::open_profile(profile, &["general", "keybinds", "extra"], &mut issues);
+        let [general, keybinds, extra] : [_; 3] = sections.try_into().unwrap();
+
+        let mut section = SectionReader::new("general", general, &mut issues);
+
+        section.value("autoreset", &mut self.autoreset);
+        section.ranged("zoom", &mut self.zoom, ZOOM_RANGE);
 
-        if let Some(section) = extra_section {
-            set_if_ok!(self.shapes_enabled, section.get("custom_shapes").unwrap_or("").parse::<bool>());
+        section.size("start_trigger_size", &mut self.trigger_sizes[0], TRIGGER_SIZE_RANGE);
+        section.size("end_trigger_size", &mut self.trigger_sizes[1], TRIGGER_SIZE_RANGE);
+        section.ranged("timer_size", &mut self.timer_size, TIMER_SIZE_RANGE);
+        section.value("timer_position", &mut self.timer_position);
+
+        section.value("input_suppression", &mut self.input_suppression);
+        section.keys("input_suppression_exceptions", &mut self.input_suppression_exceptions);
+
+        section.color("start_trigger_color", &mut self.trigger_colors[0]);
+        section.color("end_trigger_color", &mut self.trigger_colors[1]);
+        section.color("checkpoint_color", &mut self.checkpoint_color);
+        section.color("fast_color", &mut self.fast_color);
+        section.color("slow_color", &mut self.slow_color);
+        section.color("gold_color", &mut self.gold_color);
+        section.color("select_color", &mut self.select_color);
+
+        for (i, key) in ["accent_color_0", "accent_color_1"].into_iter().enumerate() {
+            let mut accent_color = self.accent_colors[i].to_array().map(|c| c as f32 / 255.);
+            section.color(key, &mut accent_color);
+            self.accent_colors[i] = accent_color.as_color32();
         }
-        else { error!("'Extra' section not found in config file.") }
 
-        // self.generate_extra_teleport_keybinds();
-        // self.generate_spawn_teleport_keybinds();
-        // self.generate_delete_teleport_keybinds();
+        section.named("gradient_mode", &mut self.gradient_mode, GradientMode::from_name);
+        section.named("gradient_preset", &mut self.gradient_preset, GradientPreset::from_name);
+        section.colors("gradient_stops", &mut self.gradient_stops);
+        section.ranged("gradient_cutoff", &mut self.gradient_cutoff, GRADIENT_CUTOFF_RANGE);
+
+        section.value("frustum_culling", &mut self.frustum_culling);
+        section.ranged("cull_distance", &mut self.cull_distance, DISTANCE_RANGE);
+        section.ranged("lod_distance", &mut self.lod_distance, DISTANCE_RANGE);
+
+        section.named("sampling_mode", &mut self.sampling.mode, SamplingMode::from_name);
+        section.ranged("sampling_interval", &mut self.sampling.interval, SAMPLING_INTERVAL_RANGE);
+        section.ranged("sampling_distance", &mut self.sampling.distance, SAMPLING_DISTANCE_RANGE);
+        section.ranged("sampling_angle", &mut self.sampling.angle, SAMPLING_ANGLE_RANGE);
+
+        section.ranged("simplify_tolerance", &mut self.simplify_tolerance, SIMPLIFY_TOLERANCE_RANGE);
+        section.value("simplify_on_save", &mut self.simplify_on_save);
+        section.ranged("display_tolerance", &mut self.display_tolerance, DISPLAY_TOLERANCE_RANGE);
+        section.value("compress_files", &mut self.compress_files);
+        section.ranged("backup_count", &mut self.backup_count, BACKUP_COUNT_RANGE);
+        section.value("runner_name", &mut self.runner_name);
+        section.value("livesplit_enabled", &mut self.livesplit_enabled);
+        section.value("livesplit_host", &mut self.livesplit_host);
+        section.value("livesplit_port", &mut self.livesplit_port);
+        section.value("api_enabled", &mut self.api_enabled);
+        section.value("api_port", &mut self.api_port);
+        section.value("overlay_enabled", &mut self.overlay_enabled);
+        section.value("overlay_port", &mut self.overlay_port);
+        section.ranged("overlay_top_n", &mut self.overlay_top_n, OVERLAY_TOP_N_RANGE);
+
+        section.finish();
+
+        let mut section = SectionReader::new("keybinds", keybinds, &mut issues);
+
+        for action in Action::ALL {
+            let mut shortcut = self.keybinds.get(action);
+            section.shortcut(action.name(), &mut shortcut);
+            self.keybinds.set(action, shortcut);
+        }
 
-        info!("Config loaded");
-        Ok(())
+        section.finish();
+
+        for action in Action::ALL {
+            // reported once per pair
+            for other in self.keybinds.conflicts(action).into_iter().filter(|other| *other > action) {
+                issues.push(ConfigIssue {
+                    section: "keybinds".to_string(),
+                    key: action.name().to_string(),
+                    msg: format!("'{}' is also bound to {}", shortcut_to_name(&self.keybinds.get(action)), other.name()),
+                });
+            }
+        }
+
+        let mut section = SectionReader::new("extra", extra, &mut issues);
+
+        section.value("custom_shapes", &mut self.shapes_enabled);
+
+        section.finish();
+
+        for issue in &issues {
+            warn!("Config: {issue}");
+        }
+
+        self.issues = issues;
+
+        info!("Profile '{}' loaded", self.profile);
+    }
+
+    // the settings of the active profile as they would be saved
+    fn profile_document(&self) -> Value {
+        let general = [
+            ("autoreset", json!(self.autoreset)),
+            ("zoom", json!(self.zoom)),
+            ("start_trigger_size", json!(self.trigger_sizes[0])),
+            ("end_trigger_size", json!(self.trigger_sizes[1])),
+            ("timer_size", json!(self.timer_size)),
+            ("timer_position", json!(self.timer_position)),
+            ("input_suppression", json!(self.input_suppression)),
+            ("input_suppression_exceptions", json!(self.input_suppression_exceptions.iter().map(|key| key.name()).collect::<Vec<_>>())),
+            ("start_trigger_color", color_value(self.trigger_colors[0])),
+            ("end_trigger_color", color_value(self.trigger_colors[1])),
+            ("checkpoint_color", color_value(self.checkpoint_color)),
+            ("fast_color", color_value(self.fast_color)),
+            ("slow_color", color_value(self.slow_color)),
+            ("gold_color", color_value(self.gold_color)),
+            ("select_color", color_value(self.select_color)),
+            ("accent_color_0", color_value(self.accent_colors[0].to_array().map(|c| c as f32 / 255.))),
+            ("accent_color_1", color_value(self.accent_colors[1].to_array().map(|c| c as f32 / 255.))),
+            ("gradient_mode", json!(format!("{:?}", self.gradient_mode))),
+            ("gradient_preset", json!(format!("{:?}", self.gradient_preset))),
+            ("gradient_stops", json!(self.gradient_stops.iter().map(|c| color_value(*c)).collect::<Vec<_>>())),
+            ("gradient_cutoff", json!(self.gradient_cutoff)),
+            ("frustum_culling", json!(self.frustum_culling)),
+            ("cull_distance", json!(self.cull_distance)),
+            ("lod_distance", json!(self.lod_distance)),
+            ("sampling_mode", json!(format!("{:?}", self.sampling.mode))),
+            ("sampling_interval", json!(self.sampling.interval)),
+            ("sampling_distance", json!(self.sampling.distance)),
+            ("sampling_angle", json!(self.sampling.angle)),
+            ("simplify_tolerance", json!(self.simplify_tolerance)),
+            ("simplify_on_save", json!(self.simplify_on_save)),
+            ("display_tolerance", json!(self.display_tolerance)),
+            ("compress_files", json!(self.compress_files)),
+            ("backup_count", json!(self.backup_count)),
+            ("runner_name", json!(self.runner_name)),
+            ("livesplit_enabled", json!(self.livesplit_enabled)),
+            ("livesplit_host", json!(self.livesplit_host)),
+            ("livesplit_port", json!(self.livesplit_port)),
+            ("api_enabled", json!(self.api_enabled)),
+            ("api_port", json!(self.api_port)),
+            ("overlay_enabled", json!(self.overlay_enabled)),
+            ("overlay_port", json!(self.overlay_port)),
+            ("overlay_top_n", json!(self.overlay_top_n)),
+        ];
+
+        let keybinds : Map<String, Value> = self.keybinds.iter()
+            .map(|(action, shortcut)| (action.name().to_string(), Value::String(shortcut_to_name(&shortcut))))
+            .collect();
+
+        let extra = [
+            ("custom_shapes", json!(self.shapes_enabled)),
+        ];
+
+        json!({
+            "general": section_object(general),
+            "keybinds": keybinds,
+            "extra": section_object(extra),
+        })
     }
 
     pub fn write(&mut self, file_path: String) -> Result<(), Error> {
-        let mut conf = Ini::new();
-
-        conf.with_section(Some("General"))
-            // .set("show_ui", self.show_ui.to_string())
-            .set("autoreset", self.autoreset.to_string())
-            .set("zoom", self.zoom.to_string())
-            // .set("toggle_window_keybind", shortcut_to_string(self.toggle_window_keybind))
-            .set("start_keybind", self.start_keybind.to_string())
-            .set("stop_keybind", self.stop_keybind.to_string())
-            .set("reset_keybind", self.reset_keybind.to_string())
-            .set("clear_keybind", self.clear_keybind.to_string())
-
-            .set("teleport_1_keybind", self.teleport_keybinds[0].to_string())
-            .set("teleport_2_keybind", self.teleport_keybinds[1].to_string())
-            .set("spawn_checkpoint_keybind", self.spawn_checkpoint_keybind.to_string())
-
-            // .set("extra_teleport_modifiers", self.extra_teleport_modifiers.to_string())
-            // .set("spawn_teleport_modifiers", self.spawn_teleport_modifiers.to_string())
-            // .set("delete_teleport_modifiers", self.spawn_teleport_modifiers.to_string())
-            .set("extra_teleport_keybind_0", self.extra_teleport_keybinds[0].to_string())
-            .set("extra_teleport_keybind_1", self.extra_teleport_keybinds[1].to_string())
-            .set("spawn_teleport_keybind_0", self.spawn_teleport_keybinds[0].to_string())
-            .set("spawn_teleport_keybind_1", self.spawn_teleport_keybinds[1].to_string())
-            .set("delete_teleport_keybind_0", self.delete_teleport_keybinds[0].to_string())
-            .set("delete_teleport_keybind_1", self.delete_teleport_keybinds[1].to_string())
-
-            .set("start_trigger_size", format!("{:?}", self.trigger_sizes[0]))
-            .set("end_trigger_size", format!("{:?}", self.trigger_sizes[1]))
-            .set("timer_size", format!("{:?}", self.timer_size))
-            .set("timer_position", format!("{:?}", self.timer_position))
-
-            .set("input_suppression", self.input_suppression.to_string())
-            .set("input_suppression_exceptions", format!("{:?}", self.input_suppression_exceptions))
-
-            .set("start_trigger_color", format!("{:?}", self.trigger_colors[0]))
-            .set("end_trigger_color", format!("{:?}", self.trigger_colors[1]))
-            .set("checkpoint_color", format!("{:?}", self.checkpoint_color))
-            .set("fast_color", format!("{:?}", self.fast_color))
-            .set("slow_color", format!("{:?}", self.slow_color))
-            .set("gold_color", format!("{:?}", self.gold_color))
-            .set("select_color", format!("{:?}", self.select_color))
-            .set("accent_color_0", format!("{:?}", self.accent_colors[0].to_array()))
-            .set("accent_color_1", format!("{:?}", self.accent_colors[1].to_array()));
-
-        conf.with_section(Some("Extra"))
-            .set("custom_shapes", self.shapes_enabled.to_string());
-
-        conf.write_to_file(file_path)?;
+        configfile::write_document(&file_path, &self.to_document())?;
 
         info!("Config saved");
         Ok(())
     }
-}
 
-trait ShortcutString {
-    fn to_string(&self) -> String;
-    fn from_string(stringcut: &str) -> Result<Shortcut, std::io::Error>;
-}
+    // the whole file with every profile, the current one as it is now
+    pub fn to_document(&mut self) -> Value {
+        self.profiles.insert(self.profile.clone(), self.profile_document());
+
+        json!({
+            "schema_version": CONFIG_SCHEMA_VERSION,
+            "active_profile": self.profile,
+            "profiles": self.profiles,
+        })
+    }
+
+    // sorted, includes the active profile even before it was saved
+    pub fn profile_names(&self) -> Vec<String> {
+        let mut names : Vec<String> = self.profiles.keys().cloned().collect();
+
+        if !self.profiles.contains_key(&self.profile) {
+            names.push(self.profile.clone());
+            names.sort();
+        }
 
-impl ShortcutString for Shortcut {
-    fn to_string(&self) -> String {
-        let keyboard = self.keyboard();
+        names
+    }
 
-        if keyboard.is_none() {
-            return "".to_string();
+    fn check_profile_name(&self, name: &str) -> Result<(), String> {
+        if name.is_empty() {
+            return Err("Profile name can't be empty".to_string());
+        }
+        if name == self.profile || self.profiles.contains_key(name) {
+            return Err(format!("Profile '{name}' already exists"));
         }
+        Ok(())
+    }
+
+    // the current profile's changes go into its document first. the caller writes the config afterwards,
+    // so switching, creating, duplicating and renaming all save it
+    pub fn switch_profile(&mut self, name: &str) -> Result<(), String> {
+        if name == self.profile { return Ok(()); }
+
+        let Some(profile) = self.profiles.get(name).cloned() else {
+            return Err(format!("Profile '{name}' not found"));
+        };
 
-        let mut stringcut = "".to_string();
+        self.profiles.insert(self.profile.clone(), self.profile_document());
+        self.profile = name.to_string();
+        self.apply_profile(profile, Vec::new());
 
-        let mods = keyboard.unwrap().modifiers;
-        if mods.alt { stringcut += "alt+"};
-        if mods.ctrl { stringcut += "ctrl+"};
-        if mods.shift { stringcut += "shift+"};
-        if mods.mac_cmd { stringcut += "mac_cmd+"};
-        if mods.command { stringcut += "command+"};
+        Ok(())
+    }
 
-        stringcut + &(keyboard.unwrap().logical_key as u8).to_string()
+    // starts with the defaults
+    pub fn create_profile(&mut self, name: &str) -> Result<(), String> {
+        self.check_profile_name(name)?;
+        self.profiles.insert(name.to_string(), ConfigState::new().profile_document());
+        self.switch_profile(name)
     }
 
-    fn from_string(stringcut: &str) -> Result<Shortcut, std::io::Error> {
-        let mut keys: Vec<&str> = stringcut.split("+").collect();
-        let key = keys.pop();
+    pub fn duplicate_profile(&mut self, name: &str) -> Result<(), String> {
+        self.check_profile_name(name)?;
+        self.profiles.insert(self.profile.clone(), self.profile_document());
+        self.profiles.insert(name.to_string(), self.profile_document());
+        self.profile = name.to_string();
+        Ok(())
+    }
 
-        if key.is_none() {
-            return Err(std::io::Error::other("p"));
-        }
+    pub fn rename_profile(&mut self, new_name: &str) -> Result<(), String> {
+        self.check_profile_name(new_name)?;
+        self.profiles.remove(&self.profile);
+        self.profiles.insert(new_name.to_string(), self.profile_document());
+        self.profile = new_name.to_string();
+        Ok(())
+    }
 
-        let mut keyboard : KeyboardShortcut;
-        let keycode = match key.unwrap().parse::<u8>() {
-            Ok(v) => { v },
-            Err(_) => { return Err(std::io::Error::other("p")) }
-        };
-        unsafe { keyboard = KeyboardShortcut::new(Modifiers::NONE, std::mem::transmute::<_, Key>(keycode)); }
-
-        for m in keys {
-            match m {
-                "alt" => keyboard.modifiers.alt = true,
-                "ctrl" => keyboard.modifiers.ctrl = true,
-                "shift" => keyboard.modifiers.shift = true,
-                "mac_cmd" => keyboard.modifiers.mac_cmd = true,
-                "command" => keyboard.modifiers.command = true,
-                _ => error!("Invalid modifier key: {m}"),
+    // gradient color stops from fastest to slowest
+    pub fn gradient(&self) -> Vec<[f32; 4]> {
+        match self.gradient_preset {
+            GradientPreset::Custom => {
+                let mut stops = Vec::with_capacity(self.gradient_stops.len() + 2);
+                stops.push(self.fast_color);
+                stops.extend(&self.gradient_stops);
+                stops.push(self.slow_color);
+                stops
             }
+            GradientPreset::Viridis => VIRIDIS_STOPS.to_vec(),
+            GradientPreset::Cividis => CIVIDIS_STOPS.to_vec(),
+            GradientPreset::BlueOrange => BLUE_ORANGE_STOPS.to_vec(),
         }
-
-        Ok(Shortcut::new(Some(keyboard), None))
     }
 }
 
+fn section_object<const N: usize>(values: [(&str, Value); N]) -> Value {
+    Value::Object(values.into_iter().map(|(key, value)| (key.to_string(), value)).collect())
+}
+
 pub trait CompareKeybindToEvent {
     fn compare_to_event(&self, event: &egui::Event) -> bool;
 }
@@ -0,0 +1,678 @@
+use std::fmt;
+use std::ops::RangeInclusive;
+use egui::{Key, KeyboardShortcut, Modifiers};
+use egui_keybind::Shortcut;
+use ini::Ini;
+use serde::de::DeserializeOwned;
+use serde_json::{Map, Value};
+
+use crate::error::Error;
+
+// the json config. every key is read on its own so one bad value only resets that value, everything that
+// couldn't be used ends up in a list of issues that the config tab shows.
+// version 0 is the old celestial.ini, migrate_ini() turns it into version 1. version 2 added named profiles,
+// version 3 moved the keybinds into their own section keyed by action name
+
+pub const CONFIG_SCHEMA_VERSION : u32 = 3;
+
+pub const DEFAULT_PROFILE : &str = "Default";
+
+const MODIFIER_NAMES : [&str; 5] = ["alt", "ctrl", "shift", "mac_cmd", "command"];
+
+// version 2 keybind keys in general and the action they belong to. the checkpoint keybind never did anything
+const V2_KEYBINDS : [(&str, Option<&str>); 13] = [
+    ("start_keybind", Some("start")),
+    ("stop_keybind", Some("stop")),
+    ("reset_keybind", Some("reset")),
+    ("clear_keybind", Some("clear_triggers")),
+    ("teleport_1_keybind", Some("teleport_start")),
+    ("teleport_2_keybind", Some("teleport_end")),
+    ("extra_teleport_keybind_0", Some("teleport_location_1")),
+    ("extra_teleport_keybind_1", Some("teleport_location_2")),
+    ("spawn_teleport_keybind_0", Some("set_location_1")),
+    ("spawn_teleport_keybind_1", Some("set_location_2")),
+    ("delete_teleport_keybind_0", Some("delete_location_1")),
+    ("delete_teleport_keybind_1", Some("delete_location_2")),
+    ("spawn_checkpoint_keybind", None),
+];
+
+// ini values that aren't json
+const INI_STRING_KEYS : [&str; 5] = ["runner_name", "livesplit_host", "gradient_mode", "gradient_preset", "sampling_mode"];
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ConfigIssue {
+    pub section: String,
+    // empty for problems with the whole file
+    pub key: String,
+    pub msg: String,
+}
+
+impl fmt::Display for ConfigIssue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match (self.section.is_empty(), self.key.is_empty()) {
+            (true, true) => write!(f, "{}", self.msg),
+            (true, false) => write!(f, "{}: {}", self.key, self.msg),
+            (false, true) => write!(f, "[{}]: {}", self.section, self.msg),
+            (false, false) => write!(f, "{}.{}: {}", self.section, self.key, self.msg),
+        }
+    }
+}
+
+pub fn read_document(file_path: &str) -> Result<Value, Error> {
+    parse_document(&std::fs::read(file_path)?)
+}
+
+pub fn write_document(file_path: &str, document: &Value) -> Result<(), Error> {
+    std::fs::write(file_path, document_text(document)?)?;
+    Ok(())
+}
+
+pub fn parse_document(content: &[u8]) -> Result<Value, Error> {
+    serde_json::from_slice(content).map_err(|e| Error::Parse { msg: e.to_string() })
+}
+
+pub fn document_text(document: &Value) -> Result<String, Error> {
+    serde_json::to_string_pretty(document).map_err(|e| Error::Parse { msg: e.to_string() })
+}
+
+// checks the version and upgrades older documents. returns the last used profile and every profile
+pub fn open_document(document: Value, issues: &mut Vec<ConfigIssue>) -> Result<(String, Map<String, Value>), Error> {
+    let Value::Object(mut document) = document else {
+        return Err(Error::Parse { msg: "config is not a json object".to_string() });
+    };
+
+    let version = match document.remove("schema_version").and_then(|v| v.as_u64()) {
+        Some(version) if version > CONFIG_SCHEMA_VERSION as u64 => {
+            return Err(Error::UnknownVersion { version: version.to_string() });
+        }
+        Some(version) => version,
+        None => {
+            issues.push(ConfigIssue {
+                section: String::new(),
+                key: "schema_version".to_string(),
+                msg: format!("missing or not a number, reading it as version {CONFIG_SCHEMA_VERSION}"),
+            });
+            CONFIG_SCHEMA_VERSION as u64
+        }
+    };
+
+    if version < 2 { migrate_v1(&mut document); }
+    if version < 3 { migrate_v2(&mut document); }
+
+    let active_profile = match document.remove("active_profile") {
+        Some(Value::String(name)) => name,
+        Some(value) => {
+            issues.push(ConfigIssue { section: String::new(), key: "active_profile".to_string(), msg: format!("{value} is not a profile name") });
+            DEFAULT_PROFILE.to_string()
+        }
+        None => DEFAULT_PROFILE.to_string(),
+    };
+
+    let profiles = match document.remove("profiles") {
+        Some(Value::Object(profiles)) => profiles,
+        Some(_) => {
+            issues.push(ConfigIssue { section: String::new(), key: "profiles".to_string(), msg: "not a json object, using defaults".to_string() });
+            Map::new()
+        }
+        None => Map::new(),
+    };
+
+    for key in document.keys() {
+        issues.push(ConfigIssue { section: String::new(), key: key.clone(), msg: "unknown key".to_string() });
+    }
+
+    Ok((active_profile, profiles))
+}
+
+// version 1 had the sections at the top, they become the default profile
+fn migrate_v1(document: &mut Map<String, Value>) {
+    let mut profile = Map::new();
+
+    for section in ["general", "extra"] {
+        if let Some(values) = document.remove(section) {
+            profile.insert(section.to_string(), values);
+        }
+    }
+
+    let mut profiles = Map::new();
+    profiles.insert(DEFAULT_PROFILE.to_string(), Value::Object(profile));
+
+    document.insert("active_profile".to_string(), Value::String(DEFAULT_PROFILE.to_string()));
+    document.insert("profiles".to_string(), Value::Object(profiles));
+}
+
+fn migrate_v2(document: &mut Map<String, Value>) {
+    let Some(Value::Object(profiles)) = document.get_mut("profiles") else { return; };
+
+    for profile in profiles.values_mut() {
+        let Value::Object(profile) = profile else { continue; };
+
+        let mut keybinds = Map::new();
+
+        if let Some(Value::Object(general)) = profile.get_mut("general") {
+            for (old_key, action) in V2_KEYBINDS {
+                let Some(shortcut) = general.remove(old_key) else { continue; };
+                if let Some(action) = action {
+                    keybinds.insert(action.to_string(), shortcut);
+                }
+            }
+        }
+
+        profile.insert("keybinds".to_string(), Value::Object(keybinds));
+    }
+}
+
+// splits a profile into its sections. unknown sections are reported
+pub fn open_profile(profile: Value, sections: &[&str], issues: &mut Vec<ConfigIssue>) -> Vec<Map<String, Value>> {
+    let mut profile = match profile {
+        Value::Object(profile) => profile,
+        _ => {
+            issues.push(ConfigIssue { section: String::new(), key: String::new(), msg: "profile is not a json object, using defaults".to_string() });
+            Map::new()
+        }
+    };
+
+    let mut result = Vec::with_capacity(sections.len());
+
+    for section in sections {
+        match profile.remove(*section) {
+            Some(Value::Object(values)) => result.push(values),
+            Some(_) => {
+                issues.push(ConfigIssue { section: section.to_string(), key: String::new(), msg: "not a json object, using defaults".to_string() });
+                result.push(Map::new());
+            }
+            None => result.push(Map::new()),
+        }
+    }
+
+    for key in profile.keys() {
+        issues.push(ConfigIssue { section: String::new(), key: key.clone(), msg: "unknown section".to_string() });
+    }
+
+    result
+}
+
+// turns celestial.ini into a version 1 document. values that don't convert are kept as they are and
+// get reported when the document is read
+pub fn migrate_ini(file_path: &str) -> Result<Value, Error> {
+    let conf = Ini::load_from_file(file_path)?;
+
+    let mut general_section = conf.section(Some("General"));
+    if general_section.is_none() { general_section = conf.section(Some("Celestial")) } // backwards compatibility
+
+    let mut general = Map::new();
+
+    if let Some(section) = general_section {
+        for (key, value) in section.iter() {
+            general.insert(key.to_string(), migrate_ini_value(key, value));
+        }
+    }
+
+    let mut extra = Map::new();
+
+    if let Some(section) = conf.section(Some("Extra")) {
+        for (key, value) in section.iter() {
+            extra.insert(key.to_string(), migrate_ini_value(key, value));
+        }
+    }
+
+    let mut document = Map::new();
+    document.insert("schema_version".to_string(), Value::from(1));
+    document.insert("general".to_string(), Value::Object(general));
+    document.insert("extra".to_string(), Value::Object(extra));
+
+    Ok(Value::Object(document))
+}
+
+fn migrate_ini_value(key: &str, value: &str) -> Value {
+    if key.contains("keybind") {
+        return match shortcut_from_keycode(value) {
+            Some(shortcut) => Value::String(shortcut_to_name(&shortcut)),
+            None => Value::String(value.to_string()),
+        };
+    }
+
+    if key == "input_suppression_exceptions" {
+        let names = value.split(&['[', ']', ','][..])
+            .map(|name| name.trim())
+            .filter(|name| !name.is_empty())
+            .map(|name| Value::String(name.to_string()))
+            .collect();
+        return Value::Array(names);
+    }
+
+    // used to be egui colors, 0-255
+    if key.starts_with("accent_color") {
+        if let Ok(rgba) = serde_json::from_str::<[u8; 4]>(value) {
+            return Value::from(rgba.map(|c| c as f64 / 255.).to_vec());
+        }
+    }
+
+    if INI_STRING_KEYS.contains(&key) {
+        return Value::String(value.to_string());
+    }
+
+    serde_json::from_str(value).unwrap_or_else(|_| Value::String(value.to_string()))
+}
+
+// the ini stored the key code, "shift+23"
+fn shortcut_from_keycode(stringcut: &str) -> Option<Shortcut> {
+    let mut parts : Vec<&str> = stringcut.split("+").collect();
+    let keycode = parts.pop()?.parse::<u8>().ok()?;
+
+    // key codes are only valid up to the last variant
+    if keycode > Key::F35 as u8 { return None; }
+
+    let mut keyboard : KeyboardShortcut;
+    unsafe { keyboard = KeyboardShortcut::new(Modifiers::NONE, std::mem::transmute::<_, Key>(keycode)); }
+
+    for part in parts {
+        match part {
+            "alt" => keyboard.modifiers.alt = true,
+            "ctrl" => keyboard.modifiers.ctrl = true,
+            "shift" => keyboard.modifiers.shift = true,
+            "mac_cmd" => keyboard.modifiers.mac_cmd = true,
+            "command" => keyboard.modifiers.command = true,
+            _ => return None,
+        }
+    }
+
+    Some(Shortcut::new(Some(keyboard), None))
+}
+
+// "ctrl+shift+K", empty when unbound
+pub fn shortcut_to_name(shortcut: &Shortcut) -> String {
+    let Some(keyboard) = shortcut.keyboard() else { return String::new(); };
+
+    let mods = keyboard.modifiers;
+    let flags = [mods.alt, mods.ctrl, mods.shift, mods.mac_cmd, mods.command];
+
+    let mut name = String::new();
+    for (modifier, set) in MODIFIER_NAMES.iter().zip(flags) {
+        if set { name += modifier; name += "+"; }
+    }
+
+    name + keyboard.logical_key.name()
+}
+
+pub fn shortcut_from_name(name: &str) -> Result<Shortcut, String> {
+    if name.is_empty() { return Ok(Shortcut::new(None, None)); }
+
+    let mut modifiers = Modifiers::NONE;
+    let mut rest = name;
+
+    // the key itself can be "+"
+    while let Some((modifier, tail)) = rest.split_once('+').filter(|(_, tail)| !tail.is_empty()) {
+        match modifier {
+            "alt" => modifiers.alt = true,
+            "ctrl" => modifiers.ctrl = true,
+            "shift" => modifiers.shift = true,
+            "mac_cmd" => modifiers.mac_cmd = true,
+            "command" => modifiers.command = true,
+            _ => return Err(format!("unknown modifier '{modifier}'")),
+        }
+        rest = tail;
+    }
+
+    let key = Key::from_name(rest).ok_or(format!("unknown key '{rest}'"))?;
+
+    Ok(Shortcut::new(Some(KeyboardShortcut::new(modifiers, key)), None))
+}
+
+// [r, g, b] or [r, g, b, a] from 0 to 1, or "#rrggbb" / "#rrggbbaa"
+pub fn parse_color(value: &Value) -> Result<[f32; 4], String> {
+    match value {
+        Value::Array(channels) if channels.len() == 3 || channels.len() == 4 => {
+            let mut color = [1.0; 4];
+
+            for (i, channel) in channels.iter().enumerate() {
+                let c = channel.as_f64().ok_or(format!("{channel} is not a number"))?;
+                if !(0.0..=1.0).contains(&c) {
+                    return Err(format!("{c} is outside 0..=1"));
+                }
+                color[i] = c as f32;
+            }
+
+            Ok(color)
+        }
+        Value::String(hex) => {
+            let digits = hex.strip_prefix('#').unwrap_or(hex);
+
+            if (digits.len() != 6 && digits.len() != 8) || !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+                return Err(format!("'{hex}' is not #rrggbb or #rrggbbaa"));
+            }
+
+            let mut color = [1.0; 4];
+            for i in 0..digits.len() / 2 {
+                color[i] = u8::from_str_radix(&digits[i * 2..i * 2 + 2], 16).unwrap() as f32 / 255.;
+            }
+
+            Ok(color)
+        }
+        _ => Err(format!("{value} is not a color")),
+    }
+}
+
+pub fn color_value(color: [f32; 4]) -> Value {
+    Value::from(color.to_vec())
+}
+
+// reads one section and records everything it can't use. values that are missing keep their default
+pub struct SectionReader<'a> {
+    name: &'static str,
+    values: Map<String, Value>,
+    issues: &'a mut Vec<ConfigIssue>,
+}
+
+impl<'a> SectionReader<'a> {
+    pub fn new(name: &'static str, values: Map<String, Value>, issues: &'a mut Vec<ConfigIssue>) -> SectionReader<'a> {
+        SectionReader { name, values, issues }
+    }
+
+    fn issue(&mut self, key: &str, msg: String) {
+        self.issues.push(ConfigIssue { section: self.name.to_string(), key: key.to_string(), msg });
+    }
+
+    pub fn value<T: DeserializeOwned + fmt::Debug>(&mut self, key: &str, dest: &mut T) {
+        let Some(value) = self.values.remove(key) else { return; };
+
+        match serde_json::from_value(value) {
+            Ok(v) => *dest = v,
+            Err(e) => self.issue(key, format!("{e}, using {dest:?}")),
+        }
+    }
+
+    // out of range values are clamped
+    pub fn ranged<T: DeserializeOwned + PartialOrd + Copy + fmt::Debug>(&mut self, key: &str, dest: &mut T, range: RangeInclusive<T>) {
+        let mut value = *dest;
+        self.value(key, &mut value);
+
+        if range.contains(&value) {
+            *dest = value;
+            return;
+        }
+
+        let clamped = if value < *range.start() { *range.start() } else { *range.end() };
+        self.issue(key, format!("{value:?} is outside {range:?}, using {clamped:?}"));
+        *dest = clamped;
+    }
+
+    pub fn size(&mut self, key: &str, dest: &mut [f32], range: RangeInclusive<f32>) {
+        let Some(value) = self.values.remove(key) else { return; };
+
+        let sizes : Vec<f32> = match serde_json::from_value(value) {
+            Ok(sizes) => sizes,
+            Err(e) => {
+                self.issue(key, format!("{e}, using {dest:?}"));
+                return;
+            }
+        };
+
+        if sizes.len() != dest.len() {
+            self.issue(key, format!("expected {} numbers, got {}, using {dest:?}", dest.len(), sizes.len()));
+            return;
+        }
+
+        for (d, size) in dest.iter_mut().zip(sizes) {
+            *d = size.clamp(*range.start(), *range.end());
+
+            if *d != size {
+                self.issue(key, format!("{size:?} is outside {range:?}, using {d:?}"));
+            }
+        }
+    }
+
+    pub fn named<T: fmt::Debug>(&mut self, key: &str, dest: &mut T, from_name: fn(&str) -> Option<T>) {
+        let Some(value) = self.values.remove(key) else { return; };
+
+        match value.as_str().and_then(from_name) {
+            Some(v) => *dest = v,
+            None => self.issue(key, format!("{value} is not a valid option, using {dest:?}")),
+        }
+    }
+
+    pub fn color(&mut self, key: &str, dest: &mut [f32; 4]) {
+        let Some(value) = self.values.remove(key) else { return; };
+
+        match parse_color(&value) {
+            Ok(color) => *dest = color,
+            Err(e) => self.issue(key, format!("malformed color: {e}")),
+        }
+    }
+
+    pub fn colors(&mut self, key: &str, dest: &mut Vec<[f32; 4]>) {
+        let Some(value) = self.values.remove(key) else { return; };
+
+        let Value::Array(values) = value else {
+            self.issue(key, format!("{value} is not a list of colors"));
+            return;
+        };
+
+        let mut colors = Vec::with_capacity(values.len());
+
+        for value in values {
+            match parse_color(&value) {
+                Ok(color) => colors.push(color),
+                Err(e) => self.issue(key, format!("malformed color, skipped: {e}")),
+            }
+        }
+
+        *dest = colors;
+    }
+
+    pub fn shortcut(&mut self, key: &str, dest: &mut Shortcut) {
+        let Some(value) = self.values.remove(key) else { return; };
+
+        match value.as_str().ok_or(format!("{value} is not a string")).and_then(shortcut_from_name) {
+            Ok(shortcut) => *dest = shortcut,
+            Err(e) => self.issue(key, format!("{e}, using '{}'", shortcut_to_name(dest))),
+        }
+    }
+
+    pub fn keys(&mut self, key: &str, dest: &mut Vec<Key>) {
+        let Some(value) = self.values.remove(key) else { return; };
+
+        let Value::Array(names) = value else {
+            self.issue(key, format!("{value} is not a list of keys"));
+            return;
+        };
+
+        dest.clear();
+
+        for name in names {
+            match name.as_str().and_then(Key::from_name) {
+                Some(k) => dest.push(k),
+                None => self.issue(key, format!("unknown key {name}, skipped")),
+            }
+        }
+    }
+
+    // call after reading every known key
+    pub fn finish(self) {
+        for key in self.values.keys() {
+            self.issues.push(ConfigIssue { section: self.name.to_string(), key: key.clone(), msg: "unknown key".to_string() });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_ini() -> String {
+        format!("[General]\n\
+            start_keybind = ctrl+{}\n\
+            stop_keybind = {}\n\
+            spawn_checkpoint_keybind = 1\n\
+            runner_name = 2B\n\
+            start_trigger_size = [2.0, 3.0, 2.0]\n\
+            accent_color = [255, 0, 51, 255]\n\
+            input_suppression_exceptions = [W, A, S, D]\n\
+            \n\
+            [Extra]\n\
+            extra_teleport_keybind_0 = shift+{}\n",
+            Key::F5 as u8, Key::F6 as u8, Key::Num1 as u8)
+    }
+
+    fn migrate_sample() -> Value {
+        let path = std::env::temp_dir().join(format!("celestial_migrate_{}.ini", std::process::id()));
+        std::fs::write(&path, sample_ini()).unwrap();
+        let document = migrate_ini(path.to_str().unwrap());
+        std::fs::remove_file(&path).unwrap();
+        document.unwrap()
+    }
+
+    #[test]
+    fn ini_keycodes_become_shortcuts() {
+        let document = migrate_sample();
+        let general = &document["general"];
+
+        let start = shortcut_from_name(general["start_keybind"].as_str().unwrap()).unwrap();
+        assert_eq!(start, Shortcut::new(Some(KeyboardShortcut::new(Modifiers::CTRL, Key::F5)), None));
+
+        let stop = shortcut_from_name(general["stop_keybind"].as_str().unwrap()).unwrap();
+        assert_eq!(stop, Shortcut::new(Some(KeyboardShortcut::new(Modifiers::NONE, Key::F6)), None));
+
+        let teleport = shortcut_from_name(document["extra"]["extra_teleport_keybind_0"].as_str().unwrap()).unwrap();
+        assert_eq!(teleport, Shortcut::new(Some(KeyboardShortcut::new(Modifiers::SHIFT, Key::Num1)), None));
+    }
+
+    #[test]
+    fn ini_values_convert() {
+        let document = migrate_sample();
+        let general = &document["general"];
+
+        assert_eq!(document["schema_version"], 1);
+        assert_eq!(general["runner_name"], "2B");
+        assert_eq!(general["start_trigger_size"], serde_json::json!([2.0, 3.0, 2.0]));
+        assert_eq!(general["input_suppression_exceptions"], serde_json::json!(["W", "A", "S", "D"]));
+
+        // 0-255 egui colors become 0-1
+        let accent = parse_color(&general["accent_color"]).unwrap();
+        assert_eq!(accent, [1.0, 0.0, 0.2, 1.0]);
+    }
+
+    #[test]
+    fn ini_document_opens_as_current_version() {
+        let mut issues = Vec::new();
+        let (active_profile, mut profiles) = open_document(migrate_sample(), &mut issues).unwrap();
+
+        assert!(issues.is_empty(), "{issues:?}");
+        assert_eq!(active_profile, DEFAULT_PROFILE);
+
+        let sections = open_profile(profiles.remove(DEFAULT_PROFILE).unwrap(), &["general", "keybinds", "extra"], &mut issues);
+        assert!(issues.is_empty(), "{issues:?}");
+
+        // keybinds moved out of general, the checkpoint keybind is dropped
+        assert!(!sections[0].keys().any(|key| key.contains("keybind")));
+        assert_eq!(sections[1]["start"], "ctrl+F5");
+        assert_eq!(sections[1]["stop"], "F6");
+        assert_eq!(sections[1].len(), 2);
+
+        // extra keybinds stay where they were, version 2 only moved the ones in general
+        assert_eq!(sections[2]["extra_teleport_keybind_0"], "shift+1");
+    }
+
+    #[test]
+    fn v1_sections_become_the_default_profile() {
+        let mut document = serde_json::json!({
+            "general": { "zoom": 2.0 },
+            "extra": { "livesplit_host": "localhost" },
+        });
+
+        migrate_v1(document.as_object_mut().unwrap());
+
+        assert_eq!(document, serde_json::json!({
+            "active_profile": DEFAULT_PROFILE,
+            "profiles": { DEFAULT_PROFILE: {
+                "general": { "zoom": 2.0 },
+                "extra": { "livesplit_host": "localhost" },
+            }},
+        }));
+    }
+
+    #[test]
+    fn v2_keybinds_move_to_their_own_section() {
+        let mut document = serde_json::json!({
+            "profiles": {
+                "any%": { "general": {
+                    "zoom": 2.0,
+                    "reset_keybind": "F3",
+                    "delete_teleport_keybind_1": "ctrl+2",
+                    "spawn_checkpoint_keybind": "F9",
+                }},
+                "empty": {},
+            },
+        });
+
+        migrate_v2(document.as_object_mut().unwrap());
+
+        assert_eq!(document["profiles"]["any%"], serde_json::json!({
+            "general": { "zoom": 2.0 },
+            "keybinds": { "reset": "F3", "delete_location_2": "ctrl+2" },
+        }));
+        assert_eq!(document["profiles"]["empty"], serde_json::json!({ "keybinds": {} }));
+    }
+
+    #[test]
+    fn colors_parse_from_lists_and_hex() {
+        assert_eq!(parse_color(&serde_json::json!([0.5, 0.25, 1.0])), Ok([0.5, 0.25, 1.0, 1.0]));
+        assert_eq!(parse_color(&serde_json::json!([0.0, 0.0, 0.0, 0.5])), Ok([0.0, 0.0, 0.0, 0.5]));
+        assert_eq!(parse_color(&serde_json::json!("#ff0033")), Ok([1.0, 0.0, 0.2, 1.0]));
+        assert_eq!(parse_color(&serde_json::json!("00ff0000")), Ok([0.0, 1.0, 0.0, 0.0]));
+
+        for malformed in [
+            serde_json::json!("#ff00"),
+            serde_json::json!("#ff0033zz"),
+            serde_json::json!("#ff00339"),
+            serde_json::json!([0.5, 0.5]),
+            serde_json::json!([0.5, 1.5, 0.5]),
+            serde_json::json!([0.5, "red", 0.5]),
+            serde_json::json!(16711680),
+        ] {
+            assert!(parse_color(&malformed).is_err(), "{malformed}");
+        }
+    }
+
+    #[test]
+    fn invalid_values_are_reported() {
+        let values = serde_json::json!({
+            "trigger_size": [2.0, 500.0, -1.0],
+            "color": "#12345g",
+            "zoom": 9000.0,
+            "mystery": true,
+        });
+
+        let mut issues = Vec::new();
+        let mut size = [1.0; 3];
+        let mut color = [0.5; 4];
+        let mut zoom = 1.0;
+
+        let mut section = SectionReader::new("general", values.as_object().unwrap().clone(), &mut issues);
+        section.size("trigger_size", &mut size, 0.1..=100.0);
+        section.color("color", &mut color);
+        section.ranged("zoom", &mut zoom, 0.1..=10.0);
+        section.finish();
+
+        // out of range sizes are clamped, a bad color keeps the old one
+        assert_eq!(size, [2.0, 100.0, 0.1]);
+        assert_eq!(color, [0.5; 4]);
+        assert_eq!(zoom, 10.0);
+
+        let keys : Vec<&str> = issues.iter().map(|issue| issue.key.as_str()).collect();
+        assert_eq!(keys, ["trigger_size", "trigger_size", "color", "zoom", "mystery"]);
+        assert!(issues.iter().all(|issue| issue.section == "general"));
+        assert!(issues[2].msg.starts_with("malformed color"));
+        assert_eq!(issues[4].msg, "unknown key");
+    }
+
+    #[test]
+    fn unknown_top_level_keys_are_reported() {
+        let mut issues = Vec::new();
+        let document = serde_json::json!({ "schema_version": CONFIG_SCHEMA_VERSION, "profiles": {}, "theme": "dark" });
+
+        open_document(document, &mut issues).unwrap();
+
+        assert_eq!(issues, [ConfigIssue { section: String::new(), key: "theme".to_string(), msg: "unknown key".to_string() }]);
+    }
+}
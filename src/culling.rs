@@ -0,0 +1,134 @@
+use crate::pathdata::{self, Path};
+
+// nodes per chunk. chunks share their last node with the next one so the line stays connected
+pub const CHUNK_SIZE : usize = 32;
+const MAX_LOD_LEVEL : u32 = 3;
+
+#[derive(Clone)]
+pub struct PathChunk {
+    pub nodes: Vec<[f32; 3]>,
+    pub min: [f32; 3],
+    pub max: [f32; 3],
+}
+
+// tolerance > 0 simplifies the drawn geometry without touching the path itself
+pub fn chunk_path(path: &Path, tolerance: f32) -> Vec<PathChunk> {
+    let mut chunks = Vec::new();
+
+    for segment_index in 0..path.segment_count() {
+        let segment = pathdata::simplify_nodes(path.segment(segment_index).unwrap(), tolerance);
+        if segment.len() < 2 { continue; }
+
+        let mut start = 0;
+        while start < segment.len() - 1 {
+            let end = (start + CHUNK_SIZE + 1).min(segment.len());
+
+            let mut min = segment[start];
+            let mut max = segment[start];
+            for node in &segment[start..end] {
+                for i in 0..3 {
+                    min[i] = min[i].min(node[i]);
+                    max[i] = max[i].max(node[i]);
+                }
+            }
+
+            chunks.push(PathChunk { nodes: segment[start..end].to_vec(), min, max });
+            start = end - 1;
+        }
+    }
+
+    chunks
+}
+
+pub struct Frustum {
+    planes: [[f32; 4]; 6],
+}
+
+impl Frustum {
+    // the game hands us a d3d style view projection matrix meant for row vectors (clip = pos * m),
+    // so the planes come from its columns
+    pub fn from_view_proj(m: &[[f32; 4]; 4]) -> Frustum {
+        let column = |j: usize| [m[0][j], m[1][j], m[2][j], m[3][j]];
+        let add = |a: [f32; 4], b: [f32; 4]| [a[0] + b[0], a[1] + b[1], a[2] + b[2], a[3] + b[3]];
+        let sub = |a: [f32; 4], b: [f32; 4]| [a[0] - b[0], a[1] - b[1], a[2] - b[2], a[3] - b[3]];
+
+        let (x, y, z, w) = (column(0), column(1), column(2), column(3));
+
+        Frustum {
+            planes: [
+                add(w, x),
+                sub(w, x),
+                add(w, y),
+                sub(w, y),
+                z, // d3d clip space z goes from 0 to w
+                sub(w, z),
+            ]
+        }
+    }
+
+    pub fn contains_point(&self, point: [f32; 3]) -> bool {
+        self.planes.iter().all(|p| p[0] * point[0] + p[1] * point[1] + p[2] * point[2] + p[3] >= 0.)
+    }
+
+    // conservative, boxes near the corners can count as visible
+    pub fn intersects_box(&self, min: [f32; 3], max: [f32; 3]) -> bool {
+        for p in &self.planes {
+            // corner furthest along the plane normal
+            let x = if p[0] >= 0. { max[0] } else { min[0] };
+            let y = if p[1] >= 0. { max[1] } else { min[1] };
+            let z = if p[2] >= 0. { max[2] } else { min[2] };
+
+            if p[0] * x + p[1] * y + p[2] * z + p[3] < 0. { return false; }
+        }
+        true
+    }
+}
+
+pub fn box_distance(min: [f32; 3], max: [f32; 3], point: [f32; 3]) -> f32 {
+    let mut sum = 0.;
+    for i in 0..3 {
+        let d = (min[i] - point[i]).max(point[i] - max[i]).max(0.);
+        sum += d * d;
+    }
+    sum.sqrt()
+}
+
+pub struct CullSettings {
+    pub frustum: Option<Frustum>,
+    pub viewer: [f32; 3],
+    // 0 disables distance culling
+    pub cull_distance: f32,
+    // 0 disables lod
+    pub lod_distance: f32,
+}
+
+impl CullSettings {
+    // node stride to draw the chunk with, 0 means culled
+    pub fn chunk_stride(&self, chunk: &PathChunk) -> u8 {
+        if let Some(frustum) = &self.frustum {
+            if !frustum.intersects_box(chunk.min, chunk.max) { return 0; }
+        }
+
+        let distance = box_distance(chunk.min, chunk.max, self.viewer);
+
+        if self.cull_distance > 0. && distance > self.cull_distance { return 0; }
+
+        if self.lod_distance > 0. && distance > self.lod_distance {
+            let level = ((distance / self.lod_distance) as u32).min(MAX_LOD_LEVEL);
+            return 1 << level;
+        }
+
+        1
+    }
+}
+
+// every stride-th node of the chunk, always keeping both ends
+pub fn chunk_nodes(chunk: &PathChunk, stride: u8) -> Vec<[f32; 3]> {
+    let mut nodes : Vec<[f32; 3]> = chunk.nodes.iter().step_by(stride.max(1) as usize).cloned().collect();
+
+    if !(chunk.nodes.len() - 1).is_multiple_of(stride.max(1) as usize) {
+        nodes.push(chunk.nodes[chunk.nodes.len() - 1]);
+    }
+
+    nodes
+}
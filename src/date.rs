@@ -0,0 +1,53 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// all dates are utc, the game doesn't tell us the timezone and it doesn't matter much for runs
+
+pub fn unix_time() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs())
+}
+
+// [year, month, day, hour, minute, second]
+pub fn utc_date_time(seconds: u64) -> [u64; 6] {
+    let days = (seconds / 86400) as i64;
+    let time = seconds % 86400;
+
+    // days to civil date (howard hinnant)
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    [year as u64, month as u64, day as u64, time / 3600, time % 3600 / 60, time % 60]
+}
+
+// inverse of the above, seconds at the start of the day
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year.rem_euclid(400);
+    let doy = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146097 + doe - 719468
+}
+
+pub fn format_date(seconds: u64) -> String {
+    let [year, month, day, ..] = utc_date_time(seconds);
+    format!("{year:04}-{month:02}-{day:02}")
+}
+
+// YYYY-MM-DD
+pub fn parse_date(text: &str) -> Option<u64> {
+    let mut parts = text.trim().split('-').map(|p| p.parse::<i64>().ok());
+    let (Some(Some(year)), Some(Some(month)), Some(Some(day)), None) = (parts.next(), parts.next(), parts.next(), parts.next()) else {
+        return None;
+    };
+
+    if year < 1970 || !(1..=12).contains(&month) || !(1..=31).contains(&day) { return None; }
+
+    Some(days_from_civil(year, month, day) as u64 * 86400)
+}
@@ -14,6 +14,21 @@ pub enum Error {
     Binary {
         msg: String,
     },
+    Truncated {
+        msg: String,
+    },
+    UnknownVersion {
+        version: String,
+    },
+    CorruptField {
+        field: String,
+        msg: String,
+    },
+    WrongMagic,
+    ChecksumMismatch,
+    UnknownCollection {
+        id: String,
+    },
 }
 
 impl fmt::Display for Error {
@@ -22,6 +37,12 @@ impl fmt::Display for Error {
             Error::IO{ msg } => format!("Failed to read/write file!: {}", msg),
             Error::Parse{ msg } => format!("Failed to parse file!: {}", msg),
             Error::Binary{ msg } => format!("Failed to decode file!: {}", msg),
+            Error::Truncated{ msg } => format!("File is truncated!: {}", msg),
+            Error::UnknownVersion{ version } => format!("File version {} is not supported!", version),
+            Error::CorruptField{ field, msg } => format!("File is corrupt!: {}: {}", field, msg),
+            Error::WrongMagic => "Not a comparison file!".to_string(),
+            Error::ChecksumMismatch => "File is damaged (checksum mismatch)! It might not have been saved completely.".to_string(),
+            Error::UnknownCollection{ id } => format!("Collection {} doesn't exist!", id),
         };
         write!(f, "{err_msg}")
     }
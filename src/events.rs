@@ -1,17 +1,23 @@
 use std::collections::VecDeque;
 use egui::Key;
 use uuid::Uuid;
-use std::sync::mpsc;
-use std::thread;
-use native_dialog::FileDialog;
+use serde::{Serialize, Deserialize};
 
 use tracing::*;
-use crate::{gamedata, RenderUpdates, CONFIG_STATE, EVENTS, PATHLOG, RENDER_UPDATES, UI_STATE, RX};
-use crate::pathdata::{HighPassFilter, FILE_EXTENTION};
-use crate::config::CONFIG_FILE_NAME;
-use crate::ui::{Teleport, TeleportIndex};
+use crate::{RenderUpdates, CAPTURE, CONFIG_STATE, EVENTS, PATHLOG, RENDER_UPDATES, UI_STATE, RX};
+use crate::capture::Capture;
+use crate::comparison;
+use crate::pathdata::{DisplayFilter, HighPassFilter};
+use crate::pathlog::PathLog;
+use crate::config::{ConfigState, CONFIG_FILE_NAME};
+use crate::configfile;
+use crate::error::Error;
+use crate::services::{FileDialogKind, GameServices, Services};
+use crate::snapshot;
+use crate::ui::{Teleport, TeleportIndex, UIState};
 
 #[derive(Clone)]
+#[derive(Serialize, Deserialize)]
 pub enum CelEvent {
     DeletePath {
         path_id: Uuid,
@@ -36,8 +42,13 @@ pub enum CelEvent {
     StartRecording,
     StopRecording,
     ResetRecording,
+    TogglePause,
     ClearTriggers,
     CreateCollection,
+    CycleActiveCollection {
+        forward: bool,
+    },
+    NextComparisonMode,
     RenameCollection {
         id: Uuid,
         new_name: String,
@@ -68,8 +79,45 @@ pub enum CelEvent {
     },
     SaveComparison,
     LoadComparison,
+    RestoreBackup {
+        backup_path: String,
+    },
+    CompactComparison,
+    RecoverRuns {
+        collection_id: Option<Uuid>,
+    },
+    DiscardRecoveredRuns,
+    SetDisplayFilter {
+        filter: DisplayFilter,
+    },
+    SetPathNotes {
+        path_id: Uuid,
+        notes: String,
+    },
+    AddPathTag {
+        path_id: Uuid,
+        tag: String,
+    },
+    RemovePathTag {
+        path_id: Uuid,
+        tag: String,
+    },
     SaveConfig,
     LoadConfig,
+    SwitchProfile {
+        name: String,
+    },
+    CreateProfile {
+        name: String,
+    },
+    DuplicateProfile {
+        name: String,
+    },
+    RenameProfile {
+        new_name: String,
+    },
+    StartCapture,
+    StopCapture,
     SelectPath {
         path_id: Uuid,
         collection_id: Uuid,
@@ -88,367 +136,751 @@ pub enum CelEvent {
         update: RenderUpdates,
     },
     NewException {
+        #[serde(with = "crate::capture::key_name")]
         key: Key,
     },
     RemoveException {
+        #[serde(with = "crate::capture::key_name")]
         key: Key,
     },
 }
 
-pub fn process_events() {
-    let mut loop_events : VecDeque<CelEvent> = VecDeque::new();
-
-    let mut events = EVENTS.lock().unwrap();
+// everything the events work on, locked once per frame
+pub struct AppState<'a> {
+    pub pathlog: &'a mut PathLog,
+    pub config: &'a mut ConfigState,
+    pub ui: &'a mut UIState,
+    pub render_updates: &'a mut RenderUpdates,
+    pub capture: &'a mut Capture,
+}
 
-    let mut event_list = events.clone();
-    events.clear();
+fn write_config(config: &mut ConfigState, services: &mut dyn Services) -> Result<(), Error> {
+    let text = configfile::document_text(&config.to_document())?;
+    services.write_file(std::path::Path::new(CONFIG_FILE_NAME), text.as_bytes())?;
+    info!("Config saved");
+    Ok(())
+}
 
-    drop(events);
+// saved right away so the next start uses the same profile
+fn finish_profile_change(state: &mut AppState, services: &mut dyn Services, result: Result<(), String>, loop_events: &mut VecDeque<CelEvent>) {
+    match result {
+        Ok(()) => {
+            state.ui.profile_error = None;
 
-    while let Some(event) = event_list.pop_front() {
-        match event {
-            CelEvent::DeletePath { path_id } => {
-                PATHLOG.lock().unwrap().delete_path(path_id);
-                loop_events.push_back(CelEvent::RenderUpdate { update: RenderUpdates::paths() });
-            }
-            CelEvent::ChangeDirectMode { new } => {
-                PATHLOG.lock().unwrap().set_direct_mode(new);
-            }
-            CelEvent::ChangeAutosave { new } => {
-                PATHLOG.lock().unwrap().set_autosave(new);
-            }
-            CelEvent::ChangeAutoReset { new } => {
-                PATHLOG.lock().unwrap().set_autoreset(new);
-            }
-            CelEvent::SpawnTrigger { index, position, rotation } => {
-                let trigger_size = CONFIG_STATE.lock().unwrap().trigger_sizes[index];
-                if PATHLOG.lock().unwrap().is_empty() {
-                    PATHLOG.lock().unwrap().create_trigger(index, position, rotation, trigger_size);
-                    EVENTS.lock().unwrap().push_back(CelEvent::SpawnTeleport { index: TeleportIndex::Main { i: index } });
-                }
-                else {
-                    // TODO: popup warning
-                }
+            if let Err(e) = write_config(state.config, services) {
+                error!("{e}");
+                state.ui.profile_error = Some(format!("Profile changed but the config wasn't saved: {e}"));
             }
-            CelEvent::DeleteTrigger { id } => {
-                let pos = PATHLOG.lock().unwrap().checkpoint_triggers.iter().position(|t| t.id() == id);
-                if let Some(i) = pos {
-                    PATHLOG.lock().unwrap().checkpoint_triggers.remove(i);
-                    continue;
-                }
 
-                let mut main_triggers = PATHLOG.lock().unwrap().main_triggers;
-                let mut main_teleports = UI_STATE.lock().unwrap().main_teleports;
+            loop_events.push_back(CelEvent::RenderUpdate { update: RenderUpdates::paths() });
+            loop_events.push_back(CelEvent::RenderUpdate { update: RenderUpdates::settings() });
+        }
+        Err(e) => {
+            error!("{e}");
+            state.ui.profile_error = Some(e);
+        }
+    }
+}
 
-                for t in 0..2 {
-                    if let Some(trigger) = main_triggers[t] {
-                        if trigger.id() == id {
-                            main_triggers[t] = None;
-                            main_teleports[t] = None;
-                        }
-                    }
-                }
+fn teleports_from_triggers(state: &mut AppState) {
+    for i in 0..2 {
+        if let Some(trigger) = state.pathlog.main_triggers[i] {
+            state.ui.main_teleports[i] = Some(Teleport {
+                location: trigger.position,
+                rotation: trigger.rotation(),
+                camera_rotation: None,
+            })
+        }
+    }
+}
 
-                PATHLOG.lock().unwrap().main_triggers = main_triggers;
-                UI_STATE.lock().unwrap().main_teleports = main_teleports;
-            }
-            CelEvent::StartRecording => {
-                PATHLOG.lock().unwrap().start();
-            }
-            CelEvent::StopRecording => {
-                let mut pathlog = PATHLOG.lock().unwrap();
+// the queued events of this frame against the globals and the game
+pub fn process_queued_events() {
+    let event_list = std::mem::take(&mut *EVENTS.lock().unwrap());
+
+    // always in this order
+    let mut config = CONFIG_STATE.lock().unwrap();
+    let mut ui_state = UI_STATE.lock().unwrap();
+    let mut pathlog = PATHLOG.lock().unwrap();
+    let mut render_updates = RENDER_UPDATES.lock().unwrap();
+    let mut capture = CAPTURE.lock().unwrap();
+
+    let mut state = AppState {
+        pathlog: &mut pathlog,
+        config: &mut config,
+        ui: &mut ui_state,
+        render_updates: &mut render_updates,
+        capture: &mut capture,
+    };
+
+    let mut loop_events = process_events(&mut state, &mut GameServices, event_list);
+    snapshot::publish(&mut state);
+
+    drop(capture);
+    drop(render_updates);
+    drop(pathlog);
+    drop(ui_state);
+    drop(config);
 
-                let recording_path_id = pathlog.recording_path.id();
-                pathlog.mute_paths.insert(recording_path_id, false);
-                pathlog.solo_paths.insert(recording_path_id, false);
-                pathlog.stop();
+    EVENTS.lock().unwrap().append(&mut loop_events);
+}
 
-                drop(pathlog);
+// handles one frame of events, returns the ones for the next frame
+pub fn process_events(state: &mut AppState, services: &mut dyn Services, mut events: VecDeque<CelEvent>) -> VecDeque<CelEvent> {
+    state.capture.record_events(&events);
 
-                loop_events.push_back(CelEvent::RenderUpdate { update: RenderUpdates::paths() });
-            }
-            CelEvent::ResetRecording => {
-                PATHLOG.lock().unwrap().reset();
-            }
-            CelEvent::ClearTriggers => {
-                PATHLOG.lock().unwrap().clear_triggers();
-                UI_STATE.lock().unwrap().main_teleports = [None; 2];
-            }
-            CelEvent::CreateCollection => {
-                PATHLOG.lock().unwrap().create_collection();
-            }
-            CelEvent::RenameCollection { id, new_name } => {
-                PATHLOG.lock().unwrap().rename_collection(id, new_name);
+    let mut loop_events : VecDeque<CelEvent> = VecDeque::new();
+
+    while let Some(event) = events.pop_front() {
+        dispatch(state, services, event, &mut loop_events);
+    }
+
+    loop_events
+}
+
+pub fn dispatch(state: &mut AppState, services: &mut dyn Services, event: CelEvent, loop_events: &mut VecDeque<CelEvent>) {
+    match event {
+        CelEvent::DeletePath { path_id } => {
+            state.pathlog.delete_path(path_id);
+            loop_events.push_back(CelEvent::RenderUpdate { update: RenderUpdates::paths() });
+        }
+        CelEvent::ChangeDirectMode { new } => {
+            state.pathlog.set_direct_mode(new);
+        }
+        CelEvent::ChangeAutosave { new } => {
+            state.pathlog.set_autosave(new);
+        }
+        CelEvent::ChangeAutoReset { new } => {
+            state.pathlog.set_autoreset(new);
+        }
+        CelEvent::SpawnTrigger { index, position, rotation } => {
+            let trigger_size = state.config.trigger_sizes[index];
+            if state.pathlog.is_empty() {
+                state.pathlog.create_trigger(index, position, rotation, trigger_size);
+                loop_events.push_back(CelEvent::SpawnTeleport { index: TeleportIndex::Main { i: index } });
             }
-            CelEvent::MoveCollection { id, direction, to_end } => {
-                PATHLOG.lock().unwrap().move_collection(id, direction, to_end);
+            else {
+                // TODO: popup warning
             }
-            CelEvent::DeleteCollection { id } => {
-                PATHLOG.lock().unwrap().delete_collection(id);
-                loop_events.push_back(CelEvent::RenderUpdate { update: RenderUpdates::paths() });
+        }
+        CelEvent::DeleteTrigger { id } => {
+            let pos = state.pathlog.checkpoint_triggers.iter().position(|t| t.id() == id);
+            if let Some(i) = pos {
+                state.pathlog.checkpoint_triggers.remove(i);
+                return;
             }
-            CelEvent::ToggleMute { id } => {
-                let mut pathlog = PATHLOG.lock().unwrap();
-
-                if let Some(b) = pathlog.mute_paths.get_mut(&id) { *b ^= true; }
-                if let Some(b) = pathlog.mute_collections.get_mut(&id) { *b ^= true; }
-                pathlog.update_visible();
-
-                drop(pathlog);
 
-                loop_events.push_back(CelEvent::RenderUpdate { update: RenderUpdates::paths() });
-            },
-            CelEvent::ToggleSolo { id } => {
-                let mut pathlog = PATHLOG.lock().unwrap();
+            for t in 0..2 {
+                if let Some(trigger) = state.pathlog.main_triggers[t] {
+                    if trigger.id() == id {
+                        state.pathlog.main_triggers[t] = None;
+                        state.ui.main_teleports[t] = None;
+                    }
+                }
+            }
+        }
+        CelEvent::StartRecording => {
+            state.pathlog.start();
+        }
+        CelEvent::StopRecording => {
+            let pathlog = &mut *state.pathlog;
 
-                if let Some(b) = pathlog.solo_paths.get_mut(&id) { *b ^= true; }
-                if let Some(b) = pathlog.solo_collections.get_mut(&id) { *b ^= true; }
-                pathlog.update_visible();
+            let recording_path_id = pathlog.recording_path.id();
+            pathlog.mute_paths.insert(recording_path_id, false);
+            pathlog.solo_paths.insert(recording_path_id, false);
+            pathlog.stop();
 
-                drop(pathlog);
+            loop_events.push_back(CelEvent::RenderUpdate { update: RenderUpdates::paths() });
+        }
+        CelEvent::ResetRecording => {
+            state.pathlog.reset();
+        }
+        CelEvent::TogglePause => {
+            state.pathlog.toggle_pause();
+        }
+        CelEvent::ClearTriggers => {
+            state.pathlog.clear_triggers();
+            state.ui.main_teleports = [None; 2];
+        }
+        CelEvent::CreateCollection => {
+            state.pathlog.create_collection();
+        }
+        CelEvent::CycleActiveCollection { forward } => {
+            state.pathlog.cycle_active_collection(forward);
+        }
+        CelEvent::NextComparisonMode => {
+            let mut strategies = comparison::strategies();
+            let current = strategies.iter().position(|s| s.name() == state.pathlog.comparison().mode).unwrap_or(0);
 
-                loop_events.push_back(CelEvent::RenderUpdate { update: RenderUpdates::paths() });
-            },
-            CelEvent::ToggleActive { id } => {
-                let mut pathlog = PATHLOG.lock().unwrap();
+            state.pathlog.set_comparison_strategy(strategies.swap_remove((current + 1) % strategies.len()));
+            loop_events.push_back(CelEvent::RenderUpdate { update: RenderUpdates::paths() });
+        }
+        CelEvent::RenameCollection { id, new_name } => {
+            state.pathlog.rename_collection(id, new_name);
+        }
+        CelEvent::MoveCollection { id, direction, to_end } => {
+            state.pathlog.move_collection(id, direction, to_end);
+        }
+        CelEvent::DeleteCollection { id } => {
+            state.pathlog.delete_collection(id);
+            loop_events.push_back(CelEvent::RenderUpdate { update: RenderUpdates::paths() });
+        }
+        CelEvent::ToggleMute { id } => {
+            let pathlog = &mut *state.pathlog;
+
+            if let Some(b) = pathlog.mute_paths.get_mut(&id) { *b ^= true; }
+            if let Some(b) = pathlog.mute_collections.get_mut(&id) { *b ^= true; }
+            pathlog.update_visible();
+
+            loop_events.push_back(CelEvent::RenderUpdate { update: RenderUpdates::paths() });
+        },
+        CelEvent::ToggleSolo { id } => {
+            let pathlog = &mut *state.pathlog;
+
+            if let Some(b) = pathlog.solo_paths.get_mut(&id) { *b ^= true; }
+            if let Some(b) = pathlog.solo_collections.get_mut(&id) { *b ^= true; }
+            pathlog.update_visible();
+
+            loop_events.push_back(CelEvent::RenderUpdate { update: RenderUpdates::paths() });
+        },
+        CelEvent::ToggleActive { id } => {
+            if state.pathlog.active_collection == Some(id) {
+                state.pathlog.active_collection = None;
+            }
+            else {
+                state.pathlog.active_collection = Some(id);
+            }
+        }
+        CelEvent::ToggleGoldFilter { collection_id } => {
+            let filters = &mut state.pathlog.filters;
 
-                if pathlog.active_collection == Some(id) {
-                    pathlog.active_collection = None;
+            if !filters.contains_key(&collection_id) {
+                filters.insert(collection_id, HighPassFilter::Gold);
+            }
+            else {
+                if let Some(HighPassFilter::Gold) = filters.get(&collection_id) {
+                    filters.remove(&collection_id);
                 }
                 else {
-                    pathlog.active_collection = Some(id);
+                    *filters.get_mut(&collection_id).unwrap() = HighPassFilter::Gold;
                 }
-
-                drop(pathlog);
             }
-            CelEvent::ToggleGoldFilter { collection_id } => {
-                let mut pathlog = PATHLOG.lock().unwrap();
+        }
+        CelEvent::SetPathFilter { collection_id, path_id } => {
+            let filters = &mut state.pathlog.filters;
 
-                if !pathlog.filters.contains_key(&collection_id) {
-                    pathlog.filters.insert(collection_id, HighPassFilter::Gold);
-                }
-                else {
-                    if let Some(HighPassFilter::Gold) = pathlog.filters.get(&collection_id) {
-                        pathlog.filters.remove(&collection_id);
+            match filters.get_mut(&collection_id) {
+                Some(HighPassFilter::Path{ id }) => {
+                    if *id == path_id {
+                        filters.remove(&collection_id);
                     }
                     else {
-                        *pathlog.filters.get_mut(&collection_id).unwrap() = HighPassFilter::Gold;
+                        *filters.get_mut(&collection_id).unwrap() = HighPassFilter::Path { id: path_id };
                     }
+                },
+                Some(HighPassFilter::Gold) => {
+                    *filters.get_mut(&collection_id).unwrap() = HighPassFilter::Path { id: path_id };
                 }
-
-                drop(pathlog);
-            }
-            CelEvent::SetPathFilter { collection_id, path_id } => {
-                let mut pathlog = PATHLOG.lock().unwrap();
-
-                match pathlog.filters.get_mut(&collection_id) {
-                    Some(HighPassFilter::Path{ id }) => {
-                        if *id == path_id {
-                            pathlog.filters.remove(&collection_id);
-                        }
-                        else {
-                            *pathlog.filters.get_mut(&collection_id).unwrap() = HighPassFilter::Path { id: path_id };
-                        }
-                    },
-                    Some(HighPassFilter::Gold) => {
-                        *pathlog.filters.get_mut(&collection_id).unwrap() = HighPassFilter::Path { id: path_id };
-                    }
-                    _ => {
-                        pathlog.filters.insert(collection_id, HighPassFilter::Path{ id: path_id });
-                    }
+                _ => {
+                    filters.insert(collection_id, HighPassFilter::Path{ id: path_id });
                 }
-
-                drop(pathlog);
             }
-            CelEvent::SaveComparison => {
-                let mut ui_state = UI_STATE.lock().unwrap();
-
-                if ui_state.file_path_rx.is_none() {
-                    let (tx, rx) = mpsc::channel();
-                    thread::spawn(move || {
-                            tx.send(
-                                FileDialog::new()
-                                .add_filter("Celestial Comparison", &[FILE_EXTENTION])
-                                .set_filename("Untitled")
-                                .show_save_single_file()
-                            ).unwrap();
-                    });
-                    ui_state.file_path_rx = Some(RX::Save { rx });
-                    loop_events.push_back(CelEvent::SaveComparison);
-                }
-                else if let Some(RX::Save { rx }) = &ui_state.file_path_rx {
-                    if let Ok(dialog_result) = rx.try_recv() {
-                        drop(ui_state);
-
-                        if let Ok(Some(path)) = dialog_result {
-                            PATHLOG.lock().unwrap().save_comparison(path.to_str().unwrap().to_string());
+        }
+        CelEvent::SaveComparison => {
+            if state.ui.file_path_rx.is_none() {
+                let rx = services.file_dialog(FileDialogKind::SaveComparison);
+                state.ui.file_path_rx = Some(RX::Save { rx });
+                loop_events.push_back(CelEvent::SaveComparison);
+            }
+            else if let Some(RX::Save { rx }) = &state.ui.file_path_rx {
+                if let Ok(dialog_result) = rx.try_recv() {
+                    if let Some(path) = dialog_result {
+                        if let Err(e) = state.pathlog.save_comparison(path.to_str().unwrap().to_string(), services) {
+                            state.ui.file_status = Some(e.to_string());
+                            error!("{e}");
                         }
-                        UI_STATE.lock().unwrap().file_path_rx = None;
                     }
-                    else { loop_events.push_back(CelEvent::SaveComparison); }
+                    state.ui.file_path_rx = None;
                 }
+                else { loop_events.push_back(CelEvent::SaveComparison); }
             }
-            CelEvent::LoadComparison => {
-                let mut ui_state = UI_STATE.lock().unwrap();
-
-                if ui_state.file_path_rx.is_none() {
-                    let (tx, rx) = mpsc::channel();
-                    thread::spawn(move || {
-                        tx.send(
-                            FileDialog::new()
-                            .add_filter("Celestial Comparison", &[FILE_EXTENTION])
-                            .add_filter("Any", &["*"])
-                            .show_open_single_file()
-                        ).unwrap();
-                    });
-                    ui_state.file_path_rx = Some(RX::Load { rx });
-                    loop_events.push_back(CelEvent::LoadComparison);
-                }
-                else if let Some(RX::Load { rx }) = &ui_state.file_path_rx {
-                    if let Ok(dialog_result) = rx.try_recv() {
-                        drop(ui_state);
-
-                        if let Ok(Some(path)) = dialog_result {
-                            let load_res = PATHLOG.lock().unwrap().load_comparison(path.to_str().unwrap().to_string());
-                            // if let Err(e) = PATHLOG.lock().unwrap().load_comparison(path.to_str().unwrap().to_string()) {
-                            if let Err(e) = load_res {
-                                UI_STATE.lock().unwrap().file_path_rx = None;
-                                error!("{e}");
-                                continue;
-                            }
-
-                            if let Some(start_trigger) = PATHLOG.lock().unwrap().main_triggers[0] {
-                                UI_STATE.lock().unwrap().main_teleports[0] = Some(Teleport {
-                                    location: start_trigger.position,
-                                    rotation: start_trigger.rotation(),
-                                    camera_rotation: None,
-                                })
-                            }
-
-                            if let Some(end_trigger) = PATHLOG.lock().unwrap().main_triggers[1] {
-                                UI_STATE.lock().unwrap().main_teleports[1] = Some(Teleport {
-                                    location: end_trigger.position,
-                                    rotation: end_trigger.rotation(),
-                                    camera_rotation: None,
-                                })
-                            }
+        }
+        CelEvent::LoadComparison => {
+            if state.ui.file_path_rx.is_none() {
+                let rx = services.file_dialog(FileDialogKind::LoadComparison);
+                state.ui.file_path_rx = Some(RX::Load { rx });
+                loop_events.push_back(CelEvent::LoadComparison);
+            }
+            else if let Some(RX::Load { rx }) = &state.ui.file_path_rx {
+                if let Ok(dialog_result) = rx.try_recv() {
+                    if let Some(path) = dialog_result {
+                        if let Err(e) = state.pathlog.load_comparison(path.to_str().unwrap().to_string(), services) {
+                            state.ui.file_path_rx = None;
+                            state.ui.file_status = Some(e.to_string());
+                            error!("{e}");
+                            return;
                         }
 
-                        UI_STATE.lock().unwrap().file_path_rx = None;
-                        loop_events.push_back(CelEvent::RenderUpdate { update: RenderUpdates::paths() });
+                        state.ui.file_status = None;
+                        teleports_from_triggers(state);
                     }
-                    else { loop_events.push_back(CelEvent::LoadComparison); }
+
+                    state.ui.file_path_rx = None;
+                    loop_events.push_back(CelEvent::RenderUpdate { update: RenderUpdates::all_paths() });
                 }
+                else { loop_events.push_back(CelEvent::LoadComparison); }
             }
-            CelEvent::SaveConfig => {
-                if let Err(e) = CONFIG_STATE.lock().unwrap().write(CONFIG_FILE_NAME.to_string()) {
-                    error!("{e}");
+        }
+        CelEvent::RestoreBackup { backup_path } => {
+            match state.pathlog.restore_backup(backup_path.clone(), services) {
+                Ok(()) => {
+                    info!("Restored {backup_path}");
+                    state.ui.file_status = Some(format!("Restored {backup_path}"));
+                    teleports_from_triggers(state);
+                    loop_events.push_back(CelEvent::RenderUpdate { update: RenderUpdates::all_paths() });
                 }
-            },
-            CelEvent::LoadConfig => {
-                if let Err(e) = CONFIG_STATE.lock().unwrap().read(CONFIG_FILE_NAME.to_string()) {
+                Err(e) => {
                     error!("{e}");
+                    state.ui.file_status = Some(e.to_string());
                 }
-            },
-            CelEvent::SelectPath { path_id, collection_id, modifier } => {
-                let pathlog = PATHLOG.lock().unwrap();
-
-                let collection = pathlog.get_collection(collection_id).unwrap().clone();
-                let path = pathlog.path(&path_id).unwrap().clone();
-
-                let mut selected = pathlog.selected_paths.get(&collection_id).unwrap().clone();
-
-                drop(pathlog);
-
-                match modifier {
-                    1 => {
-                        let last_id = *selected.last().unwrap_or(&(path.id()));
-                        let mut last_pos = collection.paths().iter().position(|p| *p == last_id).unwrap();
-                        let mut this_pos = collection.paths().iter().position(|p| *p == path.id()).unwrap();
-                        if last_pos < this_pos { (last_pos, this_pos) = (this_pos + 1, last_pos + 1) }
-                        for p in &collection.paths()[this_pos..last_pos] {
-                            if let Some(pos) = selected.iter().position(|id| *id == *p) { selected.remove(pos);}
-                            else { selected.push(*p) }
-                        }
-                    },
-                    2 => {
-                        if let Some(pos) = selected.iter().position(|id| *id == path.id()) { selected.remove(pos);}
-                        else { selected.push(path.id()) }
-                    },
-                    _ => {
-                        selected.clear();
-                        selected.push(path.id());
-                    }
-                }
+            }
+        }
+        CelEvent::RecoverRuns { collection_id } => {
+            state.pathlog.recover_runs(collection_id);
+            loop_events.push_back(CelEvent::RenderUpdate { update: RenderUpdates::all_paths() });
+        }
+        CelEvent::DiscardRecoveredRuns => {
+            state.pathlog.discard_recovered_runs();
+        }
+        CelEvent::SetDisplayFilter { filter } => {
+            state.pathlog.set_display_filter(filter);
+            loop_events.push_back(CelEvent::RenderUpdate { update: RenderUpdates::paths() });
+        }
+        CelEvent::SetPathNotes { path_id, notes } => {
+            state.pathlog.set_path_notes(path_id, notes);
+        }
+        CelEvent::AddPathTag { path_id, tag } => {
+            state.pathlog.add_path_tag(path_id, tag);
+            loop_events.push_back(CelEvent::RenderUpdate { update: RenderUpdates::paths() });
+        }
+        CelEvent::RemovePathTag { path_id, tag } => {
+            state.pathlog.remove_path_tag(path_id, &tag);
+            loop_events.push_back(CelEvent::RenderUpdate { update: RenderUpdates::paths() });
+        }
+        CelEvent::CompactComparison => {
+            let report = state.pathlog.compact(state.config.simplify_tolerance);
+
+            info!("{report}");
+            state.ui.file_status = Some(report.to_string());
+        }
+        CelEvent::SaveConfig => {
+            if let Err(e) = write_config(state.config, services) {
+                error!("{e}");
+            }
+        },
+        CelEvent::LoadConfig => {
+            let document = services.read_file(std::path::Path::new(CONFIG_FILE_NAME)).and_then(|content| configfile::parse_document(&content));
 
-                PATHLOG.lock().unwrap().selected_paths.insert(collection_id, selected);
-                loop_events.push_back(CelEvent::RenderUpdate { update: RenderUpdates::paths() });
+            if let Err(e) = state.config.open(document) {
+                error!("{e}");
             }
-            CelEvent::Teleport { index } => {
-                let t = match index {
-                    TeleportIndex::Main { i } => {
-                        UI_STATE.lock().unwrap().main_teleports[i]
-                    },
-                    TeleportIndex::Extra { i } => {
-                        UI_STATE.lock().unwrap().extra_teleports[i]
-                    },
-                };
 
-                if let Some(teleport) = t {
-                    gamedata::teleport_player(teleport.location, teleport.rotation);
-                    if let Some(cam_rotation) = teleport.camera_rotation {
-                        gamedata::set_camera_rotation(cam_rotation);
+            loop_events.push_back(CelEvent::RenderUpdate { update: RenderUpdates::paths() });
+            loop_events.push_back(CelEvent::RenderUpdate { update: RenderUpdates::settings() });
+        },
+        CelEvent::SwitchProfile { name } => {
+            let result = state.config.switch_profile(&name);
+            finish_profile_change(state, services, result, loop_events);
+        }
+        CelEvent::CreateProfile { name } => {
+            let result = state.config.create_profile(&name);
+            finish_profile_change(state, services, result, loop_events);
+        }
+        CelEvent::DuplicateProfile { name } => {
+            let result = state.config.duplicate_profile(&name);
+            finish_profile_change(state, services, result, loop_events);
+        }
+        CelEvent::RenameProfile { new_name } => {
+            let result = state.config.rename_profile(&new_name);
+            finish_profile_change(state, services, result, loop_events);
+        }
+        CelEvent::StartCapture => {
+            if let Err(e) = state.capture.start(state.pathlog, state.config, services.game_version()) {
+                error!("{e}");
+                state.ui.file_status = Some(e.to_string());
+            }
+        }
+        CelEvent::StopCapture => {
+            state.capture.stop(state.pathlog);
+            state.ui.file_status = state.capture.file_path().map(|file_path| format!("Saved capture to {file_path}"));
+        }
+        CelEvent::SelectPath { path_id, collection_id, modifier } => {
+            let pathlog = &mut *state.pathlog;
+
+            let collection = pathlog.get_collection(collection_id).unwrap().clone();
+            let path_id = pathlog.path(&path_id).unwrap().id();
+
+            let mut selected = pathlog.selected_paths.get(&collection_id).unwrap().clone();
+
+            match modifier {
+                1 => {
+                    let last_id = *selected.last().unwrap_or(&path_id);
+                    let mut last_pos = collection.paths().iter().position(|p| *p == last_id).unwrap();
+                    let mut this_pos = collection.paths().iter().position(|p| *p == path_id).unwrap();
+                    if last_pos < this_pos { (last_pos, this_pos) = (this_pos + 1, last_pos + 1) }
+                    for p in &collection.paths()[this_pos..last_pos] {
+                        if let Some(pos) = selected.iter().position(|id| *id == *p) { selected.remove(pos);}
+                        else { selected.push(*p) }
                     }
-                    loop_events.push_back(CelEvent::ResetRecording);
+                },
+                2 => {
+                    if let Some(pos) = selected.iter().position(|id| *id == path_id) { selected.remove(pos);}
+                    else { selected.push(path_id) }
+                },
+                _ => {
+                    selected.clear();
+                    selected.push(path_id);
                 }
             }
-            CelEvent::SpawnTeleport { index } => {
-                let teleport = Some(Teleport {
-                    location: gamedata::get_player_position(),
-                    rotation: gamedata::get_player_rotation(),
-                    camera_rotation: Some(gamedata::get_camera_rotation()),
-                });
-
-                match index {
-                    TeleportIndex::Main { i } => {
-                        UI_STATE.lock().unwrap().main_teleports[i] = teleport;
-                    },
-                    TeleportIndex::Extra { i } => {
-                        UI_STATE.lock().unwrap().extra_teleports[i] = teleport;
-                    },
+
+            pathlog.selected_paths.insert(collection_id, selected);
+            loop_events.push_back(CelEvent::RenderUpdate { update: RenderUpdates::paths() });
+        }
+        CelEvent::Teleport { index } => {
+            let t = match index {
+                TeleportIndex::Main { i } => state.ui.main_teleports[i],
+                TeleportIndex::Extra { i } => state.ui.extra_teleports[i],
+            };
+
+            if let Some(teleport) = t {
+                services.teleport_player(teleport.location, teleport.rotation);
+                if let Some(cam_rotation) = teleport.camera_rotation {
+                    services.set_camera_rotation(cam_rotation);
                 }
+                loop_events.push_back(CelEvent::ResetRecording);
             }
-            CelEvent::DeleteTeleport { index } => {
-                match index {
-                    TeleportIndex::Main { i } => {
-                        UI_STATE.lock().unwrap().main_teleports[i] = None;
-                    },
-                    TeleportIndex::Extra { i } => {
-                        UI_STATE.lock().unwrap().extra_teleports[i] = None;
-                    },
-                }
+        }
+        CelEvent::SpawnTeleport { index } => {
+            let teleport = Some(Teleport {
+                location: services.player_position(),
+                rotation: services.player_rotation(),
+                camera_rotation: Some(services.camera_rotation()),
+            });
+
+            match index {
+                TeleportIndex::Main { i } => state.ui.main_teleports[i] = teleport,
+                TeleportIndex::Extra { i } => state.ui.extra_teleports[i] = teleport,
+            }
+        }
+        CelEvent::DeleteTeleport { index } => {
+            match index {
+                TeleportIndex::Main { i } => state.ui.main_teleports[i] = None,
+                TeleportIndex::Extra { i } => state.ui.extra_teleports[i] = None,
+            }
+        }
+        CelEvent::RenderUpdate { update } => {
+            if !update.paths.is_none() {
+                state.pathlog.update_visible();
             }
-            CelEvent::RenderUpdate { update } => {
-                RENDER_UPDATES.lock().unwrap().or(update);
 
-                if update.paths {
-                    PATHLOG.lock().unwrap().update_visible();
-                }
+            state.render_updates.or(update);
+        }
+        CelEvent::NewException { key } => {
+            let exceptions = &mut state.config.input_suppression_exceptions;
+            if !exceptions.contains(&key) {
+                exceptions.push(key);
             }
-            CelEvent::NewException { key } => {
-                if !CONFIG_STATE.lock().unwrap().input_suppression_exceptions.contains(&key) {
-                    CONFIG_STATE.lock().unwrap().input_suppression_exceptions.push(key);
-                }
+        }
+        CelEvent::RemoveException { key } => {
+            let exceptions = &mut state.config.input_suppression_exceptions;
+            if let Some(i) = exceptions.iter().position(|x| *x == key) {
+                exceptions.remove(i);
             }
-            CelEvent::RemoveException { key } => {
-                let mut config_state = CONFIG_STATE.lock().unwrap();
-                if let Some(i) = config_state.input_suppression_exceptions.iter().position(|x| *x == key) {
-                    config_state.input_suppression_exceptions.remove(i);
-                }
-                drop(config_state);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::VecDeque;
+    use std::path::PathBuf;
+    use uuid::Uuid;
+
+    use super::{process_events, AppState, CelEvent};
+    use crate::capture::Capture;
+    use crate::comparison;
+    use crate::config::{ConfigState, CONFIG_FILE_NAME};
+    use crate::gamedata::GameVersion;
+    use crate::pathdata::{HighPassFilter, Path};
+    use crate::pathdata::CompFile;
+    use crate::pathlog::{PathLog, SaveOptions};
+    use crate::recording::RecordingEvent;
+    use crate::rendering::RenderUpdates;
+    use crate::services::{FileDialogKind, MockServices, Services, MOCK_PLAYER_POSITION};
+    use crate::ui::{TeleportIndex, UIState};
+
+    // owns what AppState borrows from the globals in the game
+    struct Headless {
+        pathlog: PathLog,
+        config: ConfigState,
+        ui: UIState,
+        render_updates: RenderUpdates,
+        capture: Capture,
+        services: MockServices,
+    }
+
+    const MAX_FRAMES: usize = 10;
+
+    impl Headless {
+        fn new() -> Headless {
+            let services = MockServices::new();
+            // like PathLog::init, without creating the Paths directory
+            let mut pathlog = PathLog::new();
+            pathlog.set_game_version(Some(services.game_version()));
+
+            Headless {
+                pathlog,
+                config: ConfigState::new(),
+                ui: UIState::init(),
+                render_updates: RenderUpdates::new(),
+                capture: Capture::new(),
+                services,
+            }
+        }
+
+        // processes the events and everything they queue for later frames
+        fn run(&mut self, events: Vec<CelEvent>) {
+            let mut queue : VecDeque<CelEvent> = events.into();
+
+            for _ in 0..MAX_FRAMES {
+                if queue.is_empty() { return; }
+
+                let mut state = AppState {
+                    pathlog: &mut self.pathlog,
+                    config: &mut self.config,
+                    ui: &mut self.ui,
+                    render_updates: &mut self.render_updates,
+                    capture: &mut self.capture,
+                };
+                queue = process_events(&mut state, &mut self.services, queue);
+            }
+
+            panic!("events still queued after {MAX_FRAMES} frames");
+        }
+
+        fn new_active_collection(&mut self) -> Uuid {
+            self.run(vec![CelEvent::CreateCollection]);
+            let id = self.pathlog.collections().last().expect("no collection created").id();
+            // the first collection is active right away
+            if self.pathlog.active_collection != Some(id) {
+                self.run(vec![CelEvent::ToggleActive { id }]);
             }
+            id
         }
     }
 
-    EVENTS.lock().unwrap().append(&mut loop_events);
-}
\ No newline at end of file
+    #[test]
+    fn records_into_the_active_collection() {
+        let mut app = Headless::new();
+        let collection_id = app.new_active_collection();
+        assert_eq!(app.pathlog.active_collection, Some(collection_id));
+
+        app.run(vec![CelEvent::StartRecording]);
+        assert!(app.pathlog.is_recording());
+
+        app.run(vec![CelEvent::StopRecording]);
+        assert!(!app.pathlog.is_recording());
+        assert_eq!(app.pathlog.get_collection(collection_id).unwrap().paths().len(), 1);
+        assert!(!app.render_updates.paths.is_none());
+
+        let events = app.pathlog.take_events();
+        let path_id = app.pathlog.latest_path.expect("latest path wasn't set");
+        assert_eq!(events.first(), Some(&RecordingEvent::Started));
+        assert!(events.iter().any(|e| matches!(e, RecordingEvent::Stopped { .. })));
+        assert_eq!(events.last(), Some(&RecordingEvent::PathAccepted { path_id, collection_id }));
+    }
+
+    #[test]
+    fn gold_filter_rejects_slower_paths() {
+        let mut app = Headless::new();
+        let collection_id = app.new_active_collection();
+
+        app.run(vec![CelEvent::ToggleGoldFilter { collection_id }]);
+        assert!(matches!(app.pathlog.filters.get(&collection_id), Some(HighPassFilter::Gold)));
+
+        for time in [30_000, 40_000, 20_000] {
+            let mut path = Path::new();
+            path.add_node([0., 0., 0.]);
+            path.end_path(time);
+            app.pathlog.insert_path(path, collection_id).unwrap();
+        }
+
+        let events = app.pathlog.take_events();
+        assert_eq!(events.iter().filter(|e| matches!(e, RecordingEvent::PathRejected { .. })).count(), 1);
+        assert_eq!(app.pathlog.get_collection(collection_id).unwrap().paths().len(), 2);
+        assert_eq!(app.pathlog.path_count(), 2);
+
+        app.run(vec![CelEvent::ToggleGoldFilter { collection_id }]);
+        assert!(!app.pathlog.filters.contains_key(&collection_id));
+    }
+
+    #[test]
+    fn triggers_place_teleports() {
+        let mut app = Headless::new();
+
+        app.run(vec![CelEvent::SpawnTrigger { index: 0, position: [5., 0., 5.], rotation: [0., 0., 0.] }]);
+        let trigger_id = app.pathlog.main_triggers[0].expect("start trigger wasn't created").id();
+        let teleport = app.ui.main_teleports[0].expect("spawning a trigger didn't place a teleport");
+        assert_eq!(teleport.location, MOCK_PLAYER_POSITION);
+
+        app.run(vec![CelEvent::StartRecording, CelEvent::Teleport { index: TeleportIndex::Main { i: 0 } }]);
+        assert_eq!(app.services.teleports, vec![MOCK_PLAYER_POSITION]);
+        assert!(app.services.camera_rotation.is_some());
+        assert!(!app.pathlog.is_recording(), "teleporting didn't reset the recording");
+
+        app.run(vec![CelEvent::DeleteTrigger { id: trigger_id }]);
+        assert!(app.pathlog.main_triggers[0].is_none());
+        assert!(app.ui.main_teleports[0].is_none());
+    }
+
+    #[test]
+    fn cancelled_dialog_saves_nothing() {
+        let mut app = Headless::new();
+        app.services.dialog_answer = None;
+
+        app.run(vec![CelEvent::SaveComparison]);
+        assert_eq!(app.services.dialogs, vec![FileDialogKind::SaveComparison]);
+        assert!(app.ui.file_path_rx.is_none());
+        assert!(app.pathlog.last_file().is_none());
+        assert!(app.services.files.is_empty());
+    }
+
+    #[test]
+    fn saves_and_loads_through_services() {
+        let mut app = Headless::new();
+        let file_path = PathBuf::from("mock.ccmp");
+
+        app.run(vec![
+            CelEvent::SpawnTrigger { index: 0, position: [0., 0., 0.], rotation: [0., 0., 0.] },
+            CelEvent::SpawnTrigger { index: 1, position: [9., 0., 9.], rotation: [0., 0., 0.] },
+        ]);
+        let collection_id = app.new_active_collection();
+
+        for _ in 0..2 {
+            app.run(vec![CelEvent::StartRecording]);
+            app.pathlog.recording_path.add_node([1., 2., 3.]);
+            app.run(vec![CelEvent::StopRecording]);
+        }
+
+        app.services.dialog_answer = Some(file_path.clone());
+        app.run(vec![CelEvent::SaveComparison]);
+        assert!(app.services.files.contains_key(&file_path));
+        assert_eq!(app.ui.file_status, None);
+
+        app.run(vec![CelEvent::SaveComparison]);
+        assert_eq!(app.services.backups, vec![file_path.clone()]);
+
+        let mut loaded = Headless::new();
+        loaded.services.files = app.services.files.clone();
+        loaded.services.dialog_answer = Some(file_path);
+        loaded.run(vec![CelEvent::LoadComparison]);
+
+        assert_eq!(loaded.ui.file_status, None);
+        assert!(loaded.pathlog.get_collection(collection_id).is_some_and(|c| c.paths().len() == 2));
+        assert!(loaded.pathlog.history_known());
+        assert!(loaded.pathlog.recent_paths(2).iter().all(|p| p.meta().game_version == Some(GameVersion::Debug)));
+    }
+
+    #[test]
+    fn simplifying_on_save_leaves_the_loaded_paths_alone() {
+        let mut app = Headless::new();
+        let file_path = PathBuf::from("mock.ccmp");
+
+        app.run(vec![
+            CelEvent::SpawnTrigger { index: 0, position: [0., 0., 0.], rotation: [0., 0., 0.] },
+            CelEvent::SpawnTrigger { index: 1, position: [9., 0., 9.], rotation: [0., 0., 0.] },
+        ]);
+        app.new_active_collection();
+
+        app.run(vec![CelEvent::StartRecording]);
+        for i in 0..10 {
+            app.pathlog.recording_path.add_node([i as f32, 0., 0.]);
+        }
+        app.run(vec![CelEvent::StopRecording]);
+
+        app.pathlog.set_save_options(SaveOptions { simplification: Some(0.1), compress: false, backups: 0 });
+        app.services.dialog_answer = Some(file_path.clone());
+        app.run(vec![CelEvent::SaveComparison]);
+
+        assert_eq!(app.pathlog.recent_paths(1)[0].len(), 10);
+
+        let saved = CompFile::from_bytes(app.services.files[&file_path].clone()).unwrap();
+        assert!(saved.get_paths().values().all(|path| path.len() == 2));
+    }
+
+    #[test]
+    fn profile_changes_write_the_config() {
+        let mut app = Headless::new();
+
+        app.run(vec![CelEvent::CreateProfile { name: "Mock".to_string() }]);
+        assert_eq!(app.config.profile, "Mock");
+        assert_eq!(app.ui.profile_error, None);
+
+        let content = app.services.files.get(&PathBuf::from(CONFIG_FILE_NAME)).expect("config wasn't written");
+        let document : serde_json::Value = serde_json::from_slice(content).unwrap();
+        assert_eq!(document["active_profile"], "Mock");
+
+        app.run(vec![CelEvent::SwitchProfile { name: "Missing".to_string() }]);
+        assert_eq!(app.config.profile, "Mock");
+        assert!(app.ui.profile_error.is_some());
+    }
+
+    // what a capture replay relies on
+    #[test]
+    fn seeded_path_logs_create_the_same_ids() {
+        let ids : Vec<_> = (0..2).map(|_| {
+            let mut app = Headless::new();
+            // captures store the id of the recording that was in progress
+            app.pathlog.recording_path = Path::with_id(Uuid::from_u128(1));
+            app.pathlog.seed_ids(Some(42));
+            app.run(vec![CelEvent::SpawnTrigger { index: 0, position: [0., 0., 0.], rotation: [0., 0., 0.] }]);
+            let collection_id = app.new_active_collection();
+            app.run(vec![CelEvent::StartRecording, CelEvent::StopRecording]);
+
+            (app.pathlog.main_triggers[0].map(|t| t.id()), collection_id, app.pathlog.latest_path, app.pathlog.recording_path.id())
+        }).collect();
+
+        assert_eq!(ids[0], ids[1]);
+    }
+
+    #[test]
+    fn inserting_into_a_missing_collection_fails() {
+        let mut app = Headless::new();
+        let mut path = Path::new();
+        path.end_path(1_000);
+
+        assert!(app.pathlog.insert_path(path, Uuid::new_v4()).is_err());
+        assert_eq!(app.pathlog.path_count(), 0);
+    }
+
+    #[test]
+    fn cycling_comparison_modes_keeps_parameters() {
+        let mut app = Headless::new();
+        let strategy_count = comparison::strategies().len();
+
+        for _ in 0..strategy_count {
+            if app.pathlog.comparison_strategy().parameter().is_some() {
+                app.pathlog.set_comparison_parameter(7);
+            }
+            app.run(vec![CelEvent::NextComparisonMode]);
+        }
+        assert_eq!(app.pathlog.comparison().mode, "All");
+
+        for _ in 0..strategy_count {
+            if let Some(parameter) = app.pathlog.comparison_strategy().parameter() {
+                assert_eq!(parameter.value, 7, "{} lost its parameter", app.pathlog.comparison().mode);
+            }
+            app.run(vec![CelEvent::NextComparisonMode]);
+        }
+    }
+}
@@ -0,0 +1,106 @@
+use std::ffi::c_void;
+use std::sync::Mutex;
+use once_cell::sync::Lazy;
+
+use crate::events::CelEvent;
+use crate::recording::RecordingEvent;
+use crate::{EVENTS, PATHLOG};
+
+// c api for other mods, declared in include/celestial.h and exported through exports.def.
+// only add functions here, never change existing ones, and bump the version when adding some
+
+const API_VERSION : u32 = 1;
+
+pub type FinishedCallback = extern "C" fn(time_ms: u64, user_data: *mut c_void);
+
+struct RegisteredCallback {
+    handle: u32,
+    callback: FinishedCallback,
+    // a pointer, but raw pointers aren't Send. we only hand it back to the owner
+    user_data: usize,
+}
+
+static FINISHED_CALLBACKS: Lazy<Mutex<(u32, Vec<RegisteredCallback>)>> = Lazy::new(|| Mutex::new((0, Vec::new())));
+
+// subscribed to the recording bus in lib.rs
+pub fn notify(event: &RecordingEvent) {
+    let RecordingEvent::Stopped { time, .. } = *event else { return; };
+
+    // copied so callbacks can (un)register without deadlocking
+    let callbacks : Vec<(FinishedCallback, usize)> = match FINISHED_CALLBACKS.lock() {
+        Ok(callbacks) => callbacks.1.iter().map(|c| (c.callback, c.user_data)).collect(),
+        Err(_) => return,
+    };
+
+    for (callback, user_data) in callbacks {
+        callback(time, user_data as *mut c_void);
+    }
+}
+
+fn push_event(event: CelEvent) {
+    if let Ok(mut events) = EVENTS.lock() {
+        events.push_back(event);
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn celestial_api_version() -> u32 {
+    API_VERSION
+}
+
+// timer in milliseconds, the last finished time while not recording
+#[no_mangle]
+pub extern "C" fn celestial_get_time() -> u64 {
+    PATHLOG.lock().map_or(0, |pathlog| pathlog.time())
+}
+
+#[no_mangle]
+pub extern "C" fn celestial_is_recording() -> bool {
+    PATHLOG.lock().is_ok_and(|pathlog| pathlog.is_recording())
+}
+
+// 0 if nothing was recorded yet
+#[no_mangle]
+pub extern "C" fn celestial_last_path_time() -> u64 {
+    PATHLOG.lock().map_or(0, |pathlog| pathlog.latest_time())
+}
+
+// these run on the next frame, same as pressing the keybinds
+#[no_mangle]
+pub extern "C" fn celestial_start_recording() {
+    push_event(CelEvent::StartRecording);
+}
+
+#[no_mangle]
+pub extern "C" fn celestial_stop_recording() {
+    push_event(CelEvent::StopRecording);
+}
+
+#[no_mangle]
+pub extern "C" fn celestial_reset_recording() {
+    push_event(CelEvent::ResetRecording);
+}
+
+// the callback runs on the render thread whenever a recording stops, at the end trigger or by hand.
+// returns a handle for unregistering, never 0
+#[no_mangle]
+pub extern "C" fn celestial_register_finished_callback(callback: Option<FinishedCallback>, user_data: *mut c_void) -> u32 {
+    let Some(callback) = callback else { return 0; };
+    let Ok(mut callbacks) = FINISHED_CALLBACKS.lock() else { return 0; };
+
+    callbacks.0 += 1;
+    let handle = callbacks.0;
+    callbacks.1.push(RegisteredCallback { handle, callback, user_data: user_data as usize });
+
+    handle
+}
+
+#[no_mangle]
+pub extern "C" fn celestial_unregister_finished_callback(handle: u32) -> bool {
+    let Ok(mut callbacks) = FINISHED_CALLBACKS.lock() else { return false; };
+
+    let count = callbacks.1.len();
+    callbacks.1.retain(|c| c.handle != handle);
+
+    callbacks.1.len() != count
+}
@@ -10,6 +10,7 @@ use windows::Win32::Devices::HumanInterfaceDevice::{DIKEYBOARD_0, DIKEYBOARD_1,
 use lazy_static::lazy_static;
 use egui::Key;
 use once_cell::sync::Lazy;
+use serde::{Serialize, Deserialize};
 
 pub const DINPUT_KEYS: Lazy<HashMap<Key, u32>> = Lazy::new(|| HashMap::from([
     (Key::Num0, DIKEYBOARD_0),
@@ -89,7 +90,8 @@ pub const DINPUT_KEYS: Lazy<HashMap<Key, u32>> = Lazy::new(|| HashMap::from([
     (Key::Z, DIKEYBOARD_Z),
 ]));
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Serialize, Deserialize)]
 pub enum GameVersion {
     V101,
     V102,
@@ -120,8 +122,10 @@ lazy_static! {
         versions
     };
 
+    static ref GAME_VERSION : GameVersion = unsafe { get_game_version() };
+
     static ref OFFSETS : Offsets = unsafe {
-        match get_game_version() {
+        match *GAME_VERSION {
             GameVersion::V101 => Offsets {
                 process_start: GetModuleHandleA(PCSTR::null()).unwrap().0,
                 player_actor: 0x16053E8,
@@ -183,6 +187,10 @@ unsafe fn get_game_version() -> GameVersion {
     version
 }
 
+pub fn game_version() -> GameVersion {
+    *GAME_VERSION
+}
+
 pub fn get_player_position() -> [f32; 3] {
     let player_addr: usize = unsafe { std::ptr::read((OFFSETS.process_start + OFFSETS.player_actor) as *const _) };
     if player_addr == 0 { return [0.0, 0.0, 0.0]; }
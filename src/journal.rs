@@ -0,0 +1,236 @@
+use std::fs;
+use std::io::{BufWriter, Write};
+
+use tracing::{error, info};
+
+use crate::backup;
+use crate::pathdata::Path;
+
+// everything recorded since the last save is appended here line by line. it's buffered and written out at the end
+// of every segment and path (or when the buffer is full), so a crash only loses the nodes since then.
+//   start <unix time>
+//   node <ms since segment start> <x> <y> <z>
+//   segment <ms>    end of a segment (pause)
+//   end <ms>        path finished, <ms> is the time of its last segment
+//   reset           the current path was thrown away
+//   discard         the path that just ended wasn't kept: no active collection, or a filter rejected it
+pub const JOURNAL_FILE : &str = "Paths/recording.journal";
+// journal of a session that didn't shut down cleanly, kept until the runs are recovered or discarded
+const RECOVERY_FILE : &str = "Paths/recording.journal.recovered";
+
+#[derive(Clone)]
+pub struct RecoveredRun {
+    pub path: Path,
+    // false if the game closed while it was still recording
+    pub finished: bool,
+}
+
+pub struct Journal {
+    file: Option<BufWriter<fs::File>>,
+    // off in direct mode, those recordings aren't kept anyway
+    enabled: bool,
+}
+
+impl Journal {
+    pub fn open() -> Journal {
+        let file = fs::OpenOptions::new().create(true).append(true).open(JOURNAL_FILE);
+
+        if let Err(e) = &file {
+            error!("Failed to open recording journal: {e}");
+        }
+
+        Journal { file: file.ok().map(BufWriter::new), enabled: true }
+    }
+
+    // a journal that doesn't write anything
+    pub fn disabled() -> Journal {
+        Journal { file: None, enabled: false }
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+
+    fn write_line(&mut self, line: String) {
+        if !self.enabled { return; }
+        let Some(file) = &mut self.file else { return; };
+
+        if let Err(e) = writeln!(file, "{line}") {
+            error!("Failed to write recording journal, disabling it: {e}");
+            self.file = None;
+        }
+    }
+
+    fn flush(&mut self) {
+        let Some(file) = &mut self.file else { return; };
+
+        if let Err(e) = file.flush() {
+            error!("Failed to write recording journal, disabling it: {e}");
+            self.file = None;
+        }
+    }
+
+    pub fn start(&mut self, recorded_at: u64) {
+        self.write_line(format!("start {recorded_at}"));
+    }
+
+    pub fn node(&mut self, time: u64, pos: [f32; 3]) {
+        self.write_line(format!("node {time} {} {} {}", pos[0], pos[1], pos[2]));
+    }
+
+    pub fn end_segment(&mut self, time: u64) {
+        self.write_line(format!("segment {time}"));
+        self.flush();
+    }
+
+    pub fn end_path(&mut self, time: u64) {
+        self.write_line(format!("end {time}"));
+        self.flush();
+    }
+
+    pub fn reset(&mut self) {
+        self.write_line("reset".to_string());
+        self.flush();
+    }
+
+    pub fn discard(&mut self) {
+        self.write_line("discard".to_string());
+        self.flush();
+    }
+
+    // whole paths are written even when the journal is off, recovered runs go into a collection in direct mode too
+    pub fn write_path(&mut self, path: &Path, finished: bool) {
+        let enabled = std::mem::replace(&mut self.enabled, true);
+        self.start(path.meta().recorded_at);
+
+        for i in 0..path.segment_count() {
+            for node in path.segment(i).unwrap() {
+                self.node(0, *node);
+            }
+
+            match path.segment_time(i) {
+                Some(time) if i + 1 < path.segment_count() || !finished => self.end_segment(time),
+                Some(time) => self.end_path(time),
+                None => {}
+            }
+        }
+
+        self.flush();
+        self.enabled = enabled;
+    }
+
+    // everything so far is saved, only the path that's still being recorded has to stay
+    pub fn clear(&mut self, recording: Option<&Path>) {
+        self.flush();
+        let Some(file) = &mut self.file else { return; };
+
+        if let Err(e) = file.get_ref().set_len(0) {
+            error!("Failed to clear recording journal: {e}");
+            return;
+        }
+
+        if let Some(path) = recording {
+            self.write_path(path, false);
+        }
+    }
+}
+
+fn parse(content: &str) -> Vec<RecoveredRun> {
+    let mut runs = Vec::new();
+    let mut current : Option<Path> = None;
+    let mut last_time = 0;
+
+    for line in content.lines() {
+        let mut words = line.split_whitespace();
+        let numbers : Vec<f64> = words.clone().skip(1).filter_map(|w| w.parse().ok()).collect();
+
+        // the last line can be cut off by the crash
+        match (words.next(), current.as_mut()) {
+            (Some("start"), _) => {
+                let mut path = Path::new();
+                path.meta_mut().recorded_at = numbers.first().map_or(0, |t| *t as u64);
+                current = Some(path);
+                last_time = 0;
+            }
+            (Some("node"), Some(path)) if numbers.len() == 4 => {
+                last_time = numbers[0] as u64;
+                path.add_node([numbers[1] as f32, numbers[2] as f32, numbers[3] as f32]);
+            }
+            (Some("segment"), Some(path)) if numbers.len() == 1 => {
+                path.end_segment(numbers[0] as u64);
+                last_time = 0;
+            }
+            (Some("end"), Some(path)) if numbers.len() == 1 => {
+                path.end_path(numbers[0] as u64);
+                runs.push(RecoveredRun { path: current.take().unwrap(), finished: true });
+            }
+            (Some("reset"), _) => current = None,
+            // always right after the end of the run it refers to
+            (Some("discard"), None) => { runs.pop(); }
+            _ => {}
+        }
+    }
+
+    if let Some(mut path) = current {
+        if path.len() > 0 {
+            path.end_path(last_time);
+            runs.push(RecoveredRun { path, finished: false });
+        }
+    }
+
+    runs
+}
+
+// moves whatever the last session left in the journal over to the recovery file and reads it
+pub fn recover() -> Vec<RecoveredRun> {
+    let mut content = fs::read_to_string(RECOVERY_FILE).unwrap_or_default();
+    let journal = fs::read_to_string(JOURNAL_FILE).unwrap_or_default();
+
+    if !journal.is_empty() {
+        content.push_str(&journal);
+
+        if let Err(e) = backup::write_atomic(std::path::Path::new(RECOVERY_FILE), content.as_bytes()) {
+            error!("Failed to keep recovered runs: {e}");
+        }
+        else if let Err(e) = fs::remove_file(JOURNAL_FILE) {
+            error!("{e}");
+        }
+    }
+
+    let runs = parse(&content);
+
+    if runs.is_empty() {
+        discard_recovered();
+    }
+    else {
+        info!("Found {} unsaved runs from the last session", runs.len());
+    }
+
+    runs
+}
+
+pub fn discard_recovered() {
+    if fs::exists(RECOVERY_FILE).unwrap_or(false) {
+        if let Err(e) = fs::remove_file(RECOVERY_FILE) {
+            error!("{e}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn discarded_runs_are_not_recovered() {
+        let content = "start 10\nnode 0 1 2 3\nnode 5 4 5 6\nend 900\n\
+            start 20\nnode 0 1 2 3\nend 800\ndiscard\n\
+            start 30\nnode 0 7 8 9\nsegment 400\nnode 0 1 1 1\n";
+
+        let runs = parse(content);
+
+        assert_eq!(runs.len(), 2);
+        assert!(runs[0].finished && runs[0].path.meta().recorded_at == 10 && runs[0].path.time() == 900);
+        assert!(!runs[1].finished && runs[1].path.meta().recorded_at == 30 && runs[1].path.len() == 2);
+    }
+}
@@ -0,0 +1,238 @@
+use crate::error::Error;
+
+// how the comparison file structs look once serde_binary wrote them. every length and count in a file is
+// checked against this before serde_binary sees it, a garbage length would make it allocate or slice out of bounds.
+//
+// serde_binary writes structs as a u32 field count followed by name/value pairs. strings, sequences, maps
+// and also tuples and fixed size arrays start with a u32 length, options with a one byte tag.
+// uuids go through serialize_str since the serializer says it's human readable.
+// these have to change together with the structs in pathdata.rs
+
+pub enum Shape {
+    // numbers, in bytes
+    Fixed(usize),
+    Str,
+    // the hyphenated string
+    Uuid,
+    // fixed size array, the length prefix has to match
+    Array(usize, &'static Shape),
+    Seq(&'static Shape),
+    Map(&'static Shape, &'static Shape),
+    Option(&'static Shape),
+    Struct(&'static [(&'static str, Shape)]),
+}
+
+static F32: Shape = Shape::Fixed(4);
+static NODE: Shape = Shape::Array(3, &F32);
+static BASIS: Shape = Shape::Array(3, &NODE);
+const TRIGGER_DATA: Shape = Shape::Array(2, &BASIS);
+
+static NODES: Shape = Shape::Seq(&NODE);
+
+static OLD_PATH_04: Shape = Shape::Struct(&[
+    ("id", Shape::Uuid),
+    ("time", Shape::Fixed(8)),
+    ("nodes", Shape::Seq(&NODE)),
+]);
+
+static PATH_COLLECTION_04: Shape = Shape::Struct(&[
+    ("id", Shape::Uuid),
+    ("name", Shape::Str),
+    ("paths", Shape::Seq(&OLD_PATH_04)),
+]);
+
+pub static COMP_FILE_04: Shape = Shape::Struct(&[
+    ("trigger_data", TRIGGER_DATA),
+    ("collections", Shape::Seq(&PATH_COLLECTION_04)),
+]);
+
+static PATH_05: Shape = Shape::Struct(&[
+    ("id", Shape::Uuid),
+    ("times", Shape::Seq(&Shape::Fixed(8))),
+    ("segments", Shape::Seq(&NODES)),
+]);
+
+static PATH_COLLECTION_05: Shape = Shape::Struct(&[
+    ("id", Shape::Uuid),
+    ("name", Shape::Str),
+    ("paths", Shape::Seq(&PATH_05)),
+]);
+
+pub static COMP_FILE_05: Shape = Shape::Struct(&[
+    ("version", Shape::Str),
+    ("trigger_data", TRIGGER_DATA),
+    ("collections", Shape::Seq(&PATH_COLLECTION_05)),
+]);
+
+static PATH_COLLECTION: Shape = Shape::Struct(&[
+    ("id", Shape::Uuid),
+    ("name", Shape::Str),
+    ("paths", Shape::Seq(&Shape::Uuid)),
+]);
+
+pub static COMP_FILE_06: Shape = Shape::Struct(&[
+    ("version", Shape::Str),
+    // Path06 looks the same as Path05
+    ("paths", Shape::Map(&Shape::Uuid, &PATH_05)),
+    ("trigger_data", TRIGGER_DATA),
+    ("collections", Shape::Seq(&PATH_COLLECTION)),
+]);
+
+static PATH_HEADER_07: Shape = Shape::Struct(&[
+    ("id", Shape::Uuid),
+    ("times", Shape::Seq(&Shape::Fixed(8))),
+]);
+
+pub static COMP_FILE_07: Shape = Shape::Struct(&[
+    ("version", Shape::Str),
+    ("trigger_data", TRIGGER_DATA),
+    ("collections", Shape::Seq(&PATH_COLLECTION)),
+    ("quantisation", Shape::Fixed(4)),
+    ("geometry_flags", Shape::Fixed(1)),
+    ("paths", Shape::Seq(&PATH_HEADER_07)),
+    ("geometry", Shape::Seq(&Shape::Fixed(1))),
+]);
+
+const PATH_META: Shape = Shape::Struct(&[
+    ("recorded_at", Shape::Fixed(8)),
+    // the GameVersion's name
+    ("game_version", Shape::Option(&Shape::Str)),
+    ("runner", Shape::Str),
+    ("notes", Shape::Str),
+    ("tags", Shape::Seq(&Shape::Str)),
+]);
+
+static PATH_HEADER_08: Shape = Shape::Struct(&[
+    ("id", Shape::Uuid),
+    ("times", Shape::Seq(&Shape::Fixed(8))),
+    ("meta", PATH_META),
+]);
+
+pub static COMP_FILE_08: Shape = Shape::Struct(&[
+    ("version", Shape::Str),
+    ("trigger_data", TRIGGER_DATA),
+    ("collections", Shape::Seq(&PATH_COLLECTION)),
+    ("quantisation", Shape::Fixed(4)),
+    ("geometry_flags", Shape::Fixed(1)),
+    ("paths", Shape::Seq(&PATH_HEADER_08)),
+    ("geometry", Shape::Seq(&Shape::Fixed(1))),
+]);
+
+const UUID_LEN: usize = 36;
+
+impl Shape {
+    // the fewest bytes a value of this shape can take
+    fn min_len(&self) -> usize {
+        match self {
+            Shape::Fixed(len) => *len,
+            Shape::Str | Shape::Seq(_) | Shape::Map(_, _) => 4,
+            Shape::Uuid => 4 + UUID_LEN,
+            Shape::Array(len, element) => 4 + len * element.min_len(),
+            Shape::Option(_) => 1,
+            Shape::Struct(fields) => 4 + fields.iter().map(|(name, shape)| 4 + name.len() + shape.min_len()).sum::<usize>(),
+        }
+    }
+}
+
+// walks the whole content, errors if anything doesn't fit or something is left over
+pub fn check(content: &[u8], shape: &Shape, what: &str) -> Result<(), Error> {
+    let mut reader = Reader { content, head: 0 };
+    reader.check(shape, what)?;
+
+    if reader.head != content.len() {
+        return Err(Error::CorruptField { field: what.to_string(), msg: "data after the end of the file".to_string() });
+    }
+
+    Ok(())
+}
+
+struct Reader<'a> {
+    content: &'a [u8],
+    head: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize, field: &str) -> Result<&'a [u8], Error> {
+        let Some(bytes) = self.content.get(self.head..self.head.saturating_add(len)) else {
+            return Err(Error::Truncated { msg: format!("{field} cut off") });
+        };
+        self.head += len;
+        Ok(bytes)
+    }
+
+    fn read_u32(&mut self, field: &str) -> Result<u32, Error> {
+        Ok(u32::from_le_bytes(self.take(4, field)?.try_into().unwrap()))
+    }
+
+    // a length prefix that can't be larger than what's left of the file
+    fn read_count(&mut self, min_len_each: usize, field: &str) -> Result<usize, Error> {
+        let count = self.read_u32(field)? as usize;
+        if count.saturating_mul(min_len_each) > self.content.len() - self.head {
+            return Err(Error::Truncated { msg: format!("{field}: {count} entries don't fit in the file") });
+        }
+        Ok(count)
+    }
+
+    fn read_str(&mut self, field: &str) -> Result<&'a str, Error> {
+        let length = self.read_count(1, field)?;
+        std::str::from_utf8(self.take(length, field)?)
+            .map_err(|e| Error::CorruptField { field: field.to_string(), msg: e.to_string() })
+    }
+
+    fn check(&mut self, shape: &Shape, field: &str) -> Result<(), Error> {
+        match shape {
+            Shape::Fixed(len) => { self.take(*len, field)?; }
+            Shape::Str => { self.read_str(field)?; }
+            Shape::Uuid => {
+                let length = self.read_count(1, field)?;
+                if length != UUID_LEN {
+                    return Err(Error::CorruptField { field: field.to_string(), msg: format!("id has {length} bytes") });
+                }
+                self.take(length, field)?;
+            }
+            Shape::Array(len, element) => {
+                let count = self.read_u32(field)? as usize;
+                if count != *len {
+                    return Err(Error::CorruptField { field: field.to_string(), msg: format!("{count} entries, expected {len}") });
+                }
+                for _ in 0..count {
+                    self.check(element, field)?;
+                }
+            }
+            Shape::Seq(element) => {
+                for _ in 0..self.read_count(element.min_len().max(1), field)? {
+                    self.check(element, field)?;
+                }
+            }
+            Shape::Map(key, value) => {
+                for _ in 0..self.read_count((key.min_len() + value.min_len()).max(1), field)? {
+                    self.check(key, field)?;
+                    self.check(value, field)?;
+                }
+            }
+            Shape::Option(inner) => {
+                match self.take(1, field)?[0] {
+                    0 => {}
+                    1 => self.check(inner, field)?,
+                    tag => return Err(Error::CorruptField { field: field.to_string(), msg: format!("option tag {tag}") }),
+                }
+            }
+            Shape::Struct(fields) => {
+                let field_count = self.read_u32(field)? as usize;
+                if field_count != fields.len() {
+                    return Err(Error::CorruptField { field: field.to_string(), msg: format!("{field_count} fields, expected {}", fields.len()) });
+                }
+
+                for (name, field_shape) in fields.iter() {
+                    let found = self.read_str(name)?;
+                    if found != *name {
+                        return Err(Error::CorruptField { field: name.to_string(), msg: format!("found field {found}") });
+                    }
+                    self.check(field_shape, name)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
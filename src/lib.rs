@@ -38,19 +38,42 @@ use egui_directx11::DirectX11Renderer;
 use egui_win32::InputManager;
 
 mod tether;
+mod ffi;
 pub mod gamedata;
+pub mod actions;
 pub mod config;
+pub mod configfile;
 pub mod pathlog;
 pub mod pathdata;
 pub mod rendering;
 pub mod ui;
 pub mod error;
 pub mod events;
+pub mod comparison;
+pub mod codec;
+pub mod layout;
+pub mod backup;
+pub mod date;
+pub mod journal;
+pub mod livesplit;
+pub mod api;
+pub mod overlay;
+pub mod recording;
+pub mod services;
+pub mod snapshot;
+pub mod clock;
+pub mod capture;
+pub mod culling;
+#[cfg(debug_assertions)]
+mod bench;
 
 use pathlog::*;
 use rendering::*;
 use ui::*;
 use events::*;
+use recording::{RecordingBus, RecordingEvent};
+use services::GameServices;
+use capture::CapturedConfig;
 use config::*;
 
 use ocular;
@@ -68,11 +91,16 @@ pub struct ScreenDimensions {
 
 static SCREEN_DIMENSIONS: Lazy<Mutex<ScreenDimensions>> = Lazy::new(|| Mutex::new(ScreenDimensions::default()));
 
-pub static PATHLOG: Lazy<Mutex<PathLog>> = Lazy::new(|| Mutex::new(PathLog::init()));
+pub static PATHLOG: Lazy<Mutex<PathLog>> = Lazy::new(|| Mutex::new(PathLog::init(&GameServices)));
 pub static CONFIG_STATE: Lazy<Mutex<ConfigState>> = Lazy::new(|| Mutex::new(ConfigState::init()));
 pub static UI_STATE: Lazy<Mutex<UIState>> = Lazy::new(|| Mutex::new(UIState::init()));
 pub static EVENTS: Lazy<Mutex<VecDeque<CelEvent>>> = Lazy::new(|| Mutex::new(VecDeque::new()));
-pub static RENDER_UPDATES: Lazy<Mutex<RenderUpdates>> = Lazy::new(|| Mutex::new(RenderUpdates::new()));
+pub static RENDER_UPDATES: Lazy<Mutex<RenderUpdates>> = Lazy::new(|| Mutex::new(RenderUpdates::all()));
+pub static LIVESPLIT: Lazy<Mutex<livesplit::LiveSplit>> = Lazy::new(|| Mutex::new(livesplit::LiveSplit::new()));
+pub static API: Lazy<Mutex<api::Api>> = Lazy::new(|| Mutex::new(api::Api::new()));
+pub static OVERLAY: Lazy<Mutex<overlay::Overlay>> = Lazy::new(|| Mutex::new(overlay::Overlay::new()));
+pub static CAPTURE: Lazy<Mutex<capture::Capture>> = Lazy::new(|| Mutex::new(capture::Capture::new()));
+pub static RECORDING_BUS: Lazy<Mutex<RecordingBus>> = Lazy::new(|| Mutex::new(RecordingBus::new()));
 
 // struct InputState {
 //     input_manager: Option<InputManager>,
@@ -120,8 +148,8 @@ static KEYBOARD_GET_DEVICE_STATE_HOOK: Lazy<GenericDetour<GetDeviceStatusFn>> =
   });
 
 pub enum RX {
-    Save { rx: mpsc::Receiver<Result<Option<PathBuf>, native_dialog::Error>> },
-    Load { rx: mpsc::Receiver<Result<Option<PathBuf>, native_dialog::Error>> },
+    Save { rx: mpsc::Receiver<Option<PathBuf>> },
+    Load { rx: mpsc::Receiver<Option<PathBuf>> },
 }
 
 // pub struct DebugState {
@@ -159,7 +187,9 @@ unsafe fn init_globals(this: &IDXGISwapChain) {
     if PINTAR.is_none() {
         let mut pintar = Pintar::init(&this, 0);
         pintar.add_line_vertex_group(RECORDING_GROUP.to_string());
-        pintar.add_line_vertex_group(PATHS_GROUP.to_string());
+        for bucket in 0..PATH_BUCKET_COUNT {
+            pintar.add_line_vertex_group(path_group(bucket));
+        }
         pintar.add_default_vertex_group(TRIGGERS_GROUP.to_string());
         pintar.add_default_vertex_group(TELEPORTS_GROUP.to_string());
         pintar.add_default_vertex_group(SHAPES_GROUP.to_string());
@@ -187,20 +217,48 @@ extern "system" fn hk_present(this: IDXGISwapChain, sync_interval: u32, flags: u
     unsafe {
         init_globals(&this);
 
+        let config = CONFIG_STATE.lock().unwrap();
+
+        let sampling = config.sampling;
+        let save_options = config.save_options();
+        let captured_config = CapturedConfig::from_config(&config);
+        let runner_name = config.runner_name.clone();
+        let livesplit_settings = config.livesplit_settings();
+        let api_settings = config.api_settings();
+        let overlay_settings = config.overlay_settings();
+
+        drop(config);
+
         let mut pathlog = PATHLOG.lock().unwrap();
 
-        if gamedata::get_is_loading() {
-            pathlog.pause();
-        } else {
-            pathlog.unpause();
-        }
+        pathlog.set_save_options(save_options);
+        pathlog.set_runner(&runner_name);
+        pathlog.clock_mut().tick();
+
+        let loading = gamedata::get_is_loading();
+
+        pathlog.set_loading(loading);
 
-        let pathlog_updates = pathlog.update(&gamedata::get_player_position(), &gamedata::get_player_rotation());
+        let player_position = gamedata::get_player_position();
+        let player_rotation = gamedata::get_player_rotation();
+        let mut pathlog_updates = pathlog.update(&player_position, &player_rotation, sampling);
+        snapshot::publish_paths(&pathlog, std::mem::replace(&mut pathlog_updates.paths, PathUpdates::None));
+        let recording_events = pathlog.take_events();
+        let frame_time = pathlog.clock().micros();
 
         drop(pathlog);
 
+        CAPTURE.lock().unwrap().begin_frame(frame_time, player_position, player_rotation, loading, captured_config);
+
         RENDER_UPDATES.lock().unwrap().or(pathlog_updates);
 
+        LIVESPLIT.lock().unwrap().configure(&livesplit_settings);
+        API.lock().unwrap().configure(&api_settings);
+        OVERLAY.lock().unwrap().configure(&overlay_settings);
+
+        // after the path log is unlocked, subscribers may lock it
+        RECORDING_BUS.lock().unwrap().publish(&recording_events);
+
         if let Some(pintar) = PINTAR.as_mut() {
             let view_proj = gamedata::get_view_matrix();
 
@@ -209,8 +267,10 @@ extern "system" fn hk_present(this: IDXGISwapChain, sync_interval: u32, flags: u
             let line_vertex_group: &mut pintar::vertex_group::VertexGroup<pintar::default_elements::LineVertex, pintar::default_elements::DefaultConstants> = pintar.get_vertex_group_as(RECORDING_GROUP.to_string()).unwrap();
             line_vertex_group.constants.view_proj = XMMatrix::from(&view_proj);
 
-            let line_vertex_group: &mut pintar::vertex_group::VertexGroup<pintar::default_elements::LineVertex, pintar::default_elements::DefaultConstants> = pintar.get_vertex_group_as(PATHS_GROUP.to_string()).unwrap();
-            line_vertex_group.constants.view_proj = XMMatrix::from(&view_proj);
+            for bucket in 0..PATH_BUCKET_COUNT {
+                let line_vertex_group: &mut pintar::vertex_group::VertexGroup<pintar::default_elements::LineVertex, pintar::default_elements::DefaultConstants> = pintar.get_vertex_group_as(path_group(bucket)).unwrap();
+                line_vertex_group.constants.view_proj = XMMatrix::from(&view_proj);
+            }
 
             let triggers_vertex_group: &mut pintar::vertex_group::VertexGroup<pintar::default_elements::DefaultVertex, pintar::default_elements::DefaultConstants> = pintar.get_vertex_group_as(TRIGGERS_GROUP.to_string()).unwrap();
             triggers_vertex_group.constants.view_proj = XMMatrix::from(&view_proj);
@@ -221,19 +281,21 @@ extern "system" fn hk_present(this: IDXGISwapChain, sync_interval: u32, flags: u
             let shapes_vertex_group: &mut pintar::vertex_group::VertexGroup<pintar::default_elements::DefaultVertex, pintar::default_elements::DefaultConstants> = pintar.get_vertex_group_as(SHAPES_GROUP.to_string()).unwrap();
             shapes_vertex_group.constants.view_proj = XMMatrix::from(&view_proj);
 
+            let snapshot = snapshot::current();
+
             pintar.clear_vertex_group(RECORDING_GROUP.to_string());
-            rendering::render_path(pintar, RECORDING_GROUP.to_string(), &PATHLOG.lock().unwrap().recording_path, [1.0, 1.0, 1.0, 0.8], 0.02);
+            rendering::render_path(pintar, RECORDING_GROUP.to_string(), &snapshot.recording_path, [1.0, 1.0, 1.0, 0.8], 0.02);
 
             pintar.clear_vertex_group(SHAPES_GROUP.to_string());
-            rendering::render_custom_shapes(pintar);
+            rendering::render_custom_shapes(pintar, &snapshot);
 
             pintar.clear_vertex_group(TELEPORTS_GROUP.to_string());
-            rendering::render_teleports(pintar);
+            rendering::render_teleports(pintar, &snapshot);
 
-            rendering::render_all_paths(pintar);
+            rendering::render_all_paths(pintar, &snapshot, &view_proj, player_position);
 
             pintar.clear_vertex_group(TRIGGERS_GROUP.to_string());
-            rendering::render_triggers(pintar);
+            rendering::render_triggers(pintar, &snapshot);
 
             pintar.render();
         }
@@ -290,7 +352,7 @@ extern "system" fn hk_present(this: IDXGISwapChain, sync_interval: u32, flags: u
                     })
                 .expect("successful render");
 
-            events::process_events();
+            events::process_queued_events();
         }
     }
 
@@ -511,8 +573,23 @@ fn log_setup() {
     }
 }
 
+// everything that reacts to the recording goes through the bus instead of pathlog.rs
+fn subscribe_recording_events() {
+    let mut bus = RECORDING_BUS.lock().unwrap();
+
+    bus.subscribe(Box::new(|event| LIVESPLIT.lock().unwrap().send(event)));
+    bus.subscribe(Box::new(|event| API.lock().unwrap().broadcast(event)));
+    bus.subscribe(Box::new(ffi::notify));
+    bus.subscribe(Box::new(|event| {
+        if let RecordingEvent::PathAccepted { .. } = event {
+            PATHLOG.lock().unwrap().autosave(&mut GameServices);
+        }
+    }));
+}
+
 fn main() {
     log_setup();
+    subscribe_recording_events();
 
     unsafe {
         // let mut debug = DebugState{
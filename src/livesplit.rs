@@ -0,0 +1,123 @@
+use std::io::Write;
+use std::net::{TcpStream, ToSocketAddrs};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::time::Duration;
+
+use tracing::{error, info};
+
+use crate::recording::RecordingEvent;
+
+// client for the livesplit server component (tcp, one command per line)
+
+pub const DEFAULT_PORT : u16 = 16834;
+const CONNECT_TIMEOUT : Duration = Duration::from_millis(250);
+
+// the last split is reaching the end trigger.
+// a finished run never gets a Reset (reset() does nothing when not recording), so livesplit is reset before every start
+fn commands(event: &RecordingEvent) -> &'static [&'static str] {
+    match event {
+        RecordingEvent::Started => &["reset", "starttimer"],
+        RecordingEvent::Split { .. } | RecordingEvent::Stopped { .. } => &["split"],
+        RecordingEvent::Paused { .. } => &["pausegametime"],
+        RecordingEvent::Unpaused => &["unpausegametime"],
+        RecordingEvent::Reset => &["reset"],
+        _ => &[],
+    }
+}
+
+#[derive(Clone, PartialEq)]
+pub struct LiveSplitSettings {
+    pub enabled: bool,
+    pub host: String,
+    pub port: u16,
+}
+
+// the connection lives on its own thread so a slow or missing livesplit never stalls the game
+pub struct LiveSplit {
+    settings: Option<LiveSplitSettings>,
+    tx: Option<Sender<&'static str>>,
+}
+
+impl LiveSplit {
+    pub fn new() -> LiveSplit {
+        LiveSplit { settings: None, tx: None }
+    }
+
+    // restarts the connection thread when the settings changed
+    pub fn configure(&mut self, settings: &LiveSplitSettings) {
+        if self.settings.as_ref() == Some(settings) { return; }
+
+        self.settings = Some(settings.clone());
+        // dropping the sender ends the old thread
+        self.tx = None;
+
+        if !settings.enabled { return; }
+
+        let (tx, rx) = mpsc::channel();
+        let address = format!("{}:{}", settings.host, settings.port);
+
+        std::thread::spawn(move || connection_thread(address, rx));
+
+        self.tx = Some(tx);
+    }
+
+    pub fn send(&self, event: &RecordingEvent) {
+        let Some(tx) = &self.tx else { return; };
+
+        for command in commands(event) {
+            let _ = tx.send(*command);
+        }
+    }
+}
+
+impl Default for LiveSplit {
+    fn default() -> Self {
+        LiveSplit::new()
+    }
+}
+
+fn connect(address: &str) -> Option<TcpStream> {
+    let socket_address = address.to_socket_addrs().ok()?.next()?;
+    let stream = TcpStream::connect_timeout(&socket_address, CONNECT_TIMEOUT).ok()?;
+    let _ = stream.set_nodelay(true);
+    Some(stream)
+}
+
+fn connection_thread(address: String, rx: Receiver<&'static str>) {
+    let mut stream : Option<TcpStream> = None;
+    let mut warned = false;
+
+    while let Ok(command) = rx.recv() {
+        let line = format!("{command}\r\n");
+
+        // a second try in case livesplit was restarted since the last event
+        for _ in 0..2 {
+            if stream.is_none() {
+                stream = connect(&address);
+
+                match &stream {
+                    Some(_) => {
+                        info!("Connected to LiveSplit at {address}");
+                        warned = false;
+                    }
+                    // events are dropped while livesplit isn't running, the timer would be out of sync anyway
+                    None if !warned => {
+                        error!("Can't reach LiveSplit at {address}");
+                        warned = true;
+                    }
+                    None => {}
+                }
+            }
+
+            let Some(connection) = &mut stream else { break; };
+
+            match connection.write_all(line.as_bytes()) {
+                Ok(()) => break,
+                Err(e) => {
+                    info!("Lost connection to LiveSplit: {e}");
+                    stream = None;
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,191 @@
+use std::io::{BufRead, BufReader, ErrorKind, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde_json::json;
+use tracing::{error, info};
+
+use crate::PATHLOG;
+
+// tiny http server for an obs browser source. "/" is the page, "/events" streams the state as server-sent events
+
+pub const DEFAULT_PORT : u16 = 16836;
+const OVERLAY_PAGE : &str = include_str!("overlay.html");
+const POLL_INTERVAL : Duration = Duration::from_millis(100);
+// how often the timer gets sent
+const UPDATE_INTERVAL : Duration = Duration::from_millis(50);
+const REQUEST_TIMEOUT : Duration = Duration::from_secs(2);
+
+#[derive(Clone, PartialEq)]
+pub struct OverlaySettings {
+    pub enabled: bool,
+    pub port: u16,
+    pub top_n: usize,
+}
+
+pub struct Overlay {
+    settings: Option<OverlaySettings>,
+    stop: Arc<AtomicBool>,
+    // shared with the running server so changing it doesn't need a restart
+    top_n: Arc<AtomicUsize>,
+}
+
+impl Overlay {
+    pub fn new() -> Overlay {
+        Overlay {
+            settings: None,
+            stop: Arc::new(AtomicBool::new(false)),
+            top_n: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    pub fn configure(&mut self, settings: &OverlaySettings) {
+        if self.settings.as_ref() == Some(settings) { return; }
+
+        self.top_n.store(settings.top_n, Ordering::Relaxed);
+
+        let restart = self.settings.as_ref().is_none_or(|old| old.enabled != settings.enabled || old.port != settings.port);
+        self.settings = Some(settings.clone());
+
+        if !restart { return; }
+
+        self.stop.store(true, Ordering::Relaxed);
+        self.stop = Arc::new(AtomicBool::new(false));
+
+        if !settings.enabled { return; }
+
+        let listener = match TcpListener::bind(("127.0.0.1", settings.port)) {
+            Ok(listener) => listener,
+            Err(e) => {
+                error!("Failed to start the overlay on port {}: {e}", settings.port);
+                return;
+            }
+        };
+
+        if let Err(e) = listener.set_nonblocking(true) {
+            error!("{e}");
+            return;
+        }
+
+        info!("Overlay at http://localhost:{}/", settings.port);
+
+        let stop = self.stop.clone();
+        let top_n = self.top_n.clone();
+
+        std::thread::spawn(move || listen(listener, stop, top_n));
+    }
+}
+
+impl Default for Overlay {
+    fn default() -> Self {
+        Overlay::new()
+    }
+}
+
+fn listen(listener: TcpListener, stop: Arc<AtomicBool>, top_n: Arc<AtomicUsize>) {
+    while !stop.load(Ordering::Relaxed) {
+        match listener.accept() {
+            Ok((stream, _)) => {
+                let stop = stop.clone();
+                let top_n = top_n.clone();
+                std::thread::spawn(move || {
+                    if let Err(e) = serve(stream, stop, top_n) {
+                        // browsers close event streams all the time
+                        if e.kind() != ErrorKind::BrokenPipe && e.kind() != ErrorKind::ConnectionReset && e.kind() != ErrorKind::ConnectionAborted {
+                            error!("Overlay: {e}");
+                        }
+                    }
+                });
+            }
+            Err(e) if e.kind() == ErrorKind::WouldBlock => std::thread::sleep(POLL_INTERVAL),
+            Err(e) => {
+                error!("Overlay: {e}");
+                std::thread::sleep(POLL_INTERVAL);
+            }
+        }
+    }
+}
+
+fn serve(stream: TcpStream, stop: Arc<AtomicBool>, top_n: Arc<AtomicUsize>) -> std::io::Result<()> {
+    stream.set_nonblocking(false)?;
+    stream.set_read_timeout(Some(REQUEST_TIMEOUT))?;
+
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+
+    // the rest of the header doesn't matter, but has to be read before answering
+    let mut header_line = String::new();
+    while reader.read_line(&mut header_line)? > 2 {
+        header_line.clear();
+    }
+
+    let mut stream = stream;
+    let mut parts = request_line.split_whitespace();
+
+    match (parts.next(), parts.next()) {
+        (Some("GET"), Some("/")) => respond(&mut stream, "200 OK", "text/html; charset=utf-8", OVERLAY_PAGE),
+        (Some("GET"), Some("/state")) => respond(&mut stream, "200 OK", "application/json", &state(top_n.load(Ordering::Relaxed))),
+        (Some("GET"), Some("/events")) => stream_events(&mut stream, &stop, &top_n),
+        (Some("GET"), _) => respond(&mut stream, "404 Not Found", "text/plain", "not found"),
+        _ => respond(&mut stream, "405 Method Not Allowed", "text/plain", "only GET"),
+    }
+}
+
+fn respond(stream: &mut TcpStream, status: &str, content_type: &str, body: &str) -> std::io::Result<()> {
+    write!(stream, "HTTP/1.1 {status}\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}", body.len())
+}
+
+fn stream_events(stream: &mut TcpStream, stop: &AtomicBool, top_n: &AtomicUsize) -> std::io::Result<()> {
+    write!(stream, "HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nCache-Control: no-cache\r\nConnection: keep-alive\r\nAccess-Control-Allow-Origin: *\r\n\r\n")?;
+
+    let mut last_state = String::new();
+
+    while !stop.load(Ordering::Relaxed) {
+        let current_state = state(top_n.load(Ordering::Relaxed));
+
+        // only changes get sent, the timer doesn't move while nothing is recording
+        if current_state != last_state {
+            write!(stream, "data: {current_state}\n\n")?;
+            stream.flush()?;
+            last_state = current_state;
+        }
+
+        std::thread::sleep(UPDATE_INTERVAL);
+    }
+
+    Ok(())
+}
+
+// timer, delta to the best path and the fastest times of the active collection (or all paths if none is active)
+fn state(top_n: usize) -> String {
+    let pathlog = PATHLOG.lock().unwrap();
+
+    let time = pathlog.time();
+    let top = pathlog.fastest_times(pathlog.active_collection, top_n.max(1));
+    let best = pathlog.fastest_path(pathlog.active_collection);
+
+    // while recording, against the best path's time at the last checkpoint reached. the total time only once it ended
+    let delta = if pathlog.is_recording() {
+        pathlog.split_times().last().and_then(|split_time| {
+            let checkpoint = pathlog.split_times().len() - 1;
+            best.and_then(|path| path.checkpoint_time(&pathlog.checkpoint_triggers, checkpoint))
+                .map(|best_time| *split_time as i64 - best_time as i64)
+        })
+    } else {
+        // nothing to compare before the first run
+        best.filter(|_| time > 0).map(|path| time as i64 - path.time() as i64)
+    };
+    let collection = pathlog.active_collection.and_then(|id| pathlog.get_collection(id)).map(|c| c.name.clone());
+
+    json!({
+        "time": time,
+        "recording": pathlog.is_recording(),
+        "paused": pathlog.is_paused(),
+        "delta": delta,
+        "collection": collection,
+        "top": &top[..top.len().min(top_n)],
+    }).to_string()
+}
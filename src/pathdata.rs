@@ -1,35 +1,100 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::vec::Vec;
 use glam::{Vec3, Mat3};
 use serde_binary::binary_stream;
 use serde::{Serialize, Deserialize};
+use sha2::{Digest, Sha256};
 use tracing::info;
 use uuid::Uuid;
 
+use crate::backup;
+use crate::codec;
 use crate::error::Error;
+use crate::layout::{self, Shape};
+use crate::gamedata::GameVersion;
 
-const CURRENT_FILE_VERSION : &str = "0.6";
+const CURRENT_FILE_VERSION : &str = "0.8";
+const FILE_VERSION_07 : &str = "0.7";
+const FILE_VERSION_06 : &str = "0.6";
 const LEGACY_FILE_VERSION : &str = "0.5";
 pub const FILE_EXTENTION : &str = "ccmp";
 
-#[derive(Clone)]
+// files since 0.7 start with a header: magic, header version, payload length, sha-256 of the payload
+const FILE_MAGIC : [u8; 8] = *b"CELCOMP\0";
+const HEADER_VERSION : u32 = 1;
+const HEADER_LEN : usize = 8 + 4 + 8 + 32;
+
+// serde_binary writes a unit variant as its name plus a unit byte but only reads the name back,
+// so the version is written as a plain string
+mod game_version_name {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use crate::gamedata::GameVersion;
+
+    const NAMES : [(GameVersion, &str); 4] = [
+        (GameVersion::V101, "V101"),
+        (GameVersion::V102, "V102"),
+        (GameVersion::BAG, "BAG"),
+        (GameVersion::Debug, "Debug"),
+    ];
+
+    pub fn serialize<S: Serializer>(version: &Option<GameVersion>, serializer: S) -> Result<S::Ok, S::Error> {
+        match version.and_then(|version| NAMES.iter().find(|(v, _)| *v == version)) {
+            Some((_, name)) => serializer.serialize_some(name),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<GameVersion>, D::Error> {
+        let Some(name) = Option::<String>::deserialize(deserializer)? else { return Ok(None) };
+        NAMES.iter().find(|(_, n)| *n == name)
+            .map(|(version, _)| Some(*version))
+            .ok_or_else(|| serde::de::Error::custom(format!("unknown game version '{name}'")))
+    }
+}
+
+#[derive(Clone, Default)]
 #[derive(Serialize, Deserialize)]
+pub struct PathMeta {
+    // seconds since the unix epoch, 0 for paths from older files
+    pub recorded_at: u64,
+    #[serde(with = "game_version_name")]
+    pub game_version: Option<GameVersion>,
+    pub runner: String,
+    pub notes: String,
+    pub tags: Vec<String>,
+}
+
+#[derive(Clone)]
 pub struct Path {
 	id: Uuid,
 	times: Vec<u64>,
     segments: Vec<Vec<[f32; 3]>>,
+    meta: PathMeta,
 }
 
 impl Path {
     pub fn new() -> Path {
+        Path::with_id(Uuid::new_v4())
+    }
+
+    pub fn with_id(id: Uuid) -> Path {
         Path {
-            id: Uuid::new_v4(),
+            id,
             times: Vec::new(),
             segments: Vec::new(),
+            meta: PathMeta::default(),
         }
     }
 
+    pub fn meta(&self) -> &PathMeta {
+        &self.meta
+    }
+
+    pub fn meta_mut(&mut self) -> &mut PathMeta {
+        &mut self.meta
+    }
+
     pub fn len(&self) -> usize {
         let mut sum : usize = 0;
         for segment in &self.segments {
@@ -60,6 +125,14 @@ impl Path {
         self.segments.clone()
     }
 
+    pub fn segment_count(&self) -> usize {
+        self.segments.len()
+    }
+
+    pub fn segment(&self, index: usize) -> Option<&[[f32; 3]]> {
+        self.segments.get(index).map(|s| s.as_slice())
+    }
+
     pub fn segment_nodes(&self, index: usize) -> Option<Vec<[f32; 3]>> {
         if index < self.segments.len() {
             Some(self.segments[index].clone())
@@ -102,6 +175,155 @@ impl Path {
         self.segments.clear();
         self.times.clear();
     }
+
+    // when the path passed the checkpoint, going through them in order like the recording does. paths don't keep
+    // split times, so it's the time of the segments before plus the part of its segment up to the first node inside.
+    // nodes of a segment are about evenly spread in time with interval sampling
+    pub fn checkpoint_time(&self, checkpoints: &[BoxCollider], checkpoint: usize) -> Option<u64> {
+        let mut position = (0, 0);
+
+        for trigger in checkpoints.get(..=checkpoint)? {
+            let mut trigger = *trigger;
+            let (start_segment, start_index) = position;
+
+            position = self.segments.iter().enumerate().skip(start_segment).find_map(|(s, segment)| {
+                let skip = if s == start_segment { start_index } else { 0 };
+                segment.iter().skip(skip).position(|node| trigger.check_point_collision(Vec3::from_array(*node))).map(|i| (s, i + skip))
+            })?;
+        }
+
+        let (segment, index) = position;
+        let before : u64 = self.times.iter().take(segment).sum();
+        let segment_time = *self.times.get(segment)?;
+        let nodes = self.segments[segment].len();
+
+        Some(before + segment_time * index as u64 / (nodes.max(2) - 1) as u64)
+    }
+
+    pub fn simplify(&mut self, tolerance: f32) -> SimplifyReport {
+        let mut report = SimplifyReport::default();
+
+        for segment in &mut self.segments {
+            report.nodes_before += segment.len();
+            *segment = simplify_nodes(segment, tolerance);
+            report.nodes_after += segment.len();
+        }
+
+        report
+    }
+}
+
+#[derive(Clone, Copy, Default)]
+pub struct SimplifyReport {
+    pub nodes_before: usize,
+    pub nodes_after: usize,
+    // encoded geometry, as it would be saved
+    pub bytes_before: usize,
+    pub bytes_after: usize,
+}
+
+impl SimplifyReport {
+    pub fn add(&mut self, other: SimplifyReport) {
+        self.nodes_before += other.nodes_before;
+        self.nodes_after += other.nodes_after;
+        self.bytes_before += other.bytes_before;
+        self.bytes_after += other.bytes_after;
+    }
+
+    pub fn nodes_removed(&self) -> usize {
+        self.nodes_before - self.nodes_after
+    }
+
+    pub fn bytes_saved(&self) -> usize {
+        self.bytes_before.saturating_sub(self.bytes_after)
+    }
+}
+
+impl std::fmt::Display for SimplifyReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let percent = |part: usize, whole: usize| if whole == 0 { 0. } else { part as f32 / whole as f32 * 100. };
+        write!(f, "Removed {} of {} nodes ({:.1}%), saved {} of {} bytes ({:.1}%)",
+            self.nodes_removed(), self.nodes_before, percent(self.nodes_removed(), self.nodes_before),
+            self.bytes_saved(), self.bytes_before, percent(self.bytes_saved(), self.bytes_before))
+    }
+}
+
+// bytes the paths' geometry takes in a comparison file
+pub fn geometry_size<'a>(paths: impl IntoIterator<Item = &'a Path>, compress: bool) -> usize {
+    let mut geometry = Vec::new();
+    for path in paths {
+        codec::write_path(&mut geometry, &path.segments, codec::DEFAULT_QUANTISATION);
+    }
+
+    if compress {
+        if let Ok(compressed) = codec::compress(&geometry) { return compressed.len(); }
+    }
+
+    geometry.len()
+}
+
+// simplifies every path, returns the report and the ids of paths that lost nodes
+pub fn simplify_paths(paths: &mut HashMap<Uuid, Path>, tolerance: f32, compress: bool) -> (SimplifyReport, Vec<Uuid>) {
+    let bytes_before = geometry_size(paths.values(), compress);
+    let mut report = SimplifyReport::default();
+    let mut changed = Vec::new();
+
+    for (path_id, path) in paths.iter_mut() {
+        let path_report = path.simplify(tolerance);
+        if path_report.nodes_removed() > 0 {
+            changed.push(*path_id);
+        }
+        report.add(path_report);
+    }
+
+    report.bytes_before = bytes_before;
+    report.bytes_after = geometry_size(paths.values(), compress);
+
+    (report, changed)
+}
+
+// ramer-douglas-peucker. the first and last node always stay
+pub fn simplify_nodes(nodes: &[[f32; 3]], tolerance: f32) -> Vec<[f32; 3]> {
+    if nodes.len() < 3 || tolerance <= 0. { return nodes.to_vec(); }
+
+    let mut keep = vec![false; nodes.len()];
+    keep[0] = true;
+    keep[nodes.len() - 1] = true;
+
+    let mut stack = vec![(0, nodes.len() - 1)];
+
+    while let Some((first, last)) = stack.pop() {
+        let a = Vec3::from_array(nodes[first]);
+        let b = Vec3::from_array(nodes[last]);
+
+        let mut max_distance = 0.;
+        let mut index = first;
+
+        for (i, node) in nodes.iter().enumerate().take(last).skip(first + 1) {
+            let distance = point_segment_distance(Vec3::from_array(*node), a, b);
+            if distance > max_distance {
+                max_distance = distance;
+                index = i;
+            }
+        }
+
+        if max_distance > tolerance {
+            keep[index] = true;
+            stack.push((first, index));
+            stack.push((index, last));
+        }
+    }
+
+    nodes.iter().zip(keep).filter(|(_, k)| *k).map(|(node, _)| *node).collect()
+}
+
+fn point_segment_distance(point: Vec3, a: Vec3, b: Vec3) -> f32 {
+    let ab = b - a;
+    let length_squared = ab.length_squared();
+    if length_squared == 0. { return point.distance(a); }
+
+    let t = ((point - a).dot(ab) / length_squared).clamp(0., 1.);
+    point.distance(a + ab * t)
 }
 
 impl PartialEq for Path {
@@ -118,7 +340,7 @@ impl std::fmt::Debug for Path {
     }
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, PartialEq)]
 #[derive(Serialize, Deserialize)]
 pub struct BoxCollider {
     id: Uuid,
@@ -131,8 +353,12 @@ pub struct BoxCollider {
 
 impl BoxCollider {
     pub fn new(pos: [f32; 3], rotation: [f32; 3], size: [f32; 3]) -> BoxCollider {
+        BoxCollider::with_id(Uuid::new_v4(), pos, rotation, size)
+    }
+
+    pub fn with_id(id: Uuid, pos: [f32; 3], rotation: [f32; 3], size: [f32; 3]) -> BoxCollider {
         BoxCollider {
-            id: Uuid::new_v4(),
+            id,
             position: pos.into(),
             rotation,
             basis: Mat3::from_euler(glam::EulerRot::XYZ, rotation[0], rotation[1], rotation[2]).transpose(),
@@ -166,6 +392,7 @@ impl BoxCollider {
 }
 
 #[derive(Clone, Copy)]
+#[derive(Serialize, Deserialize)]
 pub enum HighPassFilter {
     Gold,
     Path {
@@ -173,6 +400,43 @@ pub enum HighPassFilter {
     },
 }
 
+// narrows down which paths are listed and drawn, None means no limit
+#[derive(Clone, Default, PartialEq)]
+#[derive(Serialize, Deserialize)]
+pub struct DisplayFilter {
+    pub min_time: Option<u64>,
+    pub max_time: Option<u64>,
+    // unix time, before is exclusive
+    pub recorded_after: Option<u64>,
+    pub recorded_before: Option<u64>,
+    pub tag: Option<String>,
+    pub segment_count: Option<usize>,
+    // only the fastest n matching paths of every collection
+    pub top_n: Option<usize>,
+}
+
+impl DisplayFilter {
+    pub fn is_active(&self) -> bool {
+        *self != DisplayFilter::default()
+    }
+
+    // everything except top n, that depends on the other paths
+    pub fn matches(&self, path: &Path) -> bool {
+        let time = path.time();
+        let meta = path.meta();
+
+        // paths from old files have no date, so any date filter hides them
+        let dated = meta.recorded_at != 0;
+
+        self.min_time.is_none_or(|min| time >= min)
+            && self.max_time.is_none_or(|max| time <= max)
+            && self.recorded_after.is_none_or(|after| dated && meta.recorded_at >= after)
+            && self.recorded_before.is_none_or(|before| dated && meta.recorded_at < before)
+            && self.tag.as_ref().is_none_or(|tag| meta.tags.contains(tag))
+            && self.segment_count.is_none_or(|count| path.segment_count() == count)
+    }
+}
+
 #[derive(Clone)]
 #[derive(Serialize, Deserialize)]
 pub struct PathCollection {
@@ -183,8 +447,12 @@ pub struct PathCollection {
 
 impl PathCollection {
     pub fn new(new_name: String) -> PathCollection {
+        PathCollection::with_id(Uuid::new_v4(), new_name)
+    }
+
+    pub fn with_id(id: Uuid, new_name: String) -> PathCollection {
         PathCollection {
-            id: Uuid::new_v4(),
+            id,
             name: new_name,
             paths: Vec::new(),
         }
@@ -217,19 +485,21 @@ impl PathCollection {
     }
 }
 
-#[derive(Serialize, Deserialize)]
+// everything gets converted to this after loading
 pub struct CompFile {
     version: String,
     paths: HashMap<Uuid, Path>,
     trigger_data: [[[f32; 3]; 3]; 2],
     collections: Vec<PathCollection>,
+    // path ids in recording order, if the file knows it
+    order: Option<Vec<Uuid>>,
 }
 
 impl CompFile {
 
     // for some reason glam vectors don't deserialize correctly with serde_binary
     // so i have to convert them from and to arrays myself
-    pub fn new(trigger: [BoxCollider; 2], paths: HashMap<Uuid, Path>, collections: Vec<PathCollection>) -> CompFile {
+    pub fn new(trigger: [BoxCollider; 2], paths: HashMap<Uuid, Path>, collections: Vec<PathCollection>, order: Option<Vec<Uuid>>) -> CompFile {
 
         let trigger_data = [[
                 trigger[0].position,//.to_array(),
@@ -247,6 +517,7 @@ impl CompFile {
             paths,
             trigger_data,
             collections,
+            order,
         }
     }
 
@@ -265,6 +536,10 @@ impl CompFile {
         ]
     }
 
+    pub fn version(&self) -> &str {
+        &self.version
+    }
+
     pub fn get_paths(&self) -> HashMap<Uuid, Path> {
         self.paths.clone()
     }
@@ -273,45 +548,319 @@ impl CompFile {
         self.collections.clone()
     }
 
+    // only if it covers every path exactly once
+    pub fn recording_order(&self) -> Option<Vec<Uuid>> {
+        let order = self.order.as_ref()?;
+        let unique : HashSet<&Uuid> = order.iter().collect();
+
+        if unique.len() != order.len() || order.len() != self.paths.len() { return None; }
+        if !order.iter().all(|id| self.paths.contains_key(id)) { return None; }
+
+        Some(order.clone())
+    }
+
     pub fn from_file(file_path: String) -> Result<CompFile, Error> {
-        let file_content = fs::read(file_path)?;
+        CompFile::from_bytes(fs::read(file_path)?)
+    }
+
+    // never panics, whatever the input
+    pub fn from_bytes(file_content: Vec<u8>) -> Result<CompFile, Error> {
+        let comp_file = if file_content.starts_with(&FILE_MAGIC) {
+            CompFile::from_payload(read_header(&file_content)?.to_vec())?
+        } else {
+            // older files have no header
+            CompFile::from_payload(file_content)?
+        };
+
+        comp_file.check_references()?;
+        Ok(comp_file)
+    }
+
+    // the path log expects every path a collection lists to exist under its own id
+    fn check_references(&self) -> Result<(), Error> {
+        if let Some((id, path)) = self.paths.iter().find(|(id, path)| **id != path.id) {
+            return Err(Error::CorruptField { field: "paths".to_string(), msg: format!("path {} is stored as {id}", path.id) });
+        }
+
+        for collection in &self.collections {
+            if let Some(id) = collection.paths.iter().find(|id| !self.paths.contains_key(id)) {
+                return Err(Error::CorruptField {
+                    field: "collections".to_string(),
+                    msg: format!("collection '{}' lists path {id}, which isn't in the file", collection.name),
+                });
+            }
+        }
 
-        let mut head : usize = 4;
+        Ok(())
+    }
 
-        let first_field_length = serde_binary::from_slice::<u32>(&file_content[head..(head + 4)], binary_stream::Endian::Little)? as usize;
-        let first_field_name = serde_binary::from_slice::<String>(&file_content[head..(head + 4 + first_field_length)], binary_stream::Endian::Little)?;
-        head += 4 + first_field_length;
+    fn from_payload(file_content: Vec<u8>) -> Result<CompFile, Error> {
+        // serde_binary structs start with a 4 byte field count, then each field name
+        let (first_field_name, head) = read_string(&file_content, 4, "first field name")?;
 
-        if first_field_name != "version" {
+        if first_field_name == "trigger_data" {
             info!("File Version: 0.4");
-            let old_comp_file = serde_binary::from_vec::<CompFile04>(file_content, binary_stream::Endian::Little)?;
+            let old_comp_file = decode::<CompFile04>(file_content, &layout::COMP_FILE_04, "0.4 comparison")?;
             return Ok(CompFile::from(CompFile05::from(old_comp_file)));
         }
 
-        let file_version_len = serde_binary::from_slice::<u32>(&file_content[head..(head + 4)], binary_stream::Endian::Little)? as usize;
-        let file_version = serde_binary::from_slice::<String>(&file_content[head..(head + 4 + file_version_len)], binary_stream::Endian::Little)?;
+        if first_field_name != "version" { return Err(Error::WrongMagic); }
+
+        let (file_version, _) = read_string(&file_content, head, "version")?;
 
         info!("File Version: {file_version}");
 
         if file_version == CURRENT_FILE_VERSION {
-            Ok(serde_binary::from_vec::<CompFile>(file_content.clone(), binary_stream::Endian::Little)?)
+            CompFile::try_from(decode::<CompFile08>(file_content, &layout::COMP_FILE_08, "0.8 comparison")?)
+        }
+        else if file_version == FILE_VERSION_07 {
+            CompFile::try_from(decode::<CompFile07>(file_content, &layout::COMP_FILE_07, "0.7 comparison")?)
+        }
+        else if file_version == FILE_VERSION_06 {
+            Ok(CompFile::from(decode::<CompFile06>(file_content, &layout::COMP_FILE_06, "0.6 comparison")?))
         }
         else if file_version == LEGACY_FILE_VERSION {
-            Ok(CompFile::from(serde_binary::from_vec::<CompFile05>(file_content.clone(), binary_stream::Endian::Little)?))
+            Ok(CompFile::from(decode::<CompFile05>(file_content, &layout::COMP_FILE_05, "0.5 comparison")?))
         }
         else {
-            Err(Error::Binary{ msg: format!("Version {file_version} not compatible.") })
+            Err(Error::UnknownVersion { version: file_version })
         }
     }
 
-	pub fn to_file(&self, file_path: String) -> Result<(), Error> {
-        let file_contents = serde_binary::to_vec(self, binary_stream::Endian::Little)?;
-        Ok(fs::write(file_path, file_contents)?)
+	pub fn to_file(&self, file_path: String, compress: bool) -> Result<(), Error> {
+        backup::write_atomic(std::path::Path::new(&file_path), &self.to_bytes(compress)?)
 
         // fs::write(
         //     file_path, serde_binary::to_vec(self, binary_stream::Endian::Little).expect("[Celestial][PathLog] Error: failed to serialize comparison file!")
         // ).expect("[Celestial][PathLog] Error: failed to write comparison file!");
     }
+
+    pub fn to_bytes(&self, compress: bool) -> Result<Vec<u8>, Error> {
+        let encoded = CompFile08::encode(self, compress)?;
+        let payload = serde_binary::to_vec(&encoded, binary_stream::Endian::Little)?;
+
+        Ok(with_header(&payload))
+    }
+
+    // encodes and decodes the file and lists every path that didn't survive within the quantisation step.
+    // saving doesn't check this, the tests do
+    #[cfg(test)]
+    fn round_trip_differences(&self, compress: bool) -> Vec<String> {
+        let decoded = match self.to_bytes(compress).and_then(CompFile::from_bytes) {
+            Ok(decoded) => decoded,
+            Err(e) => return vec![e.to_string()],
+        };
+
+        let max_error = codec::DEFAULT_QUANTISATION * 0.5 + 1e-4;
+        let mut differences = Vec::new();
+
+        for (id, path) in &self.paths {
+            let Some(other) = decoded.paths.get(id) else {
+                differences.push(format!("path {id} missing"));
+                continue;
+            };
+
+            let same_shape = path.times == other.times
+                && path.meta.recorded_at == other.meta.recorded_at
+                && path.meta.tags == other.meta.tags
+                && path.segments.len() == other.segments.len()
+                && path.segments.iter().zip(&other.segments).all(|(a, b)| a.len() == b.len());
+
+            let close = same_shape && path.segments.iter().flatten().zip(other.segments.iter().flatten())
+                .all(|(a, b)| (0..3).all(|i| (a[i] - b[i]).abs() <= max_error.max(a[i].abs() * f32::EPSILON * 2.)));
+
+            if !close { differences.push(format!("path {id} differs")); }
+        }
+
+        if self.paths.len() != decoded.paths.len() { differences.push("path count differs".to_string()); }
+
+        differences
+    }
+}
+
+fn with_header(payload: &[u8]) -> Vec<u8> {
+    let mut file_content = Vec::with_capacity(HEADER_LEN + payload.len());
+    file_content.extend_from_slice(&FILE_MAGIC);
+    file_content.extend_from_slice(&HEADER_VERSION.to_le_bytes());
+    file_content.extend_from_slice(&(payload.len() as u64).to_le_bytes());
+    file_content.extend_from_slice(&Sha256::digest(payload));
+    file_content.extend_from_slice(payload);
+    file_content
+}
+
+// checks the header and returns the payload
+fn read_header(content: &[u8]) -> Result<&[u8], Error> {
+    if content.len() < HEADER_LEN {
+        return Err(Error::Truncated { msg: "header cut off".to_string() });
+    }
+
+    let header_version = u32::from_le_bytes(content[8..12].try_into().unwrap());
+    if header_version != HEADER_VERSION {
+        return Err(Error::UnknownVersion { version: format!("header {header_version}") });
+    }
+
+    let payload_len = u64::from_le_bytes(content[12..20].try_into().unwrap());
+    let checksum = &content[20..HEADER_LEN];
+    let payload = &content[HEADER_LEN..];
+
+    if (payload.len() as u64) < payload_len {
+        return Err(Error::Truncated { msg: format!("expected {payload_len} bytes, found {}", payload.len()) });
+    }
+    if payload.len() as u64 > payload_len {
+        return Err(Error::CorruptField { field: "header".to_string(), msg: "data after the end of the file".to_string() });
+    }
+
+    if Sha256::digest(payload).as_slice() != checksum {
+        return Err(Error::ChecksumMismatch);
+    }
+
+    Ok(payload)
+}
+
+// u32 length followed by utf-8 bytes
+fn read_string(content: &[u8], head: usize, field: &str) -> Result<(String, usize), Error> {
+    let Some(length_bytes) = content.get(head..(head + 4)) else {
+        return Err(Error::Truncated { msg: format!("{field} missing") });
+    };
+    let length = u32::from_le_bytes(length_bytes.try_into().unwrap()) as usize;
+
+    let Some(string_bytes) = content.get((head + 4)..(head + 4).saturating_add(length)) else {
+        return Err(Error::Truncated { msg: format!("{field} cut off") });
+    };
+
+    match String::from_utf8(string_bytes.to_vec()) {
+        Ok(string) => Ok((string, head + 4 + length)),
+        Err(e) => Err(Error::CorruptField { field: field.to_string(), msg: e.to_string() }),
+    }
+}
+
+// serde_binary trusts every length in the file, so the whole layout is checked first (see layout.rs)
+fn decode<T: serde::de::DeserializeOwned>(content: Vec<u8>, shape: &Shape, what: &str) -> Result<T, Error> {
+    layout::check(&content, shape, what)?;
+
+    serde_binary::from_vec::<T>(content, binary_stream::Endian::Little)
+        .map_err(|e| Error::CorruptField { field: what.to_string(), msg: e.to_string() })
+}
+
+// everything about a path except its nodes
+trait PathHeader {
+    fn into_parts(self) -> (Uuid, Vec<u64>, PathMeta);
+}
+
+#[derive(Serialize, Deserialize)]
+struct PathHeader07 {
+    id: Uuid,
+    times: Vec<u64>,
+}
+
+impl PathHeader for PathHeader07 {
+    fn into_parts(self) -> (Uuid, Vec<u64>, PathMeta) {
+        (self.id, self.times, PathMeta::default())
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct PathHeader08 {
+    id: Uuid,
+    times: Vec<u64>,
+    meta: PathMeta,
+}
+
+impl PathHeader for PathHeader08 {
+    fn into_parts(self) -> (Uuid, Vec<u64>, PathMeta) {
+        (self.id, self.times, self.meta)
+    }
+}
+
+// same as 0.6 but the nodes are packed into one geometry stream (see codec.rs).
+// 0.8 only adds the path metadata to the headers
+#[derive(Serialize, Deserialize)]
+struct PackedCompFile<H> {
+    version: String,
+    trigger_data: [[[f32; 3]; 3]; 2],
+    collections: Vec<PathCollection>,
+    quantisation: f32,
+    geometry_flags: u8,
+    paths: Vec<H>,
+    geometry: Vec<u8>,
+}
+
+type CompFile07 = PackedCompFile<PathHeader07>;
+type CompFile08 = PackedCompFile<PathHeader08>;
+
+impl CompFile08 {
+    fn encode(comp_file: &CompFile, compress: bool) -> Result<CompFile08, Error> {
+        let quantisation = codec::DEFAULT_QUANTISATION;
+        let mut paths = Vec::with_capacity(comp_file.paths.len());
+        let mut geometry = Vec::new();
+
+        let order = comp_file.recording_order();
+        let ordered : Vec<&Path> = match &order {
+            Some(order) => order.iter().map(|id| &comp_file.paths[id]).collect(),
+            None => comp_file.paths.values().collect(),
+        };
+
+        for path in ordered {
+            paths.push(PathHeader08 { id: path.id, times: path.times.clone(), meta: path.meta.clone() });
+            codec::write_path(&mut geometry, &path.segments, quantisation);
+        }
+
+        let mut geometry_flags = 0;
+
+        if compress {
+            geometry = codec::compress(&geometry)?;
+            geometry_flags |= codec::FLAG_DEFLATE;
+        }
+
+        if order.is_some() {
+            geometry_flags |= codec::FLAG_RECORDING_ORDER;
+        }
+
+        Ok(CompFile08 {
+            version: CURRENT_FILE_VERSION.into(),
+            trigger_data: comp_file.trigger_data,
+            collections: comp_file.collections.clone(),
+            quantisation,
+            geometry_flags,
+            paths,
+            geometry,
+        })
+    }
+}
+
+impl<H: PathHeader> TryFrom<PackedCompFile<H>> for CompFile {
+    type Error = Error;
+
+    fn try_from(file: PackedCompFile<H>) -> Result<Self, Error> {
+        if file.quantisation.is_nan() || file.quantisation <= 0. {
+            return Err(Error::CorruptField { field: "quantisation".to_string(), msg: file.quantisation.to_string() });
+        }
+
+        let geometry = if file.geometry_flags & codec::FLAG_DEFLATE != 0 {
+            codec::decompress(&file.geometry)?
+        } else {
+            file.geometry
+        };
+
+        let mut reader = codec::GeometryReader::new(&geometry);
+        let mut paths = HashMap::new();
+        let mut order = Vec::with_capacity(file.paths.len());
+
+        for header in file.paths {
+            let (id, times, meta) = header.into_parts();
+            let segments = reader.read_path(file.quantisation)?;
+            paths.insert(id, Path { id, times, segments, meta });
+            order.push(id);
+        }
+
+        if !reader.is_done() {
+            return Err(Error::CorruptField { field: "geometry".to_string(), msg: "trailing data".to_string() });
+        }
+
+        let order = (file.geometry_flags & codec::FLAG_RECORDING_ORDER != 0).then_some(order);
+
+        Ok(CompFile { version: file.version, paths, trigger_data: file.trigger_data, collections: file.collections, order })
+    }
 }
 
 // Backwards Compatibility //
@@ -393,12 +942,399 @@ impl From<CompFile05> for CompFile {
 
             for old_path in old_collection.paths {
                 new_collection.paths.push(old_path.id);
-                paths.insert(old_path.id, Path{ id: old_path.id, times: old_path.times, segments: old_path.segments });
+                paths.insert(old_path.id, Path { id: old_path.id, times: old_path.times, segments: old_path.segments, meta: PathMeta::default() });
             }
 
             collections.push(new_collection);
         }
 
-        CompFile { version: CURRENT_FILE_VERSION.into(), paths, trigger_data: old_comp_file.trigger_data, collections }
+        CompFile { version: FILE_VERSION_06.into(), paths, trigger_data: old_comp_file.trigger_data, collections, order: None }
     }
-}
\ No newline at end of file
+}
+
+#[derive(Serialize, Deserialize)]
+struct Path06 {
+	id: Uuid,
+	times: Vec<u64>,
+    segments: Vec<Vec<[f32; 3]>>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CompFile06 {
+    version: String,
+    paths: HashMap<Uuid, Path06>,
+    trigger_data: [[[f32; 3]; 3]; 2],
+    collections: Vec<PathCollection>,
+}
+
+impl From<CompFile06> for CompFile {
+    fn from(old_comp_file: CompFile06) -> Self {
+        let paths = old_comp_file.paths.into_iter()
+            .map(|(id, old_path)| (id, Path { id: old_path.id, times: old_path.times, segments: old_path.segments, meta: PathMeta::default() }))
+            .collect();
+
+        CompFile { version: old_comp_file.version, paths, trigger_data: old_comp_file.trigger_data, collections: old_comp_file.collections, order: None }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pathlog::PathLog;
+
+    const FUZZ_RUNS: usize = 2_000;
+
+    fn trigger() -> BoxCollider {
+        BoxCollider::new([0., 0., 0.], [0., 0., 0.], [1., 1., 1.])
+    }
+
+    const TRIGGER_DATA: [[[f32; 3]; 3]; 2] = [[[0., 0., 0.], [0., 0., 0.], [1., 1., 1.]]; 2];
+
+    // the same three paths saved by each version's structs: "any%" holds paths 1 and 2, "glitchless" path 3.
+    // 0.4 only kept one time and one segment per path
+    const LEGACY_FIXTURES: [(&str, &[u8]); 4] = [
+        ("0.4", include_bytes!("../tests/fixtures/comparison_0.4.ccmp")),
+        ("0.5", include_bytes!("../tests/fixtures/comparison_0.5.ccmp")),
+        ("0.6", include_bytes!("../tests/fixtures/comparison_0.6.ccmp")),
+        ("0.7", include_bytes!("../tests/fixtures/comparison_0.7.ccmp")),
+    ];
+    const CURRENT_FIXTURE: &[u8] = include_bytes!("../tests/fixtures/comparison_0.8.ccmp");
+    const FIXTURE_TIMES: [u64; 3] = [132_460, 131_345, 141_188];
+
+    fn fixture_id(index: u128) -> Uuid {
+        Uuid::from_u128(index + 1)
+    }
+
+    fn check_fixture_contents(comp_file: &CompFile, segments: usize) {
+        let paths = comp_file.get_paths();
+        assert_eq!(paths.len(), 3);
+
+        for (index, time) in FIXTURE_TIMES.iter().enumerate() {
+            let path = &paths[&fixture_id(index as u128)];
+            assert_eq!(path.time(), *time);
+            assert_eq!(path.segments.len(), segments);
+            assert_eq!(path.len(), 24);
+        }
+
+        let collections : Vec<(String, Vec<Uuid>)> = comp_file.get_collections().into_iter().map(|c| (c.name.clone(), c.paths)).collect();
+        assert_eq!(collections, vec![
+            ("any%".to_string(), vec![fixture_id(0), fixture_id(1)]),
+            ("glitchless".to_string(), vec![fixture_id(2)]),
+        ]);
+
+        let [Some(start), Some(end)] = comp_file.get_triggers() else { panic!("triggers missing") };
+        assert_eq!(start.position, [-215.25, 12.0, 480.75]);
+        assert_eq!(end.size, [6.0, 8.0, 6.0]);
+    }
+
+    // xorshift, the same inputs every run
+    fn next_random(state: &mut u64) -> u64 {
+        *state ^= *state << 13;
+        *state ^= *state >> 7;
+        *state ^= *state << 17;
+        *state
+    }
+
+    fn random_segments(rng: &mut u64) -> Vec<Vec<[f32; 3]>> {
+        (0..1 + next_random(rng) % 3).map(|_| {
+            (0..next_random(rng) % 12).map(|_| {
+                [0, 1, 2].map(|_| (next_random(rng) % 20_000) as f32 * 0.01 - 100.)
+            }).collect()
+        }).collect()
+    }
+
+    fn sample_comp_file(rng: &mut u64) -> CompFile {
+        let mut paths = HashMap::new();
+        let mut collections = Vec::new();
+        let mut order = Vec::new();
+
+        for c in 0..3 {
+            let mut collection = PathCollection::new(format!("collection {c}"));
+
+            for _ in 0..4 {
+                let segments = random_segments(rng);
+                let times = segments.iter().map(|_| 1_000 + next_random(rng) % 10_000).collect();
+                let meta = PathMeta { recorded_at: next_random(rng) % 1_000_000, tags: vec!["fuzz".to_string()], ..PathMeta::default() };
+                let path = Path { id: Uuid::new_v4(), times, segments, meta };
+
+                collection.push(path.id);
+                order.push(path.id);
+                paths.insert(path.id, path);
+            }
+
+            collections.push(collection);
+        }
+
+        CompFile::new([trigger(), trigger()], paths, collections, Some(order))
+    }
+
+    fn to_vec<T: Serialize>(value: &T) -> Vec<u8> {
+        serde_binary::to_vec(value, binary_stream::Endian::Little).unwrap()
+    }
+
+    // written the way the versions before 0.8 wrote them
+    fn legacy_samples(rng: &mut u64) -> Vec<(&'static str, Vec<u8>)> {
+        let old_paths = |rng: &mut u64| -> Vec<Path05> {
+            (0..3).map(|_| {
+                let segments = random_segments(rng);
+                Path05 { id: Uuid::new_v4(), times: segments.iter().map(|_| next_random(rng) % 10_000).collect(), segments }
+            }).collect()
+        };
+
+        let file_04 = CompFile04 {
+            trigger_data: TRIGGER_DATA,
+            collections: (0..2).map(|c| PathCollection04 {
+                id: Uuid::new_v4(),
+                name: format!("collection {c}"),
+                paths: old_paths(rng).into_iter()
+                    .map(|p| OldPath04 { id: p.id, time: p.times[0], nodes: p.segments.concat() })
+                    .collect(),
+            }).collect(),
+        };
+
+        let file_05 = CompFile05 {
+            version: LEGACY_FILE_VERSION.to_string(),
+            trigger_data: TRIGGER_DATA,
+            collections: (0..2).map(|c| PathCollection05 { id: Uuid::new_v4(), name: format!("collection {c}"), paths: old_paths(rng) }).collect(),
+        };
+
+        let mut collection_06 = PathCollection::new("collection".to_string());
+        let paths_06 : HashMap<Uuid, Path06> = old_paths(rng).into_iter().map(|p| {
+            collection_06.push(p.id);
+            (p.id, Path06 { id: p.id, times: p.times, segments: p.segments })
+        }).collect();
+
+        let file_06 = CompFile06 { version: FILE_VERSION_06.to_string(), paths: paths_06, trigger_data: TRIGGER_DATA, collections: vec![collection_06] };
+
+        let current = CompFile08::encode(&sample_comp_file(rng), false).unwrap();
+        let file_07 = CompFile07 {
+            version: FILE_VERSION_07.to_string(),
+            trigger_data: current.trigger_data,
+            collections: current.collections,
+            quantisation: current.quantisation,
+            geometry_flags: current.geometry_flags & codec::FLAG_DEFLATE,
+            paths: current.paths.into_iter().map(|h| PathHeader07 { id: h.id, times: h.times }).collect(),
+            geometry: current.geometry,
+        };
+
+        vec![
+            ("0.4", to_vec(&file_04)),
+            ("0.5", to_vec(&file_05)),
+            ("0.6", to_vec(&file_06)),
+            // 0.7 already had the header
+            ("0.7", with_header(&to_vec(&file_07))),
+        ]
+    }
+
+    // damages the input in one of the ways a broken download or disk would, or like someone poking at lengths
+    fn mutate(rng: &mut u64, input: &[u8]) -> Vec<u8> {
+        let mut output = input.to_vec();
+        if output.is_empty() { return output; }
+
+        let index = (next_random(rng) % output.len() as u64) as usize;
+
+        match next_random(rng) % 5 {
+            0 => output.truncate(index),
+            1 => {
+                for _ in 0..1 + next_random(rng) % 8 {
+                    let index = (next_random(rng) % output.len() as u64) as usize;
+                    output[index] ^= 1 << (next_random(rng) % 8);
+                }
+            }
+            2 => {
+                // a length or count that points past the end
+                let large = [u32::MAX, i32::MAX as u32, input.len() as u32 + 1, 1 << 20][(next_random(rng) % 4) as usize];
+                let end = (index + 4).min(output.len());
+                output[index..end].copy_from_slice(&large.to_le_bytes()[..end - index]);
+            }
+            3 => {
+                let end = (index + 1 + (next_random(rng) % 16) as usize).min(output.len());
+                output.drain(index..end);
+            }
+            _ => {
+                let end = (index + 1 + (next_random(rng) % 16) as usize).min(output.len());
+                let copy = output[index..end].to_vec();
+                output.splice(index..index, copy);
+            }
+        }
+
+        output
+    }
+
+    // whatever from_bytes accepts has to load into a path log and save again
+    fn check_accepted(comp_file: CompFile) {
+        let mut pathlog = PathLog::new();
+        pathlog.set_comparison(comp_file.get_triggers(), comp_file.get_paths(), comp_file.get_collections(), comp_file.recording_order());
+
+        let content = comp_file.to_bytes(false).expect("accepted file doesn't save");
+        CompFile::from_bytes(content).expect("accepted file doesn't load after saving");
+    }
+
+    #[test]
+    fn legacy_fixtures_migrate_to_current() {
+        for (version, content) in LEGACY_FIXTURES {
+            let legacy = CompFile::from_bytes(content.to_vec()).unwrap_or_else(|e| panic!("{version} fixture doesn't load: {e}"));
+            check_fixture_contents(&legacy, if version == "0.4" { 1 } else { 3 });
+            assert_eq!(legacy.recording_order(), None);
+
+            // saving writes the current version, which has to load back the same within the quantisation
+            let migrated = CompFile::from_bytes(legacy.to_bytes(false).unwrap()).unwrap();
+            assert_eq!(migrated.version(), CURRENT_FILE_VERSION);
+            check_fixture_contents(&migrated, if version == "0.4" { 1 } else { 3 });
+            assert_eq!(legacy.round_trip_differences(false), Vec::<String>::new(), "{version} fixture");
+            assert!(migrated.get_paths().values().all(|path| path.meta.recorded_at == 0));
+        }
+    }
+
+    #[test]
+    fn current_fixture_round_trips() {
+        let comp_file = CompFile::from_bytes(CURRENT_FIXTURE.to_vec()).unwrap();
+        check_fixture_contents(&comp_file, 3);
+        assert_eq!(comp_file.recording_order(), Some(vec![fixture_id(0), fixture_id(1), fixture_id(2)]));
+
+        let paths = comp_file.get_paths();
+        let meta = &paths[&fixture_id(1)].meta;
+        assert_eq!(meta.game_version, Some(GameVersion::V102));
+        assert_eq!(meta.notes, "skipped the ladder");
+        assert_eq!(meta.tags, vec!["any%".to_string(), "pb".to_string()]);
+
+        // decoding and encoding again lands on the same quantised values, so the file comes out unchanged
+        assert_eq!(comp_file.to_bytes(false).unwrap(), CURRENT_FIXTURE);
+        assert_eq!(comp_file.round_trip_differences(true), Vec::<String>::new());
+    }
+
+    #[test]
+    fn fuzz_current_payloads() {
+        let mut rng = 0x9E3779B97F4A7C15_u64;
+
+        for compress in [false, true] {
+            let sample = sample_comp_file(&mut rng);
+            assert_eq!(sample.round_trip_differences(compress), Vec::<String>::new());
+
+            let payload = to_vec(&CompFile08::encode(&sample, compress).unwrap());
+
+            for _ in 0..FUZZ_RUNS {
+                // with a matching checksum, so the damage reaches the decoder
+                if let Ok(comp_file) = CompFile::from_bytes(with_header(&mutate(&mut rng, &payload))) {
+                    check_accepted(comp_file);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn fuzz_legacy_files() {
+        let mut rng = 0x2545F4914F6CDD1D_u64;
+
+        for (version, content) in legacy_samples(&mut rng) {
+            assert!(CompFile::from_bytes(content.clone()).is_ok(), "undamaged {version} file doesn't load");
+
+            for _ in 0..FUZZ_RUNS {
+                let mut damaged = mutate(&mut rng, &content);
+
+                // 0.7 files have a checksum that would catch everything
+                if version == "0.7" && damaged.len() >= HEADER_LEN {
+                    damaged = with_header(&damaged[HEADER_LEN..]);
+                }
+
+                if let Ok(comp_file) = CompFile::from_bytes(damaged) {
+                    check_accepted(comp_file);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn fuzz_geometry() {
+        let mut rng = 0xD1B54A32D192ED03_u64;
+        let mut geometry = Vec::new();
+
+        for _ in 0..8 {
+            codec::write_path(&mut geometry, &random_segments(&mut rng), codec::DEFAULT_QUANTISATION);
+        }
+
+        for _ in 0..FUZZ_RUNS * 10 {
+            let damaged = mutate(&mut rng, &geometry);
+            let mut reader = codec::GeometryReader::new(&damaged);
+
+            while !reader.is_done() {
+                if reader.read_path(codec::DEFAULT_QUANTISATION).is_err() { break; }
+            }
+        }
+    }
+
+    #[test]
+    fn random_bytes_are_rejected() {
+        let mut rng = 0xA0761D6478BD642F_u64;
+
+        for _ in 0..FUZZ_RUNS {
+            let len = (next_random(&mut rng) % 256) as usize;
+            let content : Vec<u8> = (0..len).map(|_| next_random(&mut rng) as u8).collect();
+            assert!(CompFile::from_bytes(content).is_err());
+        }
+    }
+
+    #[test]
+    fn simplify_reports_nodes_and_bytes() {
+        let mut rng = 0x8BB84B93962EACC9_u64;
+        let mut paths : HashMap<Uuid, Path> = (0..4).map(|_| {
+            let mut path = Path::new();
+            for i in 0..50 {
+                path.add_node([i as f32, (next_random(&mut rng) % 10) as f32 * 0.001, 0.]);
+            }
+            (path.id, path)
+        }).collect();
+
+        for compress in [false, true] {
+            let mut copy = paths.clone();
+            let bytes_before = geometry_size(copy.values(), compress);
+            let (report, changed) = simplify_paths(&mut copy, 0.05, compress);
+
+            assert_eq!((report.nodes_before, report.nodes_after), (200, 8));
+            assert_eq!(changed.len(), 4);
+            assert_eq!(report.bytes_before, bytes_before);
+            assert_eq!(report.bytes_after, geometry_size(copy.values(), compress));
+            assert!(report.bytes_saved() > 0);
+            assert!(report.to_string().contains(&format!("saved {} of {bytes_before} bytes", report.bytes_saved())));
+        }
+
+        let (report, _) = simplify_paths(&mut paths, 0.05, false);
+        assert_eq!(report.nodes_removed(), 192);
+    }
+
+    #[test]
+    fn checkpoint_times_come_from_segment_times() {
+        let mut path = Path::new();
+        for x in 0..=10 { path.add_node([x as f32, 0., 0.]); }
+        path.end_segment(1_000);
+        for x in 10..=20 { path.add_node([x as f32, 0., 0.]); }
+        path.end_path(2_000);
+
+        let at = |x: f32| BoxCollider::new([x, 0., 0.], [0., 0., 0.], [0.4, 0.4, 0.4]);
+
+        let checkpoints = [at(5.), at(15.)];
+        assert_eq!(path.checkpoint_time(&checkpoints, 0), Some(500));
+        assert_eq!(path.checkpoint_time(&checkpoints, 1), Some(2_000));
+        assert_eq!(path.checkpoint_time(&checkpoints, 2), None);
+
+        // in order, a checkpoint behind the previous one was never reached
+        assert_eq!(path.checkpoint_time(&[at(15.), at(5.)], 1), None);
+        assert_eq!(path.checkpoint_time(&[at(30.)], 0), None);
+    }
+
+    #[test]
+    fn rejects_collections_with_missing_paths() {
+        let mut path = Path::new();
+        path.add_node([1., 2., 3.]);
+        path.end_path(1_000);
+
+        let mut collection = PathCollection::new("dangling".to_string());
+        collection.push(path.id());
+        collection.push(Uuid::new_v4());
+
+        let paths = HashMap::from([(path.id(), path)]);
+        let content = CompFile::new([trigger(), trigger()], paths, vec![collection], None).to_bytes(false).unwrap();
+
+        assert!(matches!(CompFile::from_bytes(content), Err(Error::CorruptField { .. })));
+    }
+}
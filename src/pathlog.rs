@@ -1,5 +1,4 @@
-use std::collections::HashMap;
-use std::time;
+use std::collections::{HashMap, HashSet};
 use std::vec::Vec;
 
 use tracing::{error, info};
@@ -7,27 +6,44 @@ use tracing::{error, info};
 use glam::{Vec3, Mat3};
 use uuid::Uuid;
 
+use crate::config::{SamplingMode, SamplingPolicy};
+use crate::comparison::{self, ComparedPath, ComparisonInput, ComparisonStrategy, PathRanking, VisibleCollection};
+use crate::clock::Clock;
+use crate::date;
 use crate::error::Error;
+use crate::gamedata::GameVersion;
+use crate::journal::{self, Journal, RecoveredRun};
+use crate::recording::RecordingEvent;
 use crate::{pathdata::*, RenderUpdates};
+use crate::rendering::PathUpdates;
+use crate::services::Services;
 
 pub const DEFAULT_COLLECTION_NAME : &str = "New Collection";
+// adaptive sampling ignores direction changes over shorter distances (jitter)
+const MIN_ADAPTIVE_STEP : f32 = 0.05;
 // pub const DIRECT_COLLECTION_NAME : &str = "Direct Paths";
 
-#[derive(Clone, Copy, Debug, PartialEq)]
-pub enum ComparisonMode {
-    All,
-    Gold,
-    Median,
+#[derive(Clone, Copy, Default)]
+pub struct SaveOptions {
+    // simplification tolerance applied before saving
+    pub simplification: Option<f32>,
+    pub compress: bool,
+    // how many old versions to keep in the backups folder
+    pub backups: usize,
 }
 
 #[derive(Clone, Copy)]
 pub struct Comparison {
-    pub mode: ComparisonMode,
+    pub mode: &'static str,
     pub len: usize,
+    pub gold_time: u64,
+    pub slowest_time: u64,
 }
 
 pub struct PathLog {
     paused: bool,
+    // paused by the player, loads don't unpause it
+    held: bool,
 	primed: bool,
 	recording: bool,
 	direct: bool,
@@ -35,17 +51,51 @@ pub struct PathLog {
     autoreset: bool,
 
     current_file: Option<String>,
-	recording_start: Option<time::Instant>,
-    pub latest_path: Uuid,
+    // last file that was loaded or saved, used to find its backups
+    last_file: Option<String>,
+    save_options: SaveOptions,
+    // paths whose nodes changed since the last update
+    changed_geometry: HashSet<Uuid>,
+    // clock micros when the current segment started
+	recording_start: Option<u64>,
+    clock: Clock,
+    // last recorded path that was kept, none in direct mode or if its collection rejected it
+    pub latest_path: Option<Uuid>,
     latest_time: u64,
 	pub recording_path: Path,
+    journal: Journal,
+    // stored with every new path
+    runner: String,
+    // stored with every path, unknown in headless runs
+    game_version: Option<GameVersion>,
+    // while a capture runs new ids come from this instead of being random, so a replay creates the same ones
+    id_seed: Option<u64>,
+    // runs from a session that crashed, waiting for the user to recover or discard them
+    recovered: Vec<RecoveredRun>,
+    // time and position of the last node of the recording path
+    last_sample: Option<(u64, Vec3)>,
+    sample_direction: Option<Vec3>,
+    // latest position that didn't become a node, added when a segment ends
+    skipped_sample: Option<Vec3>,
     pub active_collection: Option<Uuid>,
     pub filters: HashMap<Uuid, HighPassFilter>,
+    display_filter: DisplayFilter,
+    // paths that pass the display filter
+    shown_paths: HashSet<Uuid>,
 
     pub main_triggers: [Option<BoxCollider>; 2],
     pub checkpoint_triggers: Vec<BoxCollider>,
+    // time of the recording at each checkpoint it reached, the next one to split is at split_times.len()
+    split_times: Vec<u64>,
+    // published once per frame, see recording.rs
+    events: Vec<RecordingEvent>,
 
     paths: HashMap<Uuid, Path>,
+    ranking: PathRanking,
+    // path ids in the order they were recorded
+    history: Vec<Uuid>,
+    // false if a comparison file didn't store the order and some paths had no recording date
+    history_known: bool,
 
     path_collections: Vec<PathCollection>,
 	// pub direct_paths: PathCollection,
@@ -57,15 +107,35 @@ pub struct PathLog {
     pub selected_paths: HashMap<Uuid, Vec<Uuid>>,
 
     comparison: Comparison,
+    comparison_strategy: Box<dyn ComparisonStrategy>,
+    // last parameter of each strategy by name, so switching back doesn't reset it
+    comparison_parameters: HashMap<&'static str, u32>,
     // compared_paths: PathCollection,
-    compared_paths: Vec<(Uuid, usize)>,
-    ignored_paths: Vec<Vec<Uuid>>,
+    compared_paths: Vec<ComparedPath>,
 }
 
 impl PathLog {
-    pub fn init() -> PathLog {
-        let pathlog = PathLog {
+    pub fn init(services: &dyn Services) -> PathLog {
+        let mut pathlog = PathLog::new();
+        pathlog.game_version = Some(services.game_version());
+
+        if !std::fs::exists("Paths").expect("") && std::fs::create_dir("Paths").is_err() {
+            error!("Failed to create Paths directory!");
+            std::process::exit(1);
+        }
+
+        pathlog.recovered = journal::recover();
+        pathlog.journal = Journal::open();
+
+        info!("Initialized");
+
+        pathlog
+    }
+
+    pub fn new() -> PathLog {
+        PathLog {
             paused: false,
+            held: false,
             primed: false,
             recording: false,
             direct: false,
@@ -73,18 +143,37 @@ impl PathLog {
             autoreset: true,
 
             current_file: None,
+            last_file: None,
+            save_options: SaveOptions::default(),
+            changed_geometry: HashSet::new(),
 
             recording_start: None,
-            latest_path: Uuid::new_v4(),
+            clock: Clock::system(),
+            latest_path: None,
             latest_time: 0,
             recording_path: Path::new(),
+            journal: Journal::disabled(),
+            runner: String::new(),
+            game_version: None,
+            id_seed: None,
+            recovered: Vec::new(),
+            last_sample: None,
+            sample_direction: None,
+            skipped_sample: None,
             active_collection: None,
             filters: HashMap::new(),
+            display_filter: DisplayFilter::default(),
+            shown_paths: HashSet::new(),
 
             main_triggers: [None, None],
             checkpoint_triggers: Vec::new(),
+            split_times: Vec::new(),
+            events: Vec::new(),
 
             paths: HashMap::new(),
+            ranking: PathRanking::new(),
+            history: Vec::new(),
+            history_known: true,
             path_collections: Vec::new(),
 
             mute_paths: HashMap::new(),
@@ -93,25 +182,21 @@ impl PathLog {
             solo_collections: HashMap::new(),
             selected_paths: HashMap::new(),
 
-            comparison: Comparison { mode: ComparisonMode::All, len: 0 },
+            comparison: Comparison { mode: "All", len: 0, gold_time: 0, slowest_time: 0 },
+            comparison_strategy: Box::new(comparison::All),
+            comparison_parameters: HashMap::new(),
             // compared_paths: PathCollection::new("compared".to_string()),
             compared_paths: Vec::new(),
-            ignored_paths: Vec::new(),
-        };
-
-        if !std::fs::exists("Paths").expect("") && std::fs::create_dir("Paths").is_err() {
-            error!("Failed to create Paths directory!");
-            std::process::exit(1);
         }
-
-        info!("Initialized");
-
-        pathlog
     }
 
-	pub fn update(&mut self, player_pos: &[f32; 3], player_rot: &[f32; 3]) -> RenderUpdates {
+	pub fn update(&mut self, player_pos: &[f32; 3], player_rot: &[f32; 3], sampling: SamplingPolicy) -> RenderUpdates {
         let mut updates = RenderUpdates::new();
 
+        if !self.changed_geometry.is_empty() {
+            updates.paths = PathUpdates::Geometry(std::mem::take(&mut self.changed_geometry));
+        }
+
         let player_up = Mat3::from_euler(glam::EulerRot::XYZ, player_rot[0], player_rot[1], player_rot[2]) * Vec3::Y;
         let player_center = [
             player_pos[0] + player_up.x,
@@ -128,6 +213,7 @@ impl PathLog {
             if player_in_trigger[0] && !self.primed && self.autoreset {
                 self.reset();
                 self.primed = true;
+                self.events.push(RecordingEvent::Armed);
             }
             else if !player_in_trigger[0] && self.primed {
                 self.primed = false;
@@ -135,84 +221,182 @@ impl PathLog {
             }
 
             if player_in_trigger[1] && self.recording {
+                // the path should end exactly where it entered the trigger
+                if !self.paused { self.sample(player_center.into(), sampling, true); }
                 self.stop();
-                updates.paths = true;
+                updates.paths.or(PathUpdates::Styles);
+                if !self.changed_geometry.is_empty() {
+                    updates.paths.or(PathUpdates::Geometry(std::mem::take(&mut self.changed_geometry)));
+                }
             }
         }
 
         if !self.recording || self.paused { return updates; }
 
-        // TODO: checkpoint logic
+        // TODO: checkpoint logic. for now they only split the livesplit timer, in order
+        let next_checkpoint = self.split_times.len();
+        if let Some(checkpoint) = self.checkpoint_triggers.get_mut(next_checkpoint) {
+            if checkpoint.check_point_collision(player_center.into()) {
+                self.events.push(RecordingEvent::Split { checkpoint: next_checkpoint });
+                self.split_times.push(self.time());
+            }
+        }
 
-	    self.recording_path.add_node(player_center);
+        self.sample(player_center.into(), sampling, false);
 
         updates
     }
 
-    pub fn update_visible(&mut self) {
-        self.compared_paths.clear();
-        self.ignored_paths.clear();
+    fn sample(&mut self, position: Vec3, sampling: SamplingPolicy, force: bool) {
+        let now = self.clock.micros();
+
+        let take = force || match self.last_sample {
+            // first node of a segment
+            None => true,
+            Some((last_time, last_position)) => {
+                match sampling.mode {
+                    SamplingMode::EveryFrame => true,
+                    SamplingMode::Interval => now.saturating_sub(last_time) as f32 / 1000. >= sampling.interval,
+                    SamplingMode::Distance => position.distance(last_position) >= sampling.distance,
+                    SamplingMode::Adaptive => {
+                        let step = position - last_position;
+                        if step.length() >= sampling.distance { true }
+                        else if step.length() < MIN_ADAPTIVE_STEP { false }
+                        else if let Some(direction) = self.sample_direction {
+                            direction.angle_between(step).to_degrees() >= sampling.angle
+                        }
+                        else { true }
+                    }
+                }
+            }
+        };
 
-        let mut all_compared : Vec<Uuid> = Vec::new();
+        if !take {
+            self.skipped_sample = Some(position);
+            return;
+        }
 
-        for path in self.paths.values() {
-            if let Some(pos) = all_compared.iter().position(|id| path.time() < self.path(&id).unwrap().time()) {
-                all_compared.insert(pos, path.id());
-            }
-            else {
-                all_compared.push(path.id());
+        if let Some((_, last_position)) = self.last_sample {
+            let step = position - last_position;
+            if step.length() >= MIN_ADAPTIVE_STEP { self.sample_direction = Some(step.normalize()); }
+        }
+
+        self.recording_path.add_node(position.to_array());
+        if let Some(segment_start) = self.recording_start {
+            self.journal.node(self.clock.millis_since(segment_start), position.to_array());
+        }
+        self.last_sample = Some((now, position));
+        self.skipped_sample = None;
+    }
+
+    // keeps the last known position so segments don't end early, then starts sampling the next segment from scratch
+    fn end_sampling(&mut self) {
+        if let Some(position) = self.skipped_sample.take() {
+            self.recording_path.add_node(position.to_array());
+            if let Some(segment_start) = self.recording_start {
+                self.journal.node(self.clock.millis_since(segment_start), position.to_array());
             }
         }
+        self.clear_sampling();
+    }
+
+    fn clear_sampling(&mut self) {
+        self.last_sample = None;
+        self.sample_direction = None;
+        self.skipped_sample = None;
+    }
 
-        self.comparison.len = all_compared.len();
+    pub fn update_visible(&mut self) {
+        self.comparison.len = self.ranking.len();
+        self.comparison.gold_time = self.ranking.fastest_time().unwrap_or(0);
+        self.comparison.slowest_time = self.ranking.slowest_time().unwrap_or(0);
+
+        let mut shown_paths = HashSet::new();
 
-        for c in 0..self.path_collections.len() {
-            let collection = &self.path_collections[c];
+        for collection in &self.path_collections {
+            // collections are sorted by time, so the first n matches are the fastest
+            let matching = collection.paths().iter()
+                .filter(|path_id| self.display_filter.matches(self.paths.get(path_id).unwrap()))
+                .take(self.display_filter.top_n.unwrap_or(usize::MAX));
+            shown_paths.extend(matching);
+        }
 
-            self.ignored_paths.push(Vec::new());
+        self.shown_paths = shown_paths;
 
-            if collection.paths().is_empty() { continue; }
+        let no_solo_collections = self.solo_collections.values().all(|s| !s);
+        let no_solo_paths = self.solo_paths.values().all(|s| !s);
 
-            let mut collection_visible = self.solo_collections.values().all(|s| !s);
+        let mut visible_collections = Vec::new();
+        let mut visible = HashSet::new();
+
+        for collection in &self.path_collections {
+            let mut collection_visible = no_solo_collections;
             if *self.solo_collections.get(&collection.id()).unwrap() { collection_visible = true; }
             if *self.mute_collections.get(&collection.id()).unwrap() { collection_visible = false; }
 
-            // if visible { continue; }
+            if !collection_visible { continue; }
 
-            if matches!(self.comparison.mode, ComparisonMode::Gold) {
-                let position =  all_compared.iter().position(|id| *id == collection.paths()[0]).unwrap();
-                self.compared_paths.push((collection.paths()[0], position));
-                continue;
-            }
+            let visible_paths = collection.paths().iter().filter(|path_id| {
+                let mut path_visible = no_solo_paths;
+                if *self.solo_paths.get(path_id).unwrap() { path_visible = true; }
+                if *self.mute_paths.get(path_id).unwrap() { path_visible = false; }
+                path_visible && self.is_shown(path_id)
+            }).cloned().collect::<Vec<Uuid>>();
 
-            if matches!(self.comparison.mode, ComparisonMode::Median) {
-                let position =  all_compared.iter().position(|id| *id == collection.paths()[collection.paths().len() / 2]).unwrap();
-                self.compared_paths.push((collection.paths()[collection.paths().len() / 2], position));
-            }
+            visible.extend(visible_paths.iter().cloned());
+            visible_collections.push(VisibleCollection { collection, paths: visible_paths });
+        }
 
-            for i in 0..collection.paths().len() {
-                let path_id = collection.paths()[i];
-                let mut path_visible = self.solo_paths.values().all(|s| !s);
-                if *self.solo_paths.get(&path_id).unwrap() { path_visible = true; }
-                if *self.mute_paths.get(&path_id).unwrap() { path_visible = false; }
+        let input = ComparisonInput {
+            paths: &self.paths,
+            ranking: &self.ranking,
+            history: &self.history,
+            collections: visible_collections,
+            visible,
+        };
 
-                if !collection_visible || !path_visible { continue; }
+        self.compared_paths = self.comparison_strategy.compare(&input);
+    }
 
-                if matches!(self.comparison.mode, ComparisonMode::Median) {
-                    if self.compared_paths.iter().find(|x| x.0 == path_id).is_some() { continue; }
-                    self.ignored_paths[c].push(path_id);
-                    continue;
-                }
+    // adds a finished path to the given collection without updating the visible paths.
+    // paths the collection's filter rejects aren't kept anywhere, not even in the ranking or history
+    pub fn insert_path(&mut self, path: Path, collection_id: Uuid) -> Result<bool, Error> {
+        let path_id = path.id();
+
+        let Some(position) = self.insert_position(&path, collection_id)? else {
+            self.events.push(RecordingEvent::PathRejected { path_id, collection_id });
+            return Ok(false);
+        };
 
-                let position = all_compared.iter().position(|id| *id == path_id).unwrap();
-                self.compared_paths.push((path_id, position));
+        if let Some(collection) = self.path_collections.iter_mut().find(|c| c.id() == collection_id) {
+            collection.insert(position, path_id);
+        }
+
+        self.mute_paths.entry(path_id).or_insert(false);
+        self.solo_paths.entry(path_id).or_insert(false);
+        self.ranking.insert(path.time(), path_id);
+        self.history.push(path_id);
+        self.paths.insert(path_id, path);
+        self.events.push(RecordingEvent::PathAccepted { path_id, collection_id });
+        Ok(true)
+    }
+
+    // saves to the file that was last saved or loaded
+    pub fn autosave(&mut self, services: &mut dyn Services) {
+        if !self.autosave { return; }
+
+        if let Some(file_path) = self.current_file.clone() {
+            if let Err(e) = self.save_comparison(file_path, services) {
+                error!("{e}");
             }
         }
     }
 
-    fn add_path_to_collection(&mut self, path_id: Uuid, collection_id: Uuid) {
-        let collection = self.path_collections.iter_mut().find(|c| c.id() == collection_id).unwrap();
-        let new_path = self.paths.get(&path_id).unwrap();
+    // where the path goes in the collection, none if the collection's filter doesn't take it
+    fn insert_position(&self, new_path: &Path, collection_id: Uuid) -> Result<Option<usize>, Error> {
+        let Some(collection) = self.path_collections.iter().find(|c| c.id() == collection_id) else {
+            return Err(Error::UnknownCollection { id: collection_id.to_string() });
+        };
         let mut position = collection.paths().len();
 
         if !collection.paths().is_empty() {
@@ -222,7 +406,7 @@ impl PathLog {
                     //     collection.insert(0, path_id);
                     // }
                     if self.paths.get(&collection.paths()[0]).unwrap().time() < new_path.time() {
-                        return;
+                        return Ok(None);
                     }
                     position = 0;
                 }
@@ -233,35 +417,24 @@ impl PathLog {
                             position = i;
                             break;
                         }
-                        if self.paths.get(&collection.paths()[i]).unwrap().id() == *id { return; }
+                        if self.paths.get(&collection.paths()[i]).unwrap().id() == *id { return Ok(None); }
                     }
                 }
                 None => {
-                    for i in 0..collection.paths().len() {
-                        if self.paths.get(&collection.paths()[i]).unwrap().time() < new_path.time() { continue; }
-                        // collection.insert(i, path_id);
-                        position = i;
-                        break;
-                    }
-                    // collection.push(path_id);
+                    // collections are sorted by time
+                    let paths = &self.paths;
+                    position = collection.paths().partition_point(|id| paths.get(id).unwrap().time() < new_path.time());
                 }
             }
         }
 
-        self.mute_paths.entry(path_id).or_insert(false);
-        self.solo_paths.entry(path_id).or_insert(false);
-
-        collection.insert(position, path_id);
+        Ok(Some(position))
     }
 
-    pub fn compared_paths(&self) -> &Vec<(Uuid, usize)> {
+    pub fn compared_paths(&self) -> &Vec<ComparedPath> {
         &self.compared_paths
     }
 
-    pub fn ignored_paths(&self) -> &Vec<Vec<Uuid>> {
-        &self.ignored_paths
-    }
-
     pub fn comparison(&self) -> Comparison {
         self.comparison
     }
@@ -273,7 +446,18 @@ impl PathLog {
 	pub fn start(&mut self) {
         if self.recording { return; }
         self.recording = true;
-        self.recording_start = Some(time::Instant::now());
+        self.recording_start = Some(self.clock.micros());
+        self.held = false;
+        self.clear_sampling();
+
+        let meta = self.recording_path.meta_mut();
+        meta.recorded_at = date::unix_time();
+        meta.game_version = self.game_version;
+        meta.runner = self.runner.clone();
+        self.journal.start(meta.recorded_at);
+        self.split_times.clear();
+        self.events.push(RecordingEvent::Started);
+
         info!("Recording started");
     }
 
@@ -281,8 +465,12 @@ impl PathLog {
         if !self.recording { return; }
         self.recording = false;
         self.recording_path.clear_all();
+        self.clear_sampling();
         // self.recording_path.set_time(0);
         self.recording_start = None;
+        self.journal.reset();
+        self.split_times.clear();
+        self.events.push(RecordingEvent::Reset);
         info!("Recording reset");
     }
 
@@ -293,8 +481,12 @@ impl PathLog {
     pub fn pause(&mut self) {
         if !self.recording || self.paused { return; }
 
-        let segment_time = self.recording_start.unwrap().elapsed().as_millis() as u64;
+        self.end_sampling();
+
+        let segment_time = self.clock.millis_since(self.recording_start.unwrap());
         self.recording_path.end_segment(segment_time);
+        self.journal.end_segment(segment_time);
+        self.events.push(RecordingEvent::Paused { segment_time });
 
         self.paused = true;
         info!("Recording paused");
@@ -303,19 +495,49 @@ impl PathLog {
     pub fn unpause(&mut self) {
         if !self.recording || !self.paused { return; }
 
-        self.recording_start = Some(time::Instant::now());
+        self.recording_start = Some(self.clock.micros());
+        self.events.push(RecordingEvent::Unpaused);
 
         self.paused = false;
         info!("Recording unpaused");
     }
 
+    // holds the recording until toggled again
     pub fn toggle_pause(&mut self) {
-        if self.paused {
-            self.unpause();
+        if !self.recording { return; }
+
+        self.held = !self.held;
+
+        if self.held {
+            self.pause();
         }
-        else {
+    }
+
+    // once per frame
+    pub fn set_loading(&mut self, loading: bool) {
+        if loading || self.held {
             self.pause();
         }
+        else {
+            self.unpause();
+        }
+    }
+
+    // activates the collection after (or before) the active one, the first (or last) if none is active
+    pub fn cycle_active_collection(&mut self, forward: bool) {
+        let len = self.path_collections.len();
+        if len == 0 { return; }
+
+        let current = self.active_collection.and_then(|id| self.path_collections.iter().position(|c| c.id() == id));
+
+        let next = match (current, forward) {
+            (Some(i), true) => (i + 1) % len,
+            (Some(i), false) => (i + len - 1) % len,
+            (None, true) => 0,
+            (None, false) => len - 1,
+        };
+
+        self.active_collection = Some(self.path_collections[next].id());
     }
 
 	pub fn stop(&mut self) {
@@ -323,36 +545,33 @@ impl PathLog {
 
         self.recording = false;
 
-        let time_recorded = self.recording_start.unwrap().elapsed().as_millis() as u64;
+        self.end_sampling();
+
+        let time_recorded = self.clock.millis_since(self.recording_start.unwrap());
         self.recording_path.end_path(time_recorded);
+        self.journal.end_path(time_recorded);
         self.latest_time = self.recording_path.time();
+        self.events.push(RecordingEvent::Stopped { path_id: self.recording_path.id(), time: self.latest_time });
+
+        self.latest_path = None;
 
         if self.direct {
             // self.direct_paths.add(self.recording_path.clone(), None);
         }
-        else {
-            let mut empty = true;
-
-            for i in 0..self.path_collections.len() {
-                empty &= self.path_collections[i].paths().is_empty();
-
-                let id = self.path_collections[i].id();
-                if self.active_collection == Some(id) {
-                    self.paths.insert(self.recording_path.id(), self.recording_path.clone());
-                    self.add_path_to_collection(self.recording_path.id(), id);
-                }
-            }
-
-            if self.autosave && !empty {
-                if let Some(file_path) = &self.current_file {
-                    self.save_comparison(file_path.clone());
-                }
+        else if let Some(collection_id) = self.active_collection {
+            match self.insert_path(self.recording_path.clone(), collection_id) {
+                Ok(true) => self.latest_path = Some(self.recording_path.id()),
+                Ok(false) => {}
+                Err(e) => error!("{e}"),
             }
         }
 
-        self.latest_path = self.recording_path.id();
+        // so recovery doesn't bring back a run that wasn't kept
+        if self.latest_path.is_none() {
+            self.journal.discard();
+        }
 
-        self.recording_path = Path::new();
+        self.recording_path = Path::with_id(self.new_id());
         self.recording_start = None;
 
         self.update_visible();
@@ -364,14 +583,45 @@ impl PathLog {
         if let Some(rec_start) = self.recording_start {
             let mut current_time : u64 = 0;
             if !self.paused {
-                current_time = rec_start.elapsed().as_millis() as u64;
+                current_time = self.clock.millis_since(rec_start);
             }
             current_time + self.recording_path.time()
         } else { self.latest_time }
     }
 
+    pub fn clock(&self) -> &Clock {
+        &self.clock
+    }
+
+    pub fn clock_mut(&mut self) -> &mut Clock {
+        &mut self.clock
+    }
+
+    pub fn set_clock(&mut self, clock: Clock) {
+        self.clock = clock;
+    }
+
+    pub fn direct_mode(&self) -> bool {
+        self.direct
+    }
+
+    pub fn autoreset(&self) -> bool {
+        self.autoreset
+    }
+
     pub fn set_direct_mode(&mut self, mode: bool) {
+        if mode == self.direct { return; }
         self.direct = mode;
+
+        // direct mode runs aren't kept, so there's nothing to recover
+        if mode {
+            if self.recording { self.journal.reset(); }
+            self.journal.set_enabled(false);
+        }
+        else {
+            self.journal.set_enabled(true);
+            if self.recording { self.journal.write_path(&self.recording_path, false); }
+        }
     }
 
     pub fn set_autosave(&mut self, mode: bool) {
@@ -382,8 +632,165 @@ impl PathLog {
         self.autoreset = mode;
     }
 
-    pub fn set_comparison_mode(&mut self, mode: ComparisonMode) {
-        self.comparison.mode = mode;
+    pub fn display_filter(&self) -> &DisplayFilter {
+        &self.display_filter
+    }
+
+    pub fn set_display_filter(&mut self, filter: DisplayFilter) {
+        self.display_filter = filter;
+        self.update_visible();
+    }
+
+    pub fn is_shown(&self, path_id: &Uuid) -> bool {
+        !self.display_filter.is_active() || self.shown_paths.contains(path_id)
+    }
+
+    pub fn path_count(&self) -> usize {
+        self.paths.len()
+    }
+
+    pub fn shown_count(&self) -> usize {
+        if self.display_filter.is_active() { self.shown_paths.len() } else { self.paths.len() }
+    }
+
+    pub fn all_tags(&self) -> Vec<String> {
+        let mut tags : Vec<String> = self.paths.values().flat_map(|p| p.meta().tags.iter().cloned()).collect();
+        tags.sort();
+        tags.dedup();
+        tags
+    }
+
+    pub fn latest_time(&self) -> u64 {
+        self.latest_time
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.recording
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    // of one collection or all paths
+    pub fn fastest_times(&self, collection_id: Option<Uuid>, count: usize) -> Vec<u64> {
+        match collection_id.and_then(|id| self.get_collection(id)) {
+            Some(collection) => {
+                let mut times : Vec<u64> = collection.paths().iter().filter_map(|id| self.paths.get(id)).map(|p| p.time()).collect();
+                times.sort();
+                times.truncate(count);
+                times
+            }
+            None => self.ranking.iter().take(count).map(|(time, _)| *time).collect(),
+        }
+    }
+
+    pub fn fastest_path(&self, collection_id: Option<Uuid>) -> Option<&Path> {
+        match collection_id.and_then(|id| self.get_collection(id)) {
+            Some(collection) => collection.paths().iter().filter_map(|id| self.paths.get(id)).min_by_key(|p| p.time()),
+            None => self.ranking.iter().next().and_then(|(_, id)| self.paths.get(id)),
+        }
+    }
+
+    pub fn split_times(&self) -> &[u64] {
+        &self.split_times
+    }
+
+    // newest first
+    pub fn recent_paths(&self, count: usize) -> Vec<&Path> {
+        self.history.iter().rev().take(count).filter_map(|id| self.paths.get(id)).collect()
+    }
+
+    pub fn take_events(&mut self) -> Vec<RecordingEvent> {
+        std::mem::take(&mut self.events)
+    }
+
+    pub fn set_game_version(&mut self, game_version: Option<GameVersion>) {
+        self.game_version = game_version;
+    }
+
+    pub fn seed_ids(&mut self, seed: Option<u64>) {
+        self.id_seed = seed;
+    }
+
+    // ids for paths, collections and triggers
+    fn new_id(&mut self) -> Uuid {
+        let Some(state) = &mut self.id_seed else { return Uuid::new_v4(); };
+
+        // splitmix64
+        let mut next = || {
+            *state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+            let mut z = *state;
+            z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+            z ^ (z >> 31)
+        };
+
+        uuid::Builder::from_random_bytes(((next() as u128) << 64 | next() as u128).to_le_bytes()).into_uuid()
+    }
+
+    pub fn set_runner(&mut self, runner: &str) {
+        if self.runner != runner { self.runner = runner.to_string(); }
+    }
+
+    pub fn set_path_notes(&mut self, path_id: Uuid, notes: String) {
+        if let Some(path) = self.paths.get_mut(&path_id) {
+            path.meta_mut().notes = notes;
+        }
+    }
+
+    pub fn add_path_tag(&mut self, path_id: Uuid, tag: String) {
+        let tag = tag.trim().to_string();
+        if tag.is_empty() { return; }
+
+        if let Some(path) = self.paths.get_mut(&path_id) {
+            if !path.meta().tags.contains(&tag) { path.meta_mut().tags.push(tag); }
+        }
+
+        if self.display_filter.is_active() { self.update_visible(); }
+    }
+
+    pub fn remove_path_tag(&mut self, path_id: Uuid, tag: &str) {
+        if let Some(path) = self.paths.get_mut(&path_id) {
+            path.meta_mut().tags.retain(|t| t != tag);
+        }
+
+        if self.display_filter.is_active() { self.update_visible(); }
+    }
+
+    pub fn set_save_options(&mut self, options: SaveOptions) {
+        self.save_options = options;
+    }
+
+    pub fn compact(&mut self, tolerance: f32) -> SimplifyReport {
+        let (report, changed) = simplify_paths(&mut self.paths, tolerance, self.save_options.compress);
+        self.changed_geometry.extend(changed);
+        report
+    }
+
+    pub fn set_comparison_strategy(&mut self, mut strategy: Box<dyn ComparisonStrategy>) {
+        if let Some(parameter) = self.comparison_strategy.parameter() {
+            self.comparison_parameters.insert(self.comparison_strategy.name(), parameter.value);
+        }
+        if let Some(value) = self.comparison_parameters.get(strategy.name()) {
+            strategy.set_parameter(*value);
+        }
+
+        self.comparison.mode = strategy.name();
+        self.comparison_strategy = strategy;
+    }
+
+    // whether history based modes (Latest N, PB Progression) show the real recording order
+    pub fn history_known(&self) -> bool {
+        self.history_known
+    }
+
+    pub fn comparison_strategy(&self) -> &dyn ComparisonStrategy {
+        self.comparison_strategy.as_ref()
+    }
+
+    pub fn set_comparison_parameter(&mut self, value: u32) {
+        self.comparison_strategy.set_parameter(value);
     }
 
 	// pub fn insert(&mut self, new_path: &Path, collection_id: Uuid) {
@@ -422,12 +829,15 @@ impl PathLog {
 
         self.mute_paths.remove(&path_id);
         self.solo_paths.remove(&path_id);
-        self.paths.remove(&path_id);
+        if let Some(path) = self.paths.remove(&path_id) {
+            self.ranking.remove(path.time(), path_id);
+        }
+        self.history.retain(|id| *id != path_id);
         self.update_visible();
     }
 
     pub fn create_collection(&mut self) {
-        let new_collection = PathCollection::new(DEFAULT_COLLECTION_NAME.to_string());
+        let new_collection = PathCollection::with_id(self.new_id(), DEFAULT_COLLECTION_NAME.to_string());
 
         if self.path_collections.is_empty() {
             self.active_collection = Some(new_collection.id());
@@ -469,9 +879,14 @@ impl PathLog {
             for path_id in self.path_collections[index].paths() {
                 self.mute_paths.remove(&path_id);
                 self.solo_paths.remove(&path_id);
-                self.paths.remove(path_id);
+                if let Some(path) = self.paths.remove(path_id) {
+                    self.ranking.remove(path.time(), *path_id);
+                }
             }
 
+            let paths = &self.paths;
+            self.history.retain(|id| paths.contains_key(id));
+
             if self.active_collection == Some(collection_id) {
                 self.active_collection = None;
             }
@@ -492,12 +907,37 @@ impl PathLog {
         empty
     }
 
-    pub fn load_comparison(&mut self, file_path: String) -> Result<(), Error> {
-        let data = CompFile::from_file(file_path)?;
-        self.main_triggers = data.get_triggers();
-        self.paths = data.get_paths();
-        self.path_collections = data.get_collections();
+    pub fn load_comparison(&mut self, file_path: String, services: &mut dyn Services) -> Result<(), Error> {
+        let data = CompFile::from_bytes(services.read_file(std::path::Path::new(&file_path))?)?;
         self.current_file = None;
+        self.last_file = Some(file_path);
+        // unsaved runs of the previous comparison don't belong to this one
+        self.clear_journal();
+        self.set_comparison(data.get_triggers(), data.get_paths(), data.get_collections(), data.recording_order());
+        Ok(())
+    }
+
+    // replaces everything with the given comparison, nothing is active, muted or selected afterwards
+    pub fn set_comparison(&mut self, main_triggers: [Option<BoxCollider>; 2], paths: HashMap<Uuid, Path>, collections: Vec<PathCollection>, order: Option<Vec<Uuid>>) {
+        self.main_triggers = main_triggers;
+        self.paths = paths;
+        self.path_collections = collections;
+        self.ranking = PathRanking::from_paths(&self.paths);
+
+        if let Some(order) = order {
+            self.history = order;
+            self.history_known = true;
+        }
+        else {
+            // paths from files before 0.8 have no recording date, those go first ordered by time.
+            // that's only a guess, so modes that depend on the order get marked in the ui
+            self.history = self.paths.keys().cloned().collect();
+            self.history.sort_by_key(|id| {
+                let path = self.paths.get(id).unwrap();
+                (path.meta().recorded_at, path.time())
+            });
+            self.history_known = self.paths.values().all(|path| path.meta().recorded_at != 0);
+        }
         self.active_collection = None;
 
         self.mute_collections.clear();
@@ -518,12 +958,19 @@ impl PathLog {
         }
 
         self.update_visible();
-        Ok(())
     }
 
-    pub fn save_comparison(&mut self, file_path: String) {
-        if self.main_triggers[0].is_none() { return; };
-        if self.main_triggers[1].is_none() { return; };
+    pub fn save_comparison(&mut self, file_path: String, services: &mut dyn Services) -> Result<(), Error> {
+        if self.main_triggers[0].is_none() { return Ok(()); };
+        if self.main_triggers[1].is_none() { return Ok(()); };
+
+        // only the saved copy gets simplified, the loaded paths stay as recorded
+        let mut paths = self.paths.clone();
+
+        if let Some(tolerance) = self.save_options.simplification {
+            let (report, _) = simplify_paths(&mut paths, tolerance, self.save_options.compress);
+            if report.nodes_removed() > 0 { info!("{report}"); }
+        }
 
         let data = CompFile::new(
             [
@@ -531,15 +978,73 @@ impl PathLog {
                 self.main_triggers[1].as_ref().unwrap().clone()
             ],
 
-            self.paths.clone(),
+            paths,
             self.path_collections.clone(),
+            self.history_known.then(|| self.history.clone()),
         );
 
-        if let Err(e) = data.to_file(file_path.clone()) {
-            error!("{e}");
+        if let Err(e) = services.back_up(std::path::Path::new(&file_path), self.save_options.backups) {
+            error!("Failed to back up {file_path}: {e}");
         }
 
-        self.current_file = Some(file_path);
+        // the journal is only cleared once the runs are really on disk
+        services.write_file(std::path::Path::new(&file_path), &data.to_bytes(self.save_options.compress)?)?;
+
+        self.current_file = Some(file_path.clone());
+        self.last_file = Some(file_path);
+        self.clear_journal();
+        Ok(())
+    }
+
+    fn clear_journal(&mut self) {
+        let recording = if self.recording && !self.direct { Some(&self.recording_path) } else { None };
+        self.journal.clear(recording);
+    }
+
+    pub fn recovered_runs(&self) -> &Vec<RecoveredRun> {
+        &self.recovered
+    }
+
+    // adds the runs of the crashed session to a collection, or a new one if there is none
+    // (or the chosen one was deleted in the meantime)
+    pub fn recover_runs(&mut self, collection_id: Option<Uuid>) {
+        let collection_id = match collection_id.filter(|id| self.path_collections.iter().any(|c| c.id() == *id)) {
+            Some(id) => id,
+            None => {
+                self.create_collection();
+                self.path_collections.last().unwrap().id()
+            }
+        };
+
+        for run in std::mem::take(&mut self.recovered) {
+            // they aren't saved anywhere yet
+            self.journal.write_path(&run.path, true);
+            if let Err(e) = self.insert_path(run.path, collection_id) {
+                error!("{e}");
+            }
+        }
+
+        journal::discard_recovered();
+        self.update_visible();
+        info!("Recovered runs");
+    }
+
+    pub fn discard_recovered_runs(&mut self) {
+        self.recovered.clear();
+        journal::discard_recovered();
+    }
+
+    pub fn last_file(&self) -> Option<&String> {
+        self.last_file.as_ref()
+    }
+
+    // loads an old version but keeps treating the original as the open file
+    pub fn restore_backup(&mut self, backup_path: String, services: &mut dyn Services) -> Result<(), Error> {
+        let original = self.last_file.clone();
+        self.load_comparison(backup_path, services)?;
+        self.current_file = original.clone();
+        self.last_file = original;
+        Ok(())
     }
 
     pub fn create_trigger(&mut self, index: usize, position: [f32; 3], rotation: [f32; 3], size: [f32; 3]) {
@@ -553,10 +1058,11 @@ impl PathLog {
         self.current_file = None;
 
         if index < 2 {
-            self.main_triggers[index] = Some(BoxCollider::new(player_center, rotation, size));
+            self.main_triggers[index] = Some(BoxCollider::with_id(self.new_id(), player_center, rotation, size));
         }
         else {
-            self.checkpoint_triggers.push(BoxCollider::new(player_center, rotation, size));
+            let trigger = BoxCollider::with_id(self.new_id(), player_center, rotation, size);
+            self.checkpoint_triggers.push(trigger);
         }
     }
 
@@ -567,4 +1073,9 @@ impl PathLog {
         self.main_triggers = [None, None];
         self.current_file = None;
     }
-}
\ No newline at end of file
+}
+impl Default for PathLog {
+    fn default() -> Self {
+        PathLog::new()
+    }
+}
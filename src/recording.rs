@@ -0,0 +1,77 @@
+use serde::Serialize;
+use uuid::Uuid;
+
+// what happens during a recording. PathLog queues these and they get published once per frame,
+// after the path log is unlocked, so subscribers are free to lock it again
+
+#[derive(Clone, Debug, PartialEq)]
+#[derive(Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum RecordingEvent {
+    // standing in the start trigger
+    Armed,
+    Started,
+    Paused {
+        segment_time: u64,
+    },
+    Unpaused,
+    Split {
+        checkpoint: usize,
+    },
+    Reset,
+    // end trigger reached or stopped by hand
+    Stopped {
+        path_id: Uuid,
+        time: u64,
+    },
+    PathAccepted {
+        path_id: Uuid,
+        collection_id: Uuid,
+    },
+    // the collection's filter only takes faster paths
+    PathRejected {
+        path_id: Uuid,
+        collection_id: Uuid,
+    },
+}
+
+pub type Subscriber = Box<dyn FnMut(&RecordingEvent) + Send>;
+
+#[derive(Clone, Copy, PartialEq)]
+pub struct SubscriptionId(u32);
+
+pub struct RecordingBus {
+    next_id: u32,
+    subscribers: Vec<(SubscriptionId, Subscriber)>,
+}
+
+impl RecordingBus {
+    pub fn new() -> RecordingBus {
+        RecordingBus { next_id: 0, subscribers: Vec::new() }
+    }
+
+    pub fn subscribe(&mut self, subscriber: Subscriber) -> SubscriptionId {
+        let id = SubscriptionId(self.next_id);
+        self.next_id += 1;
+        self.subscribers.push((id, subscriber));
+        id
+    }
+
+    pub fn unsubscribe(&mut self, id: SubscriptionId) {
+        self.subscribers.retain(|(s, _)| *s != id);
+    }
+
+    pub fn publish(&mut self, events: &[RecordingEvent]) {
+        for event in events {
+            for (_, subscriber) in &mut self.subscribers {
+                subscriber(event);
+            }
+        }
+    }
+}
+
+impl Default for RecordingBus {
+    fn default() -> Self {
+        RecordingBus::new()
+    }
+}
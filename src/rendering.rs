@@ -1,7 +1,12 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
+use once_cell::sync::Lazy;
+use serde::{Serialize, Deserialize};
 use uuid::Uuid;
 
-use crate::{CONFIG_STATE, PATHLOG, RENDER_UPDATES, UI_STATE};
-use crate::pathlog::ComparisonMode;
+use crate::config::GradientMode;
+use crate::culling::{self, CullSettings, Frustum, PathChunk};
+use crate::snapshot::RenderSnapshot;
 use crate::ui::ShapeType;
 use crate::pathdata::Path;
 use pintar::Pintar;
@@ -12,43 +17,136 @@ pub static TRIGGERS_GROUP : &str = "triggers";
 pub static TELEPORTS_GROUP : &str = "teleports";
 pub static SHAPES_GROUP : &str = "custom_shapes";
 
-#[derive(Clone, Copy)]
+// paths are spread over several vertex groups so a change only re-uploads the group the path is in
+pub const PATH_BUCKET_COUNT : usize = 16;
+
+#[derive(Clone, Copy, PartialEq)]
+struct PathStyle {
+    color: [f32; 4],
+    thickness: f32,
+}
+
+#[derive(Clone, PartialEq)]
+struct DrawnPath {
+    style: PathStyle,
+    // node stride per chunk, 0 for culled chunks
+    strides: Vec<u8>,
+}
+
+struct PathCache {
+    // paths to draw in draw order. only recomputed on path updates
+    styles: Vec<(Uuid, PathStyle)>,
+    // what is currently uploaded to the path groups
+    drawn: HashMap<Uuid, DrawnPath>,
+    chunks: HashMap<Uuid, Vec<PathChunk>>,
+    display_tolerance: f32,
+    // snapshot the styles and chunks are from
+    version: u64,
+}
+
+static PATH_CACHE: Lazy<Mutex<PathCache>> = Lazy::new(|| Mutex::new(PathCache {
+    styles: Vec::new(),
+    drawn: HashMap::new(),
+    chunks: HashMap::new(),
+    display_tolerance: 0.,
+    version: 0,
+}));
+
+#[derive(Clone, PartialEq)]
+#[derive(Serialize, Deserialize)]
+pub enum PathUpdates {
+    None,
+    // colors or visibility may have changed, only paths that look different get redrawn
+    Styles,
+    // the nodes of these paths changed
+    Geometry(HashSet<Uuid>),
+    All,
+}
+
+impl PathUpdates {
+    pub fn is_none(&self) -> bool {
+        matches!(self, PathUpdates::None)
+    }
+
+    pub fn or(&mut self, other: PathUpdates) {
+        match (&mut *self, other) {
+            (PathUpdates::All, _) => {}
+            (_, PathUpdates::All) => *self = PathUpdates::All,
+            (PathUpdates::Geometry(ids), PathUpdates::Geometry(other_ids)) => ids.extend(other_ids),
+            (_, PathUpdates::Geometry(other_ids)) => *self = PathUpdates::Geometry(other_ids),
+            (PathUpdates::None, PathUpdates::Styles) => *self = PathUpdates::Styles,
+            _ => {}
+        }
+    }
+}
+
+#[derive(Clone)]
+#[derive(Serialize, Deserialize)]
 pub struct RenderUpdates {
-    pub paths: bool,
+    pub paths: PathUpdates,
     pub triggers: bool,
     pub teleports: bool,
     pub shapes: bool,
+    // colors, gradient, culling or display tolerance in the config
+    #[serde(default)]
+    pub settings: bool,
 }
 
 impl RenderUpdates {
     pub fn new() -> Self {
-        RenderUpdates { paths: false, triggers: false, teleports: false, shapes: false }
+        RenderUpdates { paths: PathUpdates::None, triggers: false, teleports: false, shapes: false, settings: false }
     }
 
     pub fn paths() -> Self {
-        RenderUpdates { paths: true, triggers: false, teleports: false, shapes: false }
+        RenderUpdates { paths: PathUpdates::Styles, triggers: false, teleports: false, shapes: false, settings: false }
+    }
+
+    pub fn path_geometry(path_ids: impl IntoIterator<Item = Uuid>) -> Self {
+        RenderUpdates { paths: PathUpdates::Geometry(path_ids.into_iter().collect()), triggers: false, teleports: false, shapes: false, settings: false }
+    }
+
+    pub fn all_paths() -> Self {
+        RenderUpdates { paths: PathUpdates::All, triggers: false, teleports: false, shapes: false, settings: false }
     }
 
     pub fn triggers() -> Self {
-        RenderUpdates { paths: false, triggers: true, teleports: false, shapes: false }
+        RenderUpdates { paths: PathUpdates::None, triggers: true, teleports: false, shapes: false, settings: false }
     }
 
     pub fn teleports() -> Self {
-        RenderUpdates { paths: false, triggers: false, teleports: true, shapes: false }
+        RenderUpdates { paths: PathUpdates::None, triggers: false, teleports: true, shapes: false, settings: false }
     }
 
     pub fn shapes() -> Self {
-        RenderUpdates { paths: false, triggers: false, teleports: false, shapes: true }
+        RenderUpdates { paths: PathUpdates::None, triggers: false, teleports: false, shapes: true, settings: false }
+    }
+
+    pub fn settings() -> Self {
+        RenderUpdates { paths: PathUpdates::None, triggers: false, teleports: false, shapes: false, settings: true }
+    }
+
+    // everything is new on the first frame
+    pub fn all() -> Self {
+        RenderUpdates { paths: PathUpdates::All, triggers: true, teleports: true, shapes: true, settings: true }
     }
 
     pub fn or(&mut self, other: RenderUpdates) {
-        self.paths |= other.paths;
+        self.paths.or(other.paths);
         self.triggers |= other.triggers;
         self.teleports |= other.teleports;
         self.shapes |= other.shapes;
+        self.settings |= other.settings;
     }
 }
 
+pub fn path_bucket(path_id: &Uuid) -> usize {
+    (path_id.as_u128() % PATH_BUCKET_COUNT as u128) as usize
+}
+
+pub fn path_group(bucket: usize) -> String {
+    format!("{PATHS_GROUP}_{bucket}")
+}
+
 pub fn render_path(pintar: &mut Pintar, vertex_group: String, path: &Path, color: [f32; 4], thickness: f32) {
     for segment in path.segments() {
         if segment.len() < 2 { continue; }
@@ -63,43 +161,30 @@ pub fn render_path(pintar: &mut Pintar, vertex_group: String, path: &Path, color
 //     }
 // }
 
-pub fn render_all_paths(pintar: &mut Pintar) {
-    if !RENDER_UPDATES.lock().unwrap().paths { return; }
-
-    RENDER_UPDATES.lock().unwrap().paths = false;
-
-    pintar.clear_vertex_group(PATHS_GROUP.to_string());
-
-    let pathlog = PATHLOG.lock().unwrap();
-
-    let compared_paths = pathlog.compared_paths().clone();
-    let ignored_paths = pathlog.ignored_paths().clone();
-    let selected_paths = pathlog.selected_paths.clone();
-    let comparison = pathlog.comparison();
+fn path_styles(snapshot: &RenderSnapshot) -> Vec<(Uuid, PathStyle)> {
+    let compared_paths = &snapshot.paths.compared_paths;
+    let selected = &snapshot.paths.selected;
+    let comparison = snapshot.paths.comparison;
 
-    drop(pathlog);
+    let gradient = &snapshot.settings.gradient;
+    let gradient_mode = snapshot.settings.gradient_mode;
+    let gradient_cutoff = snapshot.settings.gradient_cutoff;
+    let gold_color = snapshot.settings.gold_color;
+    let select_color = snapshot.settings.select_color;
 
-    let config = CONFIG_STATE.lock().unwrap();
+    let gradient_end = if gradient_cutoff > 0. {
+        comparison.gold_time + (gradient_cutoff * 1000.) as u64
+    } else {
+        comparison.slowest_time
+    };
 
-    let fast_color = config.fast_color;
-    let slow_color = config.slow_color;
-    let gold_color = config.gold_color;
-    let select_color = config.select_color;
+    // in draw order
+    let mut styles : Vec<(Uuid, PathStyle)> = Vec::new();
 
-    drop(config);
+    for compared in compared_paths {
+        let path_id = compared.id;
+        let position = compared.position;
 
-    // let mut visible_collection = PathCollection::new("Visible".to_string());
-    let mut selected : Vec<Uuid> = Vec::new();
-    for s in selected_paths.values() {
-        selected.extend(s);
-    }
-
-    for i in 0..compared_paths.len() {
-        let (path_id, position) = compared_paths[i];
-
-        let fast = fast_color;
-        let slow = slow_color;
-        let lerp = |a: f32, b: f32, t: f32| -> f32 { a * (1.0-t) + b * t };
         let mut color: [f32; 4];
         let mut thick: f32;
 
@@ -107,27 +192,29 @@ pub fn render_all_paths(pintar: &mut Pintar) {
             color = gold_color;
             thick = 0.04;
         }
+        else if matches!(gradient_mode, GradientMode::Time) {
+            let range = gradient_end.saturating_sub(comparison.gold_time);
+            let p = if range == 0 { 1.0 } else {
+                compared.time.saturating_sub(comparison.gold_time) as f32 / range as f32
+            };
+
+            color = sample_gradient(gradient, p);
+            thick = 0.02;
+        }
         else if comparison.len == 2 {
-            color = slow_color;
+            color = sample_gradient(gradient, 1.0);
             thick = 0.02;
         }
         else {
             let p = (position - 1) as f32 / (comparison.len - 2) as f32;
 
-            color = [
-                lerp(fast[0], slow[0], p),
-                lerp(fast[1], slow[1], p),
-                lerp(fast[2], slow[2], p),
-                lerp(fast[3], slow[3], p),
-            ];
+            color = sample_gradient(gradient, p);
             thick = 0.02;
         }
 
-        if matches!(comparison.mode, ComparisonMode::Median) {
-            for ignored_id in &ignored_paths[i] {
-                let ignored_color = [color[0], color[1], color[2], color[3] * 0.5];
-                render_path(pintar, PATHS_GROUP.to_string(), &PATHLOG.lock().unwrap().path(&ignored_id).unwrap(), ignored_color, thick);
-            }
+        for grouped_id in &compared.grouped {
+            let grouped_color = [color[0], color[1], color[2], color[3] * 0.5];
+            styles.push((*grouped_id, PathStyle { color: grouped_color, thickness: thick }));
         }
 
         if selected.contains(&path_id) {
@@ -135,49 +222,144 @@ pub fn render_all_paths(pintar: &mut Pintar) {
             thick = 0.04;
         }
 
-        let pathlog = PATHLOG.lock().unwrap();
-        render_path(pintar, PATHS_GROUP.to_string(), &pathlog.path(&path_id).unwrap(), color, thick);
+        styles.push((path_id, PathStyle { color, thickness: thick }));
     }
+
+    styles
 }
 
-pub fn render_triggers(pintar: &mut Pintar) {
-    let pathlog = PATHLOG.lock().unwrap();
+// runs every frame since culling depends on the camera. groups only get refilled when a path
+// looks different or a chunk became visible, got culled or changed detail
+pub fn render_all_paths(pintar: &mut Pintar, snapshot: &RenderSnapshot, view_proj: &[[f32; 4]; 4], viewer: [f32; 3]) {
+    let frustum_culling = snapshot.settings.frustum_culling;
+    let cull_distance = snapshot.settings.cull_distance;
+    let lod_distance = snapshot.settings.lod_distance;
+    let display_tolerance = snapshot.settings.display_tolerance;
 
-    let checkpoint_triggers = pathlog.checkpoint_triggers.clone();
-    let main_triggers = pathlog.main_triggers;
+    let mut cache_lock = PATH_CACHE.lock().unwrap();
+    let cache = &mut *cache_lock;
 
-    drop(pathlog);
+    // each snapshot's updates only apply once
+    let updates = if cache.version == snapshot.version { PathUpdates::None } else { snapshot.path_updates.clone() };
+    cache.version = snapshot.version;
 
-    let config = CONFIG_STATE.lock().unwrap();
+    if !updates.is_none() {
+        cache.styles = path_styles(snapshot);
+    }
 
-    let checkpoint_color = config.checkpoint_color;
-    let trigger_colors = config.trigger_colors;
+    let mut dirty = [false; PATH_BUCKET_COUNT];
 
-    drop(config);
+    if cache.display_tolerance != display_tolerance {
+        cache.display_tolerance = display_tolerance;
+        cache.chunks.clear();
+        dirty = [true; PATH_BUCKET_COUNT];
+    }
 
-    for collider in &checkpoint_triggers {
-        pintar.add_default_mesh(TRIGGERS_GROUP.to_string(), pintar::primitives::cube::new(checkpoint_color).scale(collider.size).rotate(collider.rotation()).translate(collider.position));
+    match &updates {
+        PathUpdates::All => {
+            cache.chunks.clear();
+            dirty = [true; PATH_BUCKET_COUNT];
+        }
+        PathUpdates::Geometry(path_ids) => {
+            for path_id in path_ids {
+                cache.chunks.remove(path_id);
+                dirty[path_bucket(path_id)] = true;
+            }
+        }
+        _ => {}
     }
 
-    for i in 0..2 {
-        if let Some(collider) = main_triggers[i] {
-            pintar.add_default_mesh(TRIGGERS_GROUP.to_string(), pintar::primitives::cube::new(trigger_colors[i]).scale(collider.size).rotate(collider.rotation()).translate(collider.position));
+    // if the player isn't on screen the matrix is probably not the gameplay camera (menus, cutscenes)
+    let frustum = Some(Frustum::from_view_proj(view_proj)).filter(|f| frustum_culling && f.contains_point(viewer));
+    let settings = CullSettings { frustum, viewer, cull_distance, lod_distance };
+
+    let mut new_drawn : HashMap<Uuid, DrawnPath> = HashMap::new();
+
+    for (path_id, style) in &cache.styles {
+        let Some(path) = snapshot.paths.paths.get(path_id) else { continue; };
+
+        let chunks = cache.chunks.entry(*path_id).or_insert_with(|| culling::chunk_path(path, display_tolerance));
+        let strides = chunks.iter().map(|chunk| settings.chunk_stride(chunk)).collect();
+
+        new_drawn.insert(*path_id, DrawnPath { style: *style, strides });
+    }
+
+    for (path_id, drawn) in &new_drawn {
+        if cache.drawn.get(path_id) != Some(drawn) {
+            dirty[path_bucket(path_id)] = true;
+        }
+    }
+
+    for path_id in cache.drawn.keys() {
+        if !new_drawn.contains_key(path_id) {
+            dirty[path_bucket(path_id)] = true;
+        }
+    }
+
+    cache.chunks.retain(|path_id, _| new_drawn.contains_key(path_id));
+    cache.drawn = new_drawn;
+
+    if !dirty.contains(&true) { return; }
+
+    for (bucket, _) in dirty.iter().enumerate().filter(|(_, d)| **d) {
+        pintar.clear_vertex_group(path_group(bucket));
+    }
+
+    for (path_id, style) in &cache.styles {
+        let bucket = path_bucket(path_id);
+        if !dirty[bucket] { continue; }
+
+        let Some(drawn) = cache.drawn.get(path_id) else { continue; };
+        let Some(chunks) = cache.chunks.get(path_id) else { continue; };
+
+        for (chunk, stride) in chunks.iter().zip(&drawn.strides) {
+            if *stride == 0 { continue; }
+            pintar.add_line(path_group(bucket), culling::chunk_nodes(chunk, *stride), style.color, style.thickness);
         }
     }
 }
 
-pub fn render_teleports(pintar: &mut Pintar) {
-    let config = CONFIG_STATE.lock().unwrap();
+// piecewise linear interpolation between evenly spaced color stops
+pub fn sample_gradient(stops: &[[f32; 4]], t: f32) -> [f32; 4] {
+    if stops.is_empty() { return [1.0, 1.0, 1.0, 1.0]; }
+    if stops.len() == 1 { return stops[0]; }
 
-    let accent_colors = config.accent_colors;
+    let lerp = |a: f32, b: f32, t: f32| -> f32 { a * (1.0-t) + b * t };
 
-    drop(config);
+    let scaled = t.clamp(0.0, 1.0) * (stops.len() - 1) as f32;
+    let index = (scaled as usize).min(stops.len() - 2);
+    let local = scaled - index as f32;
 
-    let ui_state = UI_STATE.lock().unwrap();
+    let a = stops[index];
+    let b = stops[index + 1];
+
+    [
+        lerp(a[0], b[0], local),
+        lerp(a[1], b[1], local),
+        lerp(a[2], b[2], local),
+        lerp(a[3], b[3], local),
+    ]
+}
+
+pub fn render_triggers(pintar: &mut Pintar, snapshot: &RenderSnapshot) {
+    let checkpoint_color = snapshot.settings.checkpoint_color;
+    let trigger_colors = snapshot.settings.trigger_colors;
+    let main_triggers = snapshot.scene.main_triggers;
+
+    for collider in &snapshot.scene.checkpoint_triggers {
+        pintar.add_default_mesh(TRIGGERS_GROUP.to_string(), pintar::primitives::cube::new(checkpoint_color).scale(collider.size).rotate(collider.rotation()).translate(collider.position));
+    }
 
-    let teleports = ui_state.extra_teleports;
+    for i in 0..2 {
+        if let Some(collider) = main_triggers[i] {
+            pintar.add_default_mesh(TRIGGERS_GROUP.to_string(), pintar::primitives::cube::new(trigger_colors[i]).scale(collider.size).rotate(collider.rotation()).translate(collider.position));
+        }
+    }
+}
 
-    drop(ui_state);
+pub fn render_teleports(pintar: &mut Pintar, snapshot: &RenderSnapshot) {
+    let accent_colors = snapshot.settings.accent_colors;
+    let teleports = snapshot.scene.extra_teleports;
 
     // let lerp = |a: u8, b: u8, t: f32| -> f32 { (a as f32 * (1.0-t) + b as f32 * t) / 255. };
 
@@ -194,14 +376,8 @@ pub fn render_teleports(pintar: &mut Pintar) {
     }
 }
 
-pub fn render_custom_shapes(pintar: &mut Pintar) {
-    let ui_state = UI_STATE.lock().unwrap();
-
-    let custom_shapes = ui_state.custom_shapes.clone();
-
-    drop(ui_state);
-
-    for shape in &custom_shapes {
+pub fn render_custom_shapes(pintar: &mut Pintar, snapshot: &RenderSnapshot) {
+    for shape in &snapshot.scene.custom_shapes {
         if shape.1 { continue; }
         match shape.0.shape_type {
             ShapeType::Box => {
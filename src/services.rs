@@ -0,0 +1,181 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+use native_dialog::FileDialog;
+use tracing::error;
+
+use crate::backup;
+use crate::error::Error;
+use crate::gamedata::{self, GameVersion};
+use crate::pathdata::FILE_EXTENTION;
+
+// everything the event dispatcher does outside of the app state. the game, the real dialogs and the disk
+// are behind GameServices, tests swap them for MockServices to run events headless
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FileDialogKind {
+    SaveComparison,
+    LoadComparison,
+}
+
+pub trait Services {
+    // must not block, the chosen file (or None when cancelled) arrives on the receiver
+    fn file_dialog(&mut self, kind: FileDialogKind) -> Receiver<Option<PathBuf>>;
+
+    fn player_position(&self) -> [f32; 3];
+    fn player_rotation(&self) -> [f32; 3];
+    fn camera_rotation(&self) -> [f32; 2];
+
+    fn teleport_player(&mut self, location: [f32; 3], rotation: [f32; 3]);
+    fn set_camera_rotation(&mut self, rotation: [f32; 2]);
+
+    fn game_version(&self) -> GameVersion;
+
+    // comparison and config files
+    fn read_file(&mut self, path: &Path) -> Result<Vec<u8>, Error>;
+    // never leaves a half written file behind
+    fn write_file(&mut self, path: &Path, contents: &[u8]) -> Result<(), Error>;
+    // copies the current version of a comparison into its backup folder and keeps the newest ones
+    fn back_up(&mut self, path: &Path, keep: usize) -> Result<(), Error>;
+}
+
+pub struct GameServices;
+
+impl Services for GameServices {
+    fn file_dialog(&mut self, kind: FileDialogKind) -> Receiver<Option<PathBuf>> {
+        let (tx, rx) = mpsc::channel();
+
+        thread::spawn(move || {
+            let result = match kind {
+                FileDialogKind::SaveComparison => FileDialog::new()
+                    .add_filter("Celestial Comparison", &[FILE_EXTENTION])
+                    .set_filename("Untitled")
+                    .show_save_single_file(),
+                FileDialogKind::LoadComparison => FileDialog::new()
+                    .add_filter("Celestial Comparison", &[FILE_EXTENTION])
+                    .add_filter("Any", &["*"])
+                    .show_open_single_file(),
+            };
+
+            let path = result.unwrap_or_else(|e| {
+                error!("{e}");
+                None
+            });
+
+            let _ = tx.send(path);
+        });
+
+        rx
+    }
+
+    fn player_position(&self) -> [f32; 3] {
+        gamedata::get_player_position()
+    }
+
+    fn player_rotation(&self) -> [f32; 3] {
+        gamedata::get_player_rotation()
+    }
+
+    fn camera_rotation(&self) -> [f32; 2] {
+        gamedata::get_camera_rotation()
+    }
+
+    fn teleport_player(&mut self, location: [f32; 3], rotation: [f32; 3]) {
+        gamedata::teleport_player(location, rotation);
+    }
+
+    fn set_camera_rotation(&mut self, rotation: [f32; 2]) {
+        gamedata::set_camera_rotation(rotation);
+    }
+
+    fn game_version(&self) -> GameVersion {
+        gamedata::game_version()
+    }
+
+    fn read_file(&mut self, path: &Path) -> Result<Vec<u8>, Error> {
+        Ok(fs::read(path)?)
+    }
+
+    fn write_file(&mut self, path: &Path, contents: &[u8]) -> Result<(), Error> {
+        backup::write_atomic(path, contents)
+    }
+
+    fn back_up(&mut self, path: &Path, keep: usize) -> Result<(), Error> {
+        backup::create_backup(path, keep)
+    }
+}
+
+// game, dialogs and disk for driving CelEvents in tests
+#[cfg(test)]
+pub struct MockServices {
+    // what the file dialog answers
+    pub dialog_answer: Option<PathBuf>,
+    pub dialogs: Vec<FileDialogKind>,
+    pub teleports: Vec<[f32; 3]>,
+    pub camera_rotation: Option<[f32; 2]>,
+    pub files: std::collections::HashMap<PathBuf, Vec<u8>>,
+    pub backups: Vec<PathBuf>,
+}
+
+#[cfg(test)]
+pub const MOCK_PLAYER_POSITION: [f32; 3] = [1., 2., 3.];
+
+#[cfg(test)]
+impl MockServices {
+    pub fn new() -> MockServices {
+        MockServices {
+            dialog_answer: None,
+            dialogs: Vec::new(),
+            teleports: Vec::new(),
+            camera_rotation: None,
+            files: std::collections::HashMap::new(),
+            backups: Vec::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+impl Default for MockServices {
+    fn default() -> Self {
+        MockServices::new()
+    }
+}
+
+#[cfg(test)]
+impl Services for MockServices {
+    fn file_dialog(&mut self, kind: FileDialogKind) -> Receiver<Option<PathBuf>> {
+        self.dialogs.push(kind);
+        let (tx, rx) = mpsc::channel();
+        let _ = tx.send(self.dialog_answer.clone());
+        rx
+    }
+
+    fn player_position(&self) -> [f32; 3] { MOCK_PLAYER_POSITION }
+    fn player_rotation(&self) -> [f32; 3] { [0., 0.5, 0.] }
+    fn camera_rotation(&self) -> [f32; 2] { [0.25, 0.75] }
+
+    fn teleport_player(&mut self, location: [f32; 3], _rotation: [f32; 3]) {
+        self.teleports.push(location);
+    }
+
+    fn set_camera_rotation(&mut self, rotation: [f32; 2]) {
+        self.camera_rotation = Some(rotation);
+    }
+
+    fn game_version(&self) -> GameVersion { GameVersion::Debug }
+
+    fn read_file(&mut self, path: &Path) -> Result<Vec<u8>, Error> {
+        self.files.get(path).cloned().ok_or(Error::IO { msg: format!("{} doesn't exist", path.display()) })
+    }
+
+    fn write_file(&mut self, path: &Path, contents: &[u8]) -> Result<(), Error> {
+        self.files.insert(path.to_path_buf(), contents.to_vec());
+        Ok(())
+    }
+
+    fn back_up(&mut self, path: &Path, _keep: usize) -> Result<(), Error> {
+        if self.files.contains_key(path) { self.backups.push(path.to_path_buf()); }
+        Ok(())
+    }
+}
@@ -0,0 +1,251 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use once_cell::sync::Lazy;
+use uuid::Uuid;
+
+use crate::comparison::ComparedPath;
+use crate::config::{ConfigState, GradientMode};
+use crate::events::AppState;
+use crate::pathdata::{BoxCollider, Path};
+use crate::pathlog::{Comparison, PathLog};
+use crate::rendering::PathUpdates;
+use crate::ui::{Shape, Teleport, UIState};
+
+// an immutable copy of everything the renderer draws. it gets published where the state changes: after the path log
+// update and after the events of a frame were processed, which includes config edits. the renderer only reads it
+
+#[derive(Clone, PartialEq)]
+pub struct RenderSettings {
+    pub gradient: Vec<[f32; 4]>,
+    pub gradient_mode: GradientMode,
+    pub gradient_cutoff: f32,
+    pub gold_color: [f32; 4],
+    pub select_color: [f32; 4],
+    pub checkpoint_color: [f32; 4],
+    pub trigger_colors: [[f32; 4]; 2],
+    pub accent_colors: [egui::Color32; 2],
+    pub frustum_culling: bool,
+    pub cull_distance: f32,
+    pub lod_distance: f32,
+    pub display_tolerance: f32,
+}
+
+impl RenderSettings {
+    pub fn from_config(config: &ConfigState) -> RenderSettings {
+        RenderSettings {
+            gradient: config.gradient(),
+            gradient_mode: config.gradient_mode,
+            gradient_cutoff: config.gradient_cutoff,
+            gold_color: config.gold_color,
+            select_color: config.select_color,
+            checkpoint_color: config.checkpoint_color,
+            trigger_colors: config.trigger_colors,
+            accent_colors: config.accent_colors,
+            frustum_culling: config.frustum_culling,
+            cull_distance: config.cull_distance,
+            lod_distance: config.lod_distance,
+            display_tolerance: config.display_tolerance,
+        }
+    }
+}
+
+// triggers, teleports and shapes. only copied when they differ from the live state
+#[derive(Clone, PartialEq)]
+pub struct Scene {
+    pub main_triggers: [Option<BoxCollider>; 2],
+    pub checkpoint_triggers: Vec<BoxCollider>,
+    pub extra_teleports: [Option<Teleport>; 10],
+    pub custom_shapes: Vec<(Shape, bool)>,
+}
+
+// only rebuilt on path updates, unchanged paths are shared with the previous one
+pub struct PathsSnapshot {
+    pub compared_paths: Vec<ComparedPath>,
+    pub selected: HashSet<Uuid>,
+    pub comparison: Comparison,
+    pub paths: HashMap<Uuid, Arc<Path>>,
+}
+
+pub struct RenderSnapshot {
+    // goes up by one for every new snapshot
+    pub version: u64,
+    pub settings: Arc<RenderSettings>,
+    pub scene: Arc<Scene>,
+    pub paths: Arc<PathsSnapshot>,
+    pub recording_path: Arc<Path>,
+    // what happened to the paths since the previous version
+    pub path_updates: PathUpdates,
+}
+
+struct Published {
+    snapshot: Arc<RenderSnapshot>,
+    // the renderer got this version. if not, its path updates carry over into the next one
+    read: bool,
+}
+
+static RENDER_SNAPSHOT: Lazy<Mutex<Published>> = Lazy::new(|| Mutex::new(Published {
+    snapshot: Arc::new(RenderSnapshot {
+        version: 0,
+        settings: Arc::new(RenderSettings::from_config(&ConfigState::new())),
+        scene: Arc::new(Scene {
+            main_triggers: [None; 2],
+            checkpoint_triggers: Vec::new(),
+            extra_teleports: [None; 10],
+            custom_shapes: Vec::new(),
+        }),
+        paths: Arc::new(PathsSnapshot {
+            compared_paths: Vec::new(),
+            selected: HashSet::new(),
+            comparison: Comparison { mode: "", len: 0, gold_time: 0, slowest_time: 0 },
+            paths: HashMap::new(),
+        }),
+        recording_path: Arc::new(Path::new()),
+        path_updates: PathUpdates::None,
+    }),
+    read: true,
+}));
+
+// for the renderer
+pub fn current() -> Arc<RenderSnapshot> {
+    let mut published = RENDER_SNAPSHOT.lock().unwrap();
+    published.read = true;
+    published.snapshot.clone()
+}
+
+// the recording path changes every frame while recording, this is cheaper than comparing nodes
+fn recording_changed(old: &Path, new: &Path) -> bool {
+    old.id() != new.id() || old.len() != new.len() || old.segment_count() != new.segment_count()
+}
+
+fn snapshot_paths(pathlog: &PathLog, previous: &PathsSnapshot, updates: &PathUpdates) -> PathsSnapshot {
+    let compared_paths = pathlog.compared_paths().clone();
+    let mut paths : HashMap<Uuid, Arc<Path>> = HashMap::new();
+
+    for compared in &compared_paths {
+        for path_id in std::iter::once(&compared.id).chain(&compared.grouped) {
+            let unchanged = match updates {
+                PathUpdates::All => None,
+                PathUpdates::Geometry(path_ids) if path_ids.contains(path_id) => None,
+                _ => previous.paths.get(path_id).cloned(),
+            };
+
+            let path = match unchanged {
+                Some(path) => path,
+                None => match pathlog.path(path_id) {
+                    Some(path) => Arc::new(path.clone()),
+                    None => continue,
+                },
+            };
+
+            paths.insert(*path_id, path);
+        }
+    }
+
+    PathsSnapshot {
+        compared_paths,
+        selected: pathlog.selected_paths.values().flatten().copied().collect(),
+        comparison: pathlog.comparison(),
+        paths,
+    }
+}
+
+fn scene_changed(scene: &Scene, pathlog: &PathLog, ui: &UIState) -> bool {
+    scene.main_triggers != pathlog.main_triggers
+        || scene.checkpoint_triggers != pathlog.checkpoint_triggers
+        || scene.extra_teleports != ui.extra_teleports
+        || scene.custom_shapes != ui.custom_shapes
+}
+
+// replaces the published snapshot with whatever the closure changes, if it changes anything
+fn publish_with(update: impl FnOnce(&RenderSnapshot) -> Option<RenderSnapshot>) {
+    let mut published = RENDER_SNAPSHOT.lock().unwrap();
+
+    let Some(mut next) = update(&published.snapshot) else { return; };
+
+    if !published.read {
+        let mut path_updates = published.snapshot.path_updates.clone();
+        path_updates.or(next.path_updates);
+        next.path_updates = path_updates;
+    }
+
+    next.version = published.snapshot.version + 1;
+    published.snapshot = Arc::new(next);
+    published.read = false;
+}
+
+// after PathLog::update, the recording path and whatever the update did to the other paths
+pub fn publish_paths(pathlog: &PathLog, path_updates: PathUpdates) {
+    publish_with(|previous| {
+        let recording_changed = recording_changed(&previous.recording_path, &pathlog.recording_path);
+        if path_updates.is_none() && !recording_changed { return None; }
+
+        Some(RenderSnapshot {
+            version: previous.version,
+            settings: previous.settings.clone(),
+            scene: previous.scene.clone(),
+            paths: if path_updates.is_none() { previous.paths.clone() } else { Arc::new(snapshot_paths(pathlog, &previous.paths, &path_updates)) },
+            recording_path: if recording_changed { Arc::new(pathlog.recording_path.clone()) } else { previous.recording_path.clone() },
+            path_updates,
+        })
+    });
+}
+
+// after the events of a frame. takes the path and settings updates they queued
+pub fn publish(state: &mut AppState) {
+    let path_updates = std::mem::replace(&mut state.render_updates.paths, PathUpdates::None);
+    let settings_changed = std::mem::take(&mut state.render_updates.settings);
+
+    publish_with(|previous| {
+        let scene_changed = scene_changed(&previous.scene, state.pathlog, state.ui);
+        let recording_changed = recording_changed(&previous.recording_path, &state.pathlog.recording_path);
+
+        if path_updates.is_none() && !settings_changed && !scene_changed && !recording_changed { return None; }
+
+        let scene = if scene_changed {
+            Arc::new(Scene {
+                main_triggers: state.pathlog.main_triggers,
+                checkpoint_triggers: state.pathlog.checkpoint_triggers.clone(),
+                extra_teleports: state.ui.extra_teleports,
+                custom_shapes: state.ui.custom_shapes.clone(),
+            })
+        } else {
+            previous.scene.clone()
+        };
+
+        Some(RenderSnapshot {
+            version: previous.version,
+            settings: if settings_changed { Arc::new(RenderSettings::from_config(state.config)) } else { previous.settings.clone() },
+            scene,
+            paths: if path_updates.is_none() { previous.paths.clone() } else { Arc::new(snapshot_paths(state.pathlog, &previous.paths, &path_updates)) },
+            recording_path: if recording_changed { Arc::new(state.pathlog.recording_path.clone()) } else { previous.recording_path.clone() },
+            path_updates,
+        })
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unread_path_updates_carry_over() {
+        let pathlog = PathLog::new();
+        let [a, b] = [Uuid::from_u128(1), Uuid::from_u128(2)];
+
+        publish_paths(&pathlog, PathUpdates::Geometry(HashSet::from([a])));
+        publish_paths(&pathlog, PathUpdates::Geometry(HashSet::from([b])));
+
+        let snapshot = current();
+        assert!(snapshot.path_updates == PathUpdates::Geometry(HashSet::from([a, b])));
+
+        publish_paths(&pathlog, PathUpdates::Styles);
+        let next = current();
+        assert_eq!(next.version, snapshot.version + 1);
+        assert!(next.path_updates == PathUpdates::Styles);
+        assert!(Arc::ptr_eq(&next.settings, &snapshot.settings));
+
+        // nothing changed, nothing published
+        publish_paths(&pathlog, PathUpdates::None);
+        assert_eq!(current().version, next.version);
+    }
+}
@@ -1,4 +1,4 @@
-use std::collections::VecDeque;
+use std::collections::{HashMap, VecDeque};
 use std::f32;
 use std::f32::consts::PI;
 use std::fmt::Debug;
@@ -6,19 +6,28 @@ use egui::epaint::Hsva;
 use egui::scroll_area::ScrollBarVisibility;
 use egui::{Color32, RichText};
 use uuid::Uuid;
+use serde::{Serialize, Deserialize};
 use egui_keybind::{Bind, Keybind};
 
-use crate::config::{AsColor32, AsHsva, CompareKeybindToEvent};
-use crate::pathdata::HighPassFilter;
-use crate::{CONFIG_STATE, EVENTS, PATHLOG, RENDER_UPDATES, RX, RenderUpdates, SCREEN_DIMENSIONS, UI_STATE, gamedata, pathlog};
+use crate::config::{AsColor32, AsHsva, GradientMode, GradientPreset, SamplingMode, CONFIG_FILE_NAME};
+use crate::config::{BACKUP_COUNT_RANGE, DISPLAY_TOLERANCE_RANGE, DISTANCE_RANGE, GRADIENT_CUTOFF_RANGE, OVERLAY_TOP_N_RANGE, SAMPLING_ANGLE_RANGE};
+use crate::config::{SAMPLING_DISTANCE_RANGE, SAMPLING_INTERVAL_RANGE, SIMPLIFY_TOLERANCE_RANGE, TIMER_SIZE_RANGE, TRIGGER_SIZE_RANGE, ZOOM_RANGE};
+use crate::pathdata::{DisplayFilter, HighPassFilter};
+use crate::{CAPTURE, CONFIG_STATE, EVENTS, PATHLOG, RENDER_UPDATES, RX, RenderUpdates, UI_STATE, backup, comparison, date, gamedata, rendering};
 use crate::events::CelEvent;
+use crate::actions::Action;
 
 const FONT_SIZE: f32 = 12.;
+const WARNING_COLOR: Color32 = Color32::from_rgb(255, 170, 60);
 
 #[derive(Clone, Copy, PartialEq)]
 pub enum Tab { Comparison, Paths, Triggers, Config, Credits, CustomShapes }
 
-#[derive(Clone, Copy)]
+// what the profile name field is for
+#[derive(Clone, Copy, PartialEq)]
+pub enum ProfileEdit { Create, Duplicate, Rename }
+
+#[derive(Clone, Copy, PartialEq)]
 pub struct Teleport {
     pub location: [f32; 3],
     pub rotation: [f32; 3],
@@ -26,6 +35,7 @@ pub struct Teleport {
 }
 
 #[derive(Clone, Copy)]
+#[derive(Serialize, Deserialize)]
 pub enum TeleportIndex {
     Main { i: usize },
     Extra { i: usize },
@@ -38,7 +48,7 @@ pub enum ShapeType {
     Cylinder,
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, PartialEq)]
 pub struct Shape {
     id: Uuid,
     pub shape_type: ShapeType,
@@ -76,9 +86,21 @@ pub struct UIState {
     new_exception: bool,
     renaming_collection: Option<Uuid>,
     renaming_name: String,
+    // collection the recovered runs go into, None makes a new one
+    recover_collection: Option<Uuid>,
+    // tag being typed in the info menu of each path
+    new_tags: HashMap<Uuid, String>,
+    // date filter input, YYYY-MM-DD
+    filter_from: String,
+    filter_to: String,
     pub main_teleports: [ Option<Teleport>; 2 ],
     pub extra_teleports: [ Option<Teleport>; 10 ],
     pub hide_checkpoints: bool,
+    // result of the last file operation (load errors, compact savings)
+    pub file_status: Option<String>,
+    profile_edit: Option<ProfileEdit>,
+    profile_name: String,
+    pub profile_error: Option<String>,
 
     pub custom_shapes: Vec<(Shape, bool)>,
 }
@@ -94,9 +116,17 @@ impl UIState {
             new_exception: false,
             renaming_collection: None,
             renaming_name: "".to_string(),
+            recover_collection: None,
+            new_tags: HashMap::new(),
+            filter_from: "".to_string(),
+            filter_to: "".to_string(),
             main_teleports: [None; 2],
             extra_teleports: [None; 10],
             hide_checkpoints: false,
+            file_status: None,
+            profile_edit: None,
+            profile_name: "".to_string(),
+            profile_error: None,
             custom_shapes: Vec::new(),
         };
 
@@ -108,15 +138,7 @@ pub fn check_input(input: &egui::RawInput) {
     let config = CONFIG_STATE.lock().unwrap();
 
     let direct_mode = config.direct_mode;
-    let start_keybind = config.start_keybind;
-    let stop_keybind = config.stop_keybind;
-    let reset_keybind = config.reset_keybind;
-    let clear_keybind = config.clear_keybind;
-    let teleport_keybinds = config.teleport_keybinds;
-
-    let extra_teleport_keybinds = config.extra_teleport_keybinds;
-    let spawn_teleport_keybinds = config.spawn_teleport_keybinds;
-    let delete_teleport_keybinds = config.delete_teleport_keybinds;
+    let keybinds = config.keybinds.clone();
 
     drop(config);
 
@@ -143,72 +165,9 @@ pub fn check_input(input: &egui::RawInput) {
             }
         }
 
-        if start_keybind.compare_to_event(input_event) {
-            if direct_mode {
-                new_events.push_back(CelEvent::StartRecording);
-            } else {
-                new_events.push_back(CelEvent::SpawnTrigger {
-                    index: 0,
-                    position: gamedata::get_player_position(),
-                    rotation: gamedata::get_player_rotation(),
-                });
-            }
-        }
-
-        if stop_keybind.compare_to_event(input_event) {
-            if direct_mode {
-                new_events.push_back(CelEvent::StopRecording);
-            } else {
-                new_events.push_back(CelEvent::SpawnTrigger {
-                    index: 1,
-                    position: gamedata::get_player_position(),
-                    rotation: gamedata::get_player_rotation(),
-                });
-            }
-        }
-
-        if reset_keybind.compare_to_event(input_event) {
-            new_events.push_back(CelEvent::ResetRecording);
-        }
-
-        if clear_keybind.compare_to_event(input_event) {
-            new_events.push_back(CelEvent::ClearTriggers);
-        }
-
-        if teleport_keybinds[0].compare_to_event(input_event) {
-            new_events.push_back(CelEvent::Teleport { index: TeleportIndex::Main { i: 0 } });
+        for action in keybinds.triggered(input_event) {
+            new_events.push_back(action.event(direct_mode));
         }
-
-        if teleport_keybinds[1].compare_to_event(input_event) {
-            new_events.push_back(CelEvent::Teleport { index: TeleportIndex::Main { i: 1 } });
-        }
-
-        for i in 0..2 {
-            if extra_teleport_keybinds[i].compare_to_event(input_event) {
-                new_events.push_back(CelEvent::Teleport { index: TeleportIndex::Extra { i } });
-            }
-        }
-
-        for i in 0..2 {
-            if spawn_teleport_keybinds[i].compare_to_event(input_event) {
-                new_events.push_back(CelEvent::SpawnTeleport { index: TeleportIndex::Extra { i } });
-            }
-        }
-
-        for i in 0..2 {
-            if delete_teleport_keybinds[i].compare_to_event(input_event) {
-                new_events.push_back(CelEvent::DeleteTeleport { index: TeleportIndex::Extra { i } });
-            }
-        }
-
-        // if config.spawn_checkpoint_keybind.compare_to_event(e) {
-        //     egui.new_events.push_back(CelEvent::SpawnTrigger {
-        //         index: 2,
-        //         position: gamedata::get_player_position(),
-        //         rotation: gamedata::get_player_rotation(),
-        //         size: config.trigger_size[1],
-        //     });
-        // }
     }
 
     let mut ui_state = UI_STATE.lock().unwrap();
@@ -223,12 +182,29 @@ pub fn check_input(input: &egui::RawInput) {
     events.append(&mut new_events);
 }
 
+#[cfg(debug_assertions)]
 pub fn draw_debug(ui: &mut egui::Ui) {
-    let render_size = SCREEN_DIMENSIONS.lock().unwrap().render_size;
-    let window_size = SCREEN_DIMENSIONS.lock().unwrap().window_size;
+    let render_size = crate::SCREEN_DIMENSIONS.lock().unwrap().render_size;
+    let window_size = crate::SCREEN_DIMENSIONS.lock().unwrap().window_size;
     ui.add(egui::Label::new(
         RichText::new(format!("{:?}\n{:?}\n{:?}", render_size, window_size, UI_STATE.lock().unwrap().screen_scale))
     ));
+
+    ui.separator();
+
+    let running = crate::bench::is_running();
+    if ui.add_enabled(!running, egui::Button::new("Mock LiveSplit server")).on_hover_text("Listens on the configured port and lists the commands it gets").clicked() {
+        crate::bench::start_livesplit_mock(CONFIG_STATE.lock().unwrap().livesplit_port);
+    }
+    let last_capture = crate::capture::list_captures().into_iter().next();
+    if ui.add_enabled(!running && last_capture.is_some(), egui::Button::new("Replay last capture")).clicked() {
+        crate::bench::start_replay(last_capture.unwrap());
+    }
+    if running { ui.spinner(); }
+
+    for line in crate::bench::BENCH_RESULTS.lock().unwrap().iter() {
+        ui.label(line);
+    }
 }
 
 pub fn draw_ui(ui: &mut egui::Ui) {
@@ -302,6 +278,8 @@ fn draw_comparison_tab(ui: &mut egui::Ui) {
     let active_collection = pathlog.active_collection;
     let path_collections_len = pathlog.collections().len();
     let mut comparison_mode = pathlog.comparison().mode;
+    let comparison_parameter = pathlog.comparison_strategy().parameter();
+    let order_unknown = pathlog.comparison_strategy().uses_history() && !pathlog.history_known();
 
     drop(pathlog);
 
@@ -318,9 +296,19 @@ fn draw_comparison_tab(ui: &mut egui::Ui) {
     let mut renaming_collection = ui_state.renaming_collection;
     let mut renaming_name = ui_state.renaming_name.clone();
     let mut delete_mode = ui_state.delete_mode;
+    let mut file_status = ui_state.file_status.clone();
+    let mut recover_collection = ui_state.recover_collection;
 
     drop(ui_state);
 
+    let pathlog = PATHLOG.lock().unwrap();
+
+    let last_file = pathlog.last_file().cloned();
+    let recovered_runs : Vec<(u64, bool)> = pathlog.recovered_runs().iter().map(|r| (r.path.time(), r.finished)).collect();
+    let collection_names : Vec<(Uuid, String)> = pathlog.collections().iter().map(|c| (c.id(), c.name.clone())).collect();
+
+    drop(pathlog);
+
     let scale = screen_scale * zoom;
     let mut new_events : VecDeque<CelEvent> = VecDeque::new();
     let mut mute_toggles : Vec<Uuid> = Vec::new();
@@ -329,17 +317,66 @@ fn draw_comparison_tab(ui: &mut egui::Ui) {
 
     // ui.separator();
 
+    if !recovered_runs.is_empty() {
+        if recover_collection.is_some_and(|id| !collection_names.iter().any(|(c, _)| *c == id)) {
+            recover_collection = None;
+        }
+
+        ui.label(RichText::new(format!("{} unsaved runs from the last session:", recovered_runs.len())).size(FONT_SIZE * scale).strong());
+        for (time, finished) in &recovered_runs {
+            let text = format!("{:02}:{:02}.{:03}{}", time / 60000, (time % 60000) / 1000, time % 1000, if *finished { "" } else { " (unfinished)" });
+            ui.label(RichText::new(text).size(FONT_SIZE * scale));
+        }
+
+        ui.horizontal(|ui| {
+            let selected_name = collection_names.iter()
+                .find(|(id, _)| Some(*id) == recover_collection)
+                .map_or("New Collection".to_string(), |(_, name)| name.clone());
+
+            egui::ComboBox::from_id_source("recover_collection")
+                .selected_text(RichText::new(selected_name).size(FONT_SIZE * scale))
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut recover_collection, None, "New Collection");
+                    for (id, name) in &collection_names {
+                        ui.selectable_value(&mut recover_collection, Some(*id), name);
+                    }
+                });
+
+            if ui.button(RichText::new("Recover").size(FONT_SIZE * scale)).clicked() {
+                new_events.push_back(CelEvent::RecoverRuns { collection_id: recover_collection });
+            }
+            if ui.button(RichText::new("Discard").size(FONT_SIZE * scale)).clicked() {
+                new_events.push_back(CelEvent::DiscardRecoveredRuns);
+            }
+        });
+
+        ui.separator();
+    }
+
+    let mut parameter_value = comparison_parameter.as_ref().map_or(0, |p| p.value);
+
     ui.with_layout(egui::Layout::right_to_left(egui::Align::TOP), |ui| {
         egui::ComboBox::new("comp_mode_drop_down", RichText::new("Comparison Mode").size(FONT_SIZE * scale))
             // .selected_text(format!("{:?}", comparison_mode))
-            .selected_text(RichText::new(format!("{:?}", comparison_mode)).size(FONT_SIZE * scale))
+            .selected_text(RichText::new(comparison_mode).size(FONT_SIZE * scale))
             .show_ui(ui, |ui| {
-                ui.selectable_value(&mut comparison_mode, pathlog::ComparisonMode::All, "All");
-                ui.selectable_value(&mut comparison_mode, pathlog::ComparisonMode::Gold, "Gold");
-                ui.selectable_value(&mut comparison_mode, pathlog::ComparisonMode::Median, "Median");
+                for strategy in comparison::strategies() {
+                    ui.selectable_value(&mut comparison_mode, strategy.name(), strategy.name());
+                }
             });
+
+        if let Some(parameter) = &comparison_parameter {
+            ui.add(egui::DragValue::new(&mut parameter_value).clamp_range(parameter.range.clone()));
+            ui.label(RichText::new(parameter.label).size(FONT_SIZE * scale));
+        }
         });
 
+    if order_unknown {
+        ui.label(RichText::new("This file doesn't store the recording order, older runs are ordered by time").size(FONT_SIZE * scale).color(WARNING_COLOR));
+    }
+
+    draw_filter_bar(ui, scale, &mut new_events);
+
     ui.separator();
 
     let scroll_height = ui.available_height() - 41.0 * scale;
@@ -492,7 +529,7 @@ fn draw_comparison_tab(ui: &mut egui::Ui) {
 
                                 if let Some(c_id) = p_log.active_collection {
                                     if let Some(coll) = &p_log.get_collection(c_id) {
-                                        if i < coll.paths().len() && coll.paths()[i] == p_log.latest_path {
+                                        if i < coll.paths().len() && Some(coll.paths()[i]) == p_log.latest_path {
                                             Some(egui::Color32::from_gray(42))
                                             // Some(style.visuals.faint_bg_color)
                                         } else { None }
@@ -508,7 +545,11 @@ fn draw_comparison_tab(ui: &mut egui::Ui) {
 
                                 // for path in state.pathlog.path_collections[i].paths() {
                                 for p in 0..collection_len {
-                                    draw_path(ui, p, i);
+                                    let pathlog = PATHLOG.lock().unwrap();
+                                    let shown = pathlog.is_shown(&pathlog.collections()[i].paths()[p]);
+                                    drop(pathlog);
+
+                                    if shown { draw_path(ui, p, i); }
                                 }
                             });
                     });
@@ -529,6 +570,27 @@ fn draw_comparison_tab(ui: &mut egui::Ui) {
                 if ui.add(egui::Button::new(RichText::new("Load").size(FONT_SIZE * scale)).min_size(egui::vec2(19.0, 19.0) * scale)).clicked() {
                     new_events.push_back(CelEvent::LoadComparison);
                 }
+                if ui.add(egui::Button::new(RichText::new("Compact").size(FONT_SIZE * scale)).min_size(egui::vec2(19.0, 19.0) * scale))
+                    .on_hover_text("Remove nodes that barely change the shape of the paths")
+                    .clicked()
+                {
+                    new_events.push_back(CelEvent::CompactComparison);
+                }
+                if let Some(file_path) = &last_file {
+                    ui.menu_button(RichText::new("Backups").size(FONT_SIZE * scale), |ui| {
+                        let backups = backup::list_backups(std::path::Path::new(file_path));
+                        if backups.is_empty() {
+                            ui.label("No backups yet");
+                        }
+                        for backup_path in backups {
+                            let name = backup_path.file_name().unwrap_or_default().to_string_lossy().to_string();
+                            if ui.button(name).on_hover_text("Restore this version").clicked() {
+                                new_events.push_back(CelEvent::RestoreBackup { backup_path: backup_path.to_string_lossy().to_string() });
+                                ui.close_menu();
+                            }
+                        }
+                    });
+                }
             });
             ui.with_layout(egui::Layout::right_to_left(egui::Align::TOP), |ui| {
 
@@ -557,13 +619,29 @@ fn draw_comparison_tab(ui: &mut egui::Ui) {
             ui.end_row();
         });
 
+    if let Some(status) = file_status.clone() {
+        ui.horizontal(|ui| {
+            ui.label(RichText::new(status).size(FONT_SIZE * scale));
+            if ui.add(egui::Button::new(RichText::new("\u{1F5D9}").size(FONT_SIZE * scale)).frame(false)).clicked() {
+                file_status = None;
+            }
+        });
+    }
 
-    RENDER_UPDATES.lock().unwrap().paths = !to_clear.is_empty();
+    if !to_clear.is_empty() {
+        RENDER_UPDATES.lock().unwrap().or(RenderUpdates::paths());
+    }
 
     let mut pathlog = PATHLOG.lock().unwrap();
 
     if pathlog.comparison().mode != comparison_mode {
-        pathlog.set_comparison_mode(comparison_mode);
+        if let Some(strategy) = comparison::strategy_by_name(comparison_mode) {
+            pathlog.set_comparison_strategy(strategy);
+        }
+        new_events.push_back(CelEvent::RenderUpdate { update: RenderUpdates::paths() });
+    }
+    else if comparison_parameter.is_some_and(|p| p.value != parameter_value) {
+        pathlog.set_comparison_parameter(parameter_value);
         new_events.push_back(CelEvent::RenderUpdate { update: RenderUpdates::paths() });
     }
 
@@ -578,6 +656,8 @@ fn draw_comparison_tab(ui: &mut egui::Ui) {
     ui_state.renaming_collection = renaming_collection;
     ui_state.renaming_name = renaming_name;
     ui_state.delete_mode = delete_mode;
+    if file_status.is_none() { ui_state.file_status = None; }
+    ui_state.recover_collection = recover_collection;
 
     drop(ui_state);
 
@@ -594,11 +674,119 @@ fn draw_comparison_tab(ui: &mut egui::Ui) {
     events.append(&mut new_events);
 }
 
+fn draw_filter_bar(ui: &mut egui::Ui, scale: f32, new_events: &mut VecDeque<CelEvent>) {
+    let pathlog = PATHLOG.lock().unwrap();
+
+    let filter = pathlog.display_filter().clone();
+    let all_tags = pathlog.all_tags();
+    let shown_count = pathlog.shown_count();
+    let path_count = pathlog.path_count();
+
+    drop(pathlog);
+
+    let ui_state = UI_STATE.lock().unwrap();
+
+    let mut filter_from = ui_state.filter_from.clone();
+    let mut filter_to = ui_state.filter_to.clone();
+
+    drop(ui_state);
+
+    // 0 means no limit for all of these
+    let mut min_time = filter.min_time.map_or(0., |t| t as f32 / 1000.);
+    let mut max_time = filter.max_time.map_or(0., |t| t as f32 / 1000.);
+    let mut segment_count = filter.segment_count.unwrap_or(0);
+    let mut top_n = filter.top_n.unwrap_or(0);
+    let mut tag = filter.tag.clone();
+
+    let header_text = if filter.is_active() { format!("Filter ({shown_count} of {path_count} paths)") } else { "Filter".to_string() };
+
+    egui::CollapsingHeader::new(RichText::new(header_text).size(FONT_SIZE * scale))
+        .id_source("display_filter")
+        .show(ui, |ui| {
+            egui::Grid::new("display_filter_grid")
+                .num_columns(2)
+                .spacing(egui::vec2(40.0, 4.0) * scale)
+                .show(ui, |ui| {
+                    ui.label(RichText::new("Time (s)").size(FONT_SIZE * scale));
+                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                        ui.add(egui::DragValue::new(&mut max_time).speed(0.1).clamp_range(0.0..=f32::MAX));
+                        ui.label("to");
+                        ui.add(egui::DragValue::new(&mut min_time).speed(0.1).clamp_range(0.0..=f32::MAX));
+                    });
+                    ui.end_row();
+
+                    ui.label(RichText::new("Recorded (YYYY-MM-DD)").size(FONT_SIZE * scale));
+                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                        ui.add(egui::TextEdit::singleline(&mut filter_to).desired_width(80.0 * scale));
+                        ui.label("to");
+                        ui.add(egui::TextEdit::singleline(&mut filter_from).desired_width(80.0 * scale));
+                    });
+                    ui.end_row();
+
+                    ui.label(RichText::new("Tag").size(FONT_SIZE * scale));
+                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                        egui::ComboBox::from_id_source("display_filter_tag")
+                            .selected_text(tag.clone().unwrap_or("Any".to_string()))
+                            .show_ui(ui, |ui| {
+                                ui.selectable_value(&mut tag, None, "Any");
+                                for t in &all_tags {
+                                    ui.selectable_value(&mut tag, Some(t.clone()), t);
+                                }
+                            });
+                    });
+                    ui.end_row();
+
+                    ui.label(RichText::new("Segments").size(FONT_SIZE * scale));
+                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                        ui.add(egui::DragValue::new(&mut segment_count).speed(0.1).clamp_range(0..=100));
+                    });
+                    ui.end_row();
+
+                    ui.label(RichText::new("Top N per Collection").size(FONT_SIZE * scale));
+                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                        ui.add(egui::DragValue::new(&mut top_n).speed(0.1).clamp_range(0..=1000));
+                    });
+                    ui.end_row();
+                });
+
+            if ui.button(RichText::new("Clear Filter").size(FONT_SIZE * scale)).clicked() {
+                min_time = 0.;
+                max_time = 0.;
+                segment_count = 0;
+                top_n = 0;
+                tag = None;
+                filter_from.clear();
+                filter_to.clear();
+            }
+        });
+
+    let new_filter = DisplayFilter {
+        min_time: Some((min_time * 1000.) as u64).filter(|t| *t > 0),
+        max_time: Some((max_time * 1000.) as u64).filter(|t| *t > 0),
+        recorded_after: date::parse_date(&filter_from),
+        // the whole day counts
+        recorded_before: date::parse_date(&filter_to).map(|t| t + 86400),
+        tag,
+        segment_count: Some(segment_count).filter(|c| *c > 0),
+        top_n: Some(top_n).filter(|n| *n > 0),
+    };
+
+    if new_filter != filter {
+        new_events.push_back(CelEvent::SetDisplayFilter { filter: new_filter });
+    }
+
+    let mut ui_state = UI_STATE.lock().unwrap();
+
+    ui_state.filter_from = filter_from;
+    ui_state.filter_to = filter_to;
+}
+
 fn draw_path(ui: &mut egui::Ui, path: usize, collection: usize) {
     let pathlog = PATHLOG.lock().unwrap();
 
     let path_id = pathlog.collections()[collection].paths()[path];
     let path_time = pathlog.path(&path_id).unwrap().time();
+    let path_meta = pathlog.path(&path_id).unwrap().meta().clone();
     let collection_id = pathlog.collections()[collection].id();
     let latest_path = pathlog.latest_path;
     let selected = pathlog.selected_paths.get(&collection_id).unwrap().clone();
@@ -621,6 +809,7 @@ fn draw_path(ui: &mut egui::Ui, path: usize, collection: usize) {
     let screen_scale = ui_state.screen_scale;
     let mods = ui_state.modifier;
     let delete_mode = ui_state.delete_mode;
+    let mut new_tag = ui_state.new_tags.get(&path_id).cloned().unwrap_or_default();
 
     drop(ui_state);
 
@@ -676,7 +865,7 @@ fn draw_path(ui: &mut egui::Ui, path: usize, collection: usize) {
         if selected.contains(&path_id) {
             ui.visuals_mut().override_text_color = Some(select_color.as_color32());
         }
-        if latest_path == path_id {
+        if latest_path == Some(path_id) {
             ui.visuals_mut().widgets.inactive.weak_bg_fill = Color32::from_gray(42);
         }
 
@@ -706,6 +895,44 @@ fn draw_path(ui: &mut egui::Ui, path: usize, collection: usize) {
                 }
             }
         }
+
+        let info_text = if path_meta.notes.is_empty() && path_meta.tags.is_empty() { "\u{2139}" } else { "\u{1F5D2}" };
+
+        ui.menu_button(RichText::new(info_text).size(FONT_SIZE * scale), |ui| {
+            let recorded_at = if path_meta.recorded_at == 0 { "unknown".to_string() } else {
+                let [year, month, day, hour, minute, _] = date::utc_date_time(path_meta.recorded_at);
+                format!("{year:04}-{month:02}-{day:02} {hour:02}:{minute:02} UTC")
+            };
+            let game_version = path_meta.game_version.map_or("unknown".to_string(), |v| format!("{v:?}"));
+
+            ui.label(format!("Recorded: {recorded_at}"));
+            ui.label(format!("Game Version: {game_version}"));
+            if !path_meta.runner.is_empty() {
+                ui.label(format!("Runner: {}", path_meta.runner));
+            }
+
+            ui.separator();
+
+            let mut notes = path_meta.notes.clone();
+            ui.label("Notes");
+            if ui.add(egui::TextEdit::multiline(&mut notes).desired_rows(3)).changed() {
+                new_events.push_back(CelEvent::SetPathNotes { path_id, notes });
+            }
+
+            ui.label("Tags");
+            ui.horizontal_wrapped(|ui| {
+                for tag in &path_meta.tags {
+                    if ui.button(format!("{tag} \u{1F5D9}")).on_hover_text("Remove tag").clicked() {
+                        new_events.push_back(CelEvent::RemovePathTag { path_id, tag: tag.clone() });
+                    }
+                }
+            });
+
+            let tag_response = ui.add(egui::TextEdit::singleline(&mut new_tag).hint_text("New tag"));
+            if tag_response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+                new_events.push_back(CelEvent::AddPathTag { path_id, tag: std::mem::take(&mut new_tag) });
+            }
+        });
     });
 
     ui.with_layout(egui::Layout::right_to_left(egui::Align::TOP), |ui| {
@@ -735,7 +962,18 @@ fn draw_path(ui: &mut egui::Ui, path: usize, collection: usize) {
         EVENTS.lock().unwrap().push_back(CelEvent::DeletePath { path_id });
     }
 
-    UI_STATE.lock().unwrap().delete_mode = delete_mode;
+    let mut ui_state = UI_STATE.lock().unwrap();
+
+    ui_state.delete_mode = delete_mode;
+    if new_tag.is_empty() {
+        ui_state.new_tags.remove(&path_id);
+    }
+    else {
+        ui_state.new_tags.insert(path_id, new_tag);
+    }
+
+    drop(ui_state);
+
     EVENTS.lock().unwrap().append(&mut new_events);
 }
 
@@ -969,17 +1207,7 @@ fn draw_config_tab(ui: &mut egui::Ui) {
     let mut trigger_sizes = config.trigger_sizes;
 
     let direct_mode = config.direct_mode;
-    let mut start_keybind = config.start_keybind;
-    let mut stop_keybind = config.stop_keybind;
-    let mut reset_keybind = config.reset_keybind;
-    let mut clear_keybind = config.clear_keybind;
-    let mut teleport_keybinds = config.teleport_keybinds;
-    // let mut extra_teleport_modifiers = config.extra_teleport_modifiers;
-    // let mut spawn_teleport_modifiers = config.spawn_teleport_modifiers;
-    // let mut delete_teleport_modifiers = config.delete_teleport_modifiers;
-    let mut extra_teleport_keybinds = config.extra_teleport_keybinds;
-    let mut spawn_teleport_keybinds = config.spawn_teleport_keybinds;
-    let mut delete_teleport_keybinds = config.delete_teleport_keybinds;
+    let mut keybinds = config.keybinds.clone();
 
     let mut timer_size = config.timer_size;
     // let mut timer_position = config.timer_position;
@@ -995,6 +1223,35 @@ fn draw_config_tab(ui: &mut egui::Ui) {
     let mut select_color = config.select_color;
     let mut accent_colors = config.accent_colors;
 
+    let mut gradient_mode = config.gradient_mode;
+    let mut gradient_preset = config.gradient_preset;
+    let mut gradient_stops = config.gradient_stops.clone();
+    let mut gradient_cutoff = config.gradient_cutoff;
+
+    let mut frustum_culling = config.frustum_culling;
+    let mut cull_distance = config.cull_distance;
+    let mut lod_distance = config.lod_distance;
+
+    let mut sampling = config.sampling;
+
+    let mut simplify_tolerance = config.simplify_tolerance;
+    let mut simplify_on_save = config.simplify_on_save;
+    let mut display_tolerance = config.display_tolerance;
+    let mut compress_files = config.compress_files;
+    let mut backup_count = config.backup_count;
+    let mut runner_name = config.runner_name.clone();
+    let mut livesplit_enabled = config.livesplit_enabled;
+    let mut livesplit_host = config.livesplit_host.clone();
+    let mut livesplit_port = config.livesplit_port;
+    let mut api_enabled = config.api_enabled;
+    let mut api_port = config.api_port;
+    let mut overlay_enabled = config.overlay_enabled;
+    let mut overlay_port = config.overlay_port;
+    let mut overlay_top_n = config.overlay_top_n;
+    let mut config_issues = config.issues.clone();
+    let profile = config.profile.clone();
+    let profile_names = config.profile_names();
+
     // pub custom_shapes: bool,
 
     drop(config);
@@ -1003,11 +1260,86 @@ fn draw_config_tab(ui: &mut egui::Ui) {
 
     let screen_scale = ui_state.screen_scale;
     let mut new_exception = ui_state.new_exception;
+    let mut profile_edit = ui_state.profile_edit;
+    let mut profile_name = ui_state.profile_name.clone();
+    let mut profile_error = ui_state.profile_error.clone();
 
     drop(ui_state);
 
     let scale = screen_scale * zoom;
     let mut new_events : VecDeque<CelEvent> = VecDeque::new();
+    let capturing = CAPTURE.lock().unwrap().is_capturing();
+
+    ui.horizontal(|ui| {
+        ui.label(RichText::new("Profile").size(FONT_SIZE * scale));
+
+        if let Some(edit) = profile_edit {
+            let response = ui.add_sized(egui::vec2(160.0, 19.0) * scale, egui::TextEdit::singleline(&mut profile_name).char_limit(32));
+            if response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+                let name = profile_name.trim().to_string();
+                new_events.push_back(match edit {
+                    ProfileEdit::Create => CelEvent::CreateProfile { name },
+                    ProfileEdit::Duplicate => CelEvent::DuplicateProfile { name },
+                    ProfileEdit::Rename => CelEvent::RenameProfile { new_name: name },
+                });
+                profile_edit = None;
+            }
+            if ui.add(egui::Button::new(RichText::new("\u{1F5D9}").size(FONT_SIZE * scale)).frame(false)).clicked() {
+                profile_edit = None;
+            }
+            return;
+        }
+
+        egui::ComboBox::from_id_source("config_profile")
+            .selected_text(RichText::new(profile.clone()).size(FONT_SIZE * scale))
+            .show_ui(ui, |ui| {
+                for name in &profile_names {
+                    if ui.selectable_label(*name == profile, name).clicked() && *name != profile {
+                        new_events.push_back(CelEvent::SwitchProfile { name: name.clone() });
+                    }
+                }
+            })
+            .response
+            .on_hover_text("Switching saves the config, including unsaved changes to the current profile");
+
+        if ui.add(egui::Button::new(RichText::new("New").size(FONT_SIZE * scale))).clicked() {
+            profile_edit = Some(ProfileEdit::Create);
+            profile_name = "".to_string();
+            profile_error = None;
+        }
+        if ui.add(egui::Button::new(RichText::new("Duplicate").size(FONT_SIZE * scale))).clicked() {
+            profile_edit = Some(ProfileEdit::Duplicate);
+            profile_name = format!("{profile} copy");
+            profile_error = None;
+        }
+        if ui.add(egui::Button::new(RichText::new("Rename").size(FONT_SIZE * scale))).clicked() {
+            profile_edit = Some(ProfileEdit::Rename);
+            profile_name = profile.clone();
+            profile_error = None;
+        }
+    });
+
+    if let Some(error) = &profile_error {
+        ui.label(RichText::new(error).size(FONT_SIZE * scale).color(WARNING_COLOR));
+    }
+    else if profile_edit.is_some() {
+        ui.label(RichText::new("This saves the config, including unsaved changes to the current profile").size(FONT_SIZE * scale));
+    }
+
+    ui.separator();
+
+    if !config_issues.is_empty() {
+        ui.horizontal(|ui| {
+            ui.label(RichText::new(format!("Problems in {CONFIG_FILE_NAME}:")).size(FONT_SIZE * scale).color(WARNING_COLOR));
+            if ui.add(egui::Button::new(RichText::new("\u{1F5D9}").size(FONT_SIZE * scale)).frame(false)).clicked() {
+                config_issues.clear();
+            }
+        });
+        for issue in &config_issues {
+            ui.label(RichText::new(issue.to_string()).size(FONT_SIZE * scale));
+        }
+        ui.separator();
+    }
 
     let scroll_height = ui.available_height() - 30.0 * scale;
 
@@ -1041,109 +1373,193 @@ fn draw_config_tab(ui: &mut egui::Ui) {
 
                 ui.label(RichText::new("UI Scale").size(FONT_SIZE * scale));
                 ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
-                    ui.add(egui::DragValue::new(&mut zoom).speed(0.1).clamp_range(0.5..=8.0));
+                    ui.add(egui::DragValue::new(&mut zoom).speed(0.1).clamp_range(ZOOM_RANGE));
                 });
                 ui.end_row();
 
                 ui.label(RichText::new("Start Trigger Size").size(FONT_SIZE * scale));
                 ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
-                    ui.add(egui::DragValue::new(&mut trigger_sizes[0][2]).speed(0.1).clamp_range(0.1..=10.0));
-                    ui.add(egui::DragValue::new(&mut trigger_sizes[0][1]).speed(0.1).clamp_range(0.1..=10.0));
-                    ui.add(egui::DragValue::new(&mut trigger_sizes[0][0]).speed(0.1).clamp_range(0.1..=10.0));
+                    ui.add(egui::DragValue::new(&mut trigger_sizes[0][2]).speed(0.1).clamp_range(TRIGGER_SIZE_RANGE));
+                    ui.add(egui::DragValue::new(&mut trigger_sizes[0][1]).speed(0.1).clamp_range(TRIGGER_SIZE_RANGE));
+                    ui.add(egui::DragValue::new(&mut trigger_sizes[0][0]).speed(0.1).clamp_range(TRIGGER_SIZE_RANGE));
                 });
                 ui.end_row();
 
                 ui.label(RichText::new("End Trigger Size").size(FONT_SIZE * scale));
                 ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
-                    ui.add(egui::DragValue::new(&mut trigger_sizes[1][2]).speed(0.1).clamp_range(0.1..=10.0));
-                    ui.add(egui::DragValue::new(&mut trigger_sizes[1][1]).speed(0.1).clamp_range(0.1..=10.0));
-                    ui.add(egui::DragValue::new(&mut trigger_sizes[1][0]).speed(0.1).clamp_range(0.1..=10.0));
+                    ui.add(egui::DragValue::new(&mut trigger_sizes[1][2]).speed(0.1).clamp_range(TRIGGER_SIZE_RANGE));
+                    ui.add(egui::DragValue::new(&mut trigger_sizes[1][1]).speed(0.1).clamp_range(TRIGGER_SIZE_RANGE));
+                    ui.add(egui::DragValue::new(&mut trigger_sizes[1][0]).speed(0.1).clamp_range(TRIGGER_SIZE_RANGE));
+                });
+                ui.end_row();
+
+                ui.label(RichText::new("Runner Name").size(FONT_SIZE * scale));
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    ui.add(egui::TextEdit::singleline(&mut runner_name).desired_width(120.0 * scale));
+                });
+                ui.end_row();
+
+                ui.label(RichText::new("LiveSplit Server").size(FONT_SIZE * scale))
+                    .on_hover_text("Start, split, pause and reset LiveSplit together with the recording");
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    toggle_switch(ui, &mut livesplit_enabled, scale);
+                    ui.add(egui::DragValue::new(&mut livesplit_port).speed(1.0));
+                    ui.add(egui::TextEdit::singleline(&mut livesplit_host).desired_width(80.0 * scale));
+                });
+                ui.end_row();
+
+                ui.label(RichText::new("Local API").size(FONT_SIZE * scale))
+                    .on_hover_text("JSON-RPC on localhost for overlays and other tools");
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    toggle_switch(ui, &mut api_enabled, scale);
+                    ui.add(egui::DragValue::new(&mut api_port).speed(1.0));
+                });
+                ui.end_row();
+
+                ui.label(RichText::new("Stream Overlay").size(FONT_SIZE * scale))
+                    .on_hover_text(format!("Add http://localhost:{overlay_port}/ as a browser source in OBS"));
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    toggle_switch(ui, &mut overlay_enabled, scale);
+                    ui.add(egui::DragValue::new(&mut overlay_port).speed(1.0));
+                });
+                ui.end_row();
+
+                ui.label(RichText::new("Overlay Top Times").size(FONT_SIZE * scale));
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    ui.add(egui::DragValue::new(&mut overlay_top_n).speed(0.1).clamp_range(OVERLAY_TOP_N_RANGE));
+                });
+                ui.end_row();
+
+                ui.label(RichText::new("Session Capture").size(FONT_SIZE * scale)).on_hover_text("Records your inputs and movement for bug reports");
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    let label = if capturing { "Stop" } else { "Start" };
+                    if ui.add(egui::Button::new(RichText::new(label).size(FONT_SIZE * scale))).clicked() {
+                        new_events.push_back(if capturing { CelEvent::StopCapture } else { CelEvent::StartCapture });
+                    }
                 });
                 ui.end_row();
 
                 ui.label(RichText::new("Timer Size").size(FONT_SIZE * scale));
                 ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
-                    ui.add(egui::DragValue::new(&mut timer_size).speed(0.5).clamp_range(6.9..=69.0));
+                    ui.add(egui::DragValue::new(&mut timer_size).speed(0.5).clamp_range(TIMER_SIZE_RANGE));
                 });
                 ui.end_row();
-            });
 
-        ui.add_space(20.0 * scale);
-        ui.heading(RichText::new("Keybinds").size(FONT_SIZE * scale));
-        ui.separator();
+                ui.label(RichText::new("Hide Offscreen Paths").size(FONT_SIZE * scale));
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    toggle_switch(ui, &mut frustum_culling, scale);
+                });
+                ui.end_row();
 
-        egui::Grid::new("config_grid")
-            .num_columns(2)
-            .spacing(egui::vec2(40.0, 4.0) * scale)
-            .striped(true)
-            .show(ui, |ui| {
-                if direct_mode {
-                    ui.label(RichText::new("Start Recording").size(FONT_SIZE * scale));
-                }
-                else {
-                    ui.label(RichText::new("Spawn Start Trigger").size(FONT_SIZE * scale));
-                }
+                ui.label(RichText::new("Path Draw Distance (0 = unlimited)").size(FONT_SIZE * scale));
                 ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
-                    ui.add(Keybind::new(&mut start_keybind, "start_keybind"));
+                    ui.add(egui::DragValue::new(&mut cull_distance).speed(1.0).clamp_range(DISTANCE_RANGE));
                 });
                 ui.end_row();
 
-                if direct_mode {
-                    ui.label(RichText::new("Stop Recording").size(FONT_SIZE * scale));
-                }
-                else {
-                    ui.label(RichText::new("Spawn End Trigger").size(FONT_SIZE * scale));
-                }
+                ui.label(RichText::new("Path Detail Distance (0 = full detail)").size(FONT_SIZE * scale));
                 ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
-                    ui.add(Keybind::new(&mut stop_keybind, "stop_keybind"));
+                    ui.add(egui::DragValue::new(&mut lod_distance).speed(1.0).clamp_range(DISTANCE_RANGE));
                 });
                 ui.end_row();
 
-                ui.label(RichText::new("Reset Recording").size(FONT_SIZE * scale));
+                ui.label(RichText::new("Path Sampling").size(FONT_SIZE * scale));
                 ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
-                    ui.add(Keybind::new(&mut reset_keybind, "reset_keybind"));
+                    egui::ComboBox::new("sampling_mode_drop_down", "")
+                        .selected_text(RichText::new(format!("{:?}", sampling.mode)).size(FONT_SIZE * scale))
+                        .show_ui(ui, |ui| {
+                            for mode in SamplingMode::ALL {
+                                ui.selectable_value(&mut sampling.mode, mode, format!("{:?}", mode));
+                            }
+                        });
                 });
                 ui.end_row();
 
-                ui.label(RichText::new("Delete Triggers").size(FONT_SIZE * scale));
+                match sampling.mode {
+                    SamplingMode::EveryFrame => {}
+                    SamplingMode::Interval => {
+                        ui.label(RichText::new("Sampling Interval (ms)").size(FONT_SIZE * scale));
+                        ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                            ui.add(egui::DragValue::new(&mut sampling.interval).speed(0.5).clamp_range(SAMPLING_INTERVAL_RANGE));
+                        });
+                        ui.end_row();
+                    }
+                    SamplingMode::Distance | SamplingMode::Adaptive => {
+                        ui.label(RichText::new("Sampling Distance (m)").size(FONT_SIZE * scale));
+                        ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                            ui.add(egui::DragValue::new(&mut sampling.distance).speed(0.05).clamp_range(SAMPLING_DISTANCE_RANGE));
+                        });
+                        ui.end_row();
+                    }
+                }
+
+                if sampling.mode == SamplingMode::Adaptive {
+                    ui.label(RichText::new("Sampling Angle (°)").size(FONT_SIZE * scale));
+                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                        ui.add(egui::DragValue::new(&mut sampling.angle).speed(0.5).clamp_range(SAMPLING_ANGLE_RANGE));
+                    });
+                    ui.end_row();
+                }
+
+                ui.label(RichText::new("Compact Tolerance (m)").size(FONT_SIZE * scale));
                 ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
-                    ui.add(Keybind::new(&mut clear_keybind, "clear_keybind"));
+                    ui.add(egui::DragValue::new(&mut simplify_tolerance).speed(0.005).clamp_range(SIMPLIFY_TOLERANCE_RANGE));
                 });
                 ui.end_row();
 
-                ui.label(RichText::new("Teleport to Start & End Trigger").size(FONT_SIZE * scale));
+                ui.label(RichText::new("Compact on Save").size(FONT_SIZE * scale));
                 ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
-                    ui.add(Keybind::new(&mut teleport_keybinds[1], "teleport_end_keybind"));
-                    ui.add(Keybind::new(&mut teleport_keybinds[0], "teleport_start_keybind"));
+                    toggle_switch(ui, &mut simplify_on_save, scale);
                 });
                 ui.end_row();
 
-                ui.label(RichText::new("Teleport to Location 1 & 2").size(FONT_SIZE * scale));
+                ui.label(RichText::new("Display Simplification (m, 0 = off)").size(FONT_SIZE * scale));
                 ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
-                    ui.add(Keybind::new(&mut extra_teleport_keybinds[1], "teleport_2_keybind"));
-                    ui.add(Keybind::new(&mut extra_teleport_keybinds[0], "teleport_1_keybind"));
+                    ui.add(egui::DragValue::new(&mut display_tolerance).speed(0.005).clamp_range(DISPLAY_TOLERANCE_RANGE));
                 });
                 ui.end_row();
 
-                ui.label(RichText::new("Set Location 1 & 2").size(FONT_SIZE * scale));
+                ui.label(RichText::new("Compress Comparison Files").size(FONT_SIZE * scale));
                 ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
-                    ui.add(Keybind::new(&mut spawn_teleport_keybinds[1], "spawn_teleport_2_keybind"));
-                    ui.add(Keybind::new(&mut spawn_teleport_keybinds[0], "spawn_teleport_1_keybind"));
+                    toggle_switch(ui, &mut compress_files, scale);
                 });
                 ui.end_row();
 
-                ui.label(RichText::new("Delete Location 1 & 2").size(FONT_SIZE * scale));
+                ui.label(RichText::new("Backups per Comparison").size(FONT_SIZE * scale));
                 ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
-                    ui.add(Keybind::new(&mut delete_teleport_keybinds[1], "delete_teleport_2_keybind"));
-                    ui.add(Keybind::new(&mut delete_teleport_keybinds[0], "delete_teleport_1_keybind"));
+                    ui.add(egui::DragValue::new(&mut backup_count).speed(0.1).clamp_range(BACKUP_COUNT_RANGE));
                 });
                 ui.end_row();
+            });
 
-                // ui.label("Spawn Checkpoint");
-                // ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
-                //     ui.add(Keybind::new(&mut config.spawn_checkpoint_keybind, "spawn_checkpoint_keybind"));
-                // });
-                // ui.end_row();
+        ui.add_space(20.0 * scale);
+        ui.heading(RichText::new("Keybinds").size(FONT_SIZE * scale));
+        ui.separator();
+
+        egui::Grid::new("config_grid")
+            .num_columns(2)
+            .spacing(egui::vec2(40.0, 4.0) * scale)
+            .striped(true)
+            .show(ui, |ui| {
+                for action in Action::ALL {
+                    let conflicts = keybinds.conflicts(action);
+
+                    let mut label = RichText::new(action.label(direct_mode)).size(FONT_SIZE * scale);
+                    if !conflicts.is_empty() { label = label.color(WARNING_COLOR); }
+
+                    let response = ui.label(label);
+                    if !conflicts.is_empty() {
+                        let names : Vec<&str> = conflicts.iter().map(|other| other.label(direct_mode)).collect();
+                        response.on_hover_text(format!("Also bound to {}", names.join(", ")));
+                    }
+
+                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                        let mut shortcut = keybinds.get(action);
+                        ui.add(Keybind::new(&mut shortcut, action.name()));
+                        keybinds.set(action, shortcut);
+                    });
+                    ui.end_row();
+                }
             });
 
         ui.add_space(20.0);
@@ -1176,24 +1592,82 @@ fn draw_config_tab(ui: &mut egui::Ui) {
                 });
                 ui.end_row();
 
-                ui.label(RichText::new("Path Gradient: Fast").size(FONT_SIZE * scale));
+                ui.label(RichText::new("Path Gradient Mode").size(FONT_SIZE * scale));
                 ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
-                    let mut tmp_color = fast_color.as_hsva();
-                    if ui.color_edit_button_hsva(&mut tmp_color).changed() {
-                        fast_color = tmp_color.to_rgba_premultiplied();
-                    }
+                    egui::ComboBox::new("gradient_mode_drop_down", "")
+                        .selected_text(RichText::new(format!("{:?}", gradient_mode)).size(FONT_SIZE * scale))
+                        .show_ui(ui, |ui| {
+                            for mode in GradientMode::ALL {
+                                ui.selectable_value(&mut gradient_mode, mode, format!("{:?}", mode));
+                            }
+                        });
                 });
                 ui.end_row();
 
-                ui.label(RichText::new("Path Gradient: Slow").size(FONT_SIZE * scale));
+                if gradient_mode == GradientMode::Time {
+                    ui.label(RichText::new("Gradient Cutoff (s, 0 = slowest)").size(FONT_SIZE * scale));
+                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                        ui.add(egui::DragValue::new(&mut gradient_cutoff).speed(0.05).clamp_range(GRADIENT_CUTOFF_RANGE));
+                    });
+                    ui.end_row();
+                }
+
+                ui.label(RichText::new("Path Gradient Preset").size(FONT_SIZE * scale));
                 ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
-                    let mut tmp_color = slow_color.as_hsva();
-                    if ui.color_edit_button_hsva(&mut tmp_color).changed() {
-                        slow_color = tmp_color.to_rgba_premultiplied();
-                    }
+                    egui::ComboBox::new("gradient_preset_drop_down", "")
+                        .selected_text(RichText::new(format!("{:?}", gradient_preset)).size(FONT_SIZE * scale))
+                        .show_ui(ui, |ui| {
+                            for preset in GradientPreset::ALL {
+                                ui.selectable_value(&mut gradient_preset, preset, format!("{:?}", preset));
+                            }
+                        });
                 });
                 ui.end_row();
 
+                if gradient_preset == GradientPreset::Custom {
+                    ui.label(RichText::new("Path Gradient: Fast").size(FONT_SIZE * scale));
+                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                        let mut tmp_color = fast_color.as_hsva();
+                        if ui.color_edit_button_hsva(&mut tmp_color).changed() {
+                            fast_color = tmp_color.to_rgba_premultiplied();
+                        }
+                    });
+                    ui.end_row();
+
+                    let mut remove_stop = None;
+
+                    for (i, stop) in gradient_stops.iter_mut().enumerate() {
+                        ui.label(RichText::new(format!("Path Gradient: Stop {}", i + 1)).size(FONT_SIZE * scale));
+                        ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                            if ui.add(egui::Button::new(RichText::new("\u{1F5D9}").size(FONT_SIZE * scale)).min_size(egui::vec2(19.0, 19.0))).clicked() {
+                                remove_stop = Some(i);
+                            }
+                            let mut tmp_color = stop.as_hsva();
+                            if ui.color_edit_button_hsva(&mut tmp_color).changed() {
+                                *stop = tmp_color.to_rgba_premultiplied();
+                            }
+                        });
+                        ui.end_row();
+                    }
+
+                    if let Some(i) = remove_stop {
+                        gradient_stops.remove(i);
+                    }
+
+                    ui.label(RichText::new("Path Gradient: Slow").size(FONT_SIZE * scale));
+                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                        let mut tmp_color = slow_color.as_hsva();
+                        if ui.color_edit_button_hsva(&mut tmp_color).changed() {
+                            slow_color = tmp_color.to_rgba_premultiplied();
+                        }
+                        if ui.add(egui::Button::new(RichText::new("\u{2795}").size(FONT_SIZE * scale)).min_size(egui::vec2(19.0, 19.0))).clicked() {
+                            let last = *gradient_stops.last().unwrap_or(&fast_color);
+                            gradient_stops.push(rendering::sample_gradient(&[last, slow_color], 0.5));
+                        }
+                    });
+                    ui.end_row();
+                }
+
                 ui.label(RichText::new("Fastest Path").size(FONT_SIZE * scale));
                 ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
                     let mut tmp_color = gold_color.as_hsva();
@@ -1302,32 +1776,34 @@ fn draw_config_tab(ui: &mut egui::Ui) {
         }
     });
 
-    UI_STATE.lock().unwrap().new_exception = new_exception;
+    let mut ui_state = UI_STATE.lock().unwrap();
+
+    ui_state.new_exception = new_exception;
+    ui_state.profile_edit = profile_edit;
+    ui_state.profile_name = profile_name;
+    ui_state.profile_error = profile_error;
+
+    drop(ui_state);
 
     let mut config = CONFIG_STATE.lock().unwrap();
 
     config.autosave = autosave;
     config.autoreset = autoreset;
 
+    if config.trigger_colors != trigger_colors
+        || config.accent_colors != accent_colors
+        || config.frustum_culling != frustum_culling
+        || config.cull_distance != cull_distance
+        || config.lod_distance != lod_distance
+        || config.display_tolerance != display_tolerance
+    {
+        new_events.push_back(CelEvent::RenderUpdate { update: RenderUpdates::settings() });
+    }
+
     config.zoom = zoom;
     config.trigger_sizes = trigger_sizes;
 
-    config.start_keybind = start_keybind;
-    config.stop_keybind = stop_keybind;
-    config.reset_keybind = reset_keybind;
-    config.clear_keybind = clear_keybind;
-    config.teleport_keybinds = teleport_keybinds;
-    // config.spawn_teleport_keybinds = spawn_teleport_keybinds;
-
-    // if config.extra_teleport_modifiers != extra_teleport_modifiers {
-    //     config.extra_teleport_modifiers = extra_teleport_modifiers;
-    //     config.generate_extra_teleport_keybinds();
-    // }
-
-    // if config.spawn_teleport_modifiers != spawn_teleport_modifiers {
-    //     config.spawn_teleport_modifiers = spawn_teleport_modifiers;
-    //     config.generate_spawn_teleport_keybinds();
-    // }
+    config.keybinds = keybinds;
 
     config.timer_size = timer_size;
     // config.timer_position = timer_position;
@@ -1336,11 +1812,51 @@ fn draw_config_tab(ui: &mut egui::Ui) {
 
 	config.trigger_colors = trigger_colors;
 	// config.checkpoint_color = checkpoint_color;
+    config.accent_colors = accent_colors;
+
+    if config.gradient_mode != gradient_mode
+        || config.fast_color != fast_color
+        || config.slow_color != slow_color
+        || config.gold_color != gold_color
+        || config.select_color != select_color
+        || config.gradient_preset != gradient_preset
+        || config.gradient_stops != gradient_stops
+        || config.gradient_cutoff != gradient_cutoff
+    {
+        new_events.push_back(CelEvent::RenderUpdate { update: RenderUpdates::paths() });
+        new_events.push_back(CelEvent::RenderUpdate { update: RenderUpdates::settings() });
+    }
+
     config.fast_color = fast_color;
     config.slow_color = slow_color;
     config.gold_color = gold_color;
     config.select_color = select_color;
-    config.accent_colors = accent_colors;
+    config.gradient_mode = gradient_mode;
+    config.gradient_preset = gradient_preset;
+    config.gradient_stops = gradient_stops;
+    config.gradient_cutoff = gradient_cutoff;
+
+    config.frustum_culling = frustum_culling;
+    config.cull_distance = cull_distance;
+    config.lod_distance = lod_distance;
+
+    config.sampling = sampling;
+
+    config.simplify_tolerance = simplify_tolerance;
+    config.simplify_on_save = simplify_on_save;
+    config.display_tolerance = display_tolerance;
+    config.compress_files = compress_files;
+    config.backup_count = backup_count;
+    config.runner_name = runner_name;
+    config.livesplit_enabled = livesplit_enabled;
+    config.livesplit_host = livesplit_host;
+    config.livesplit_port = livesplit_port;
+    config.api_enabled = api_enabled;
+    config.api_port = api_port;
+    config.overlay_enabled = overlay_enabled;
+    config.overlay_port = overlay_port;
+    config.overlay_top_n = overlay_top_n;
+    if config_issues.is_empty() { config.issues.clear(); }
 
     drop(config);
 